@@ -1,8 +1,182 @@
+use crate::gettext::{gettext, gettextf};
 use crate::globals;
-use crate::util::error::AppError;
+use crate::util;
+use crate::util::error::{AppError, UiError};
 use serde::{Deserialize, Serialize};
 use std::ops::{Deref, DerefMut};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// What to do when a file received to the Downloads folder has the name of an existing file
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverwritePolicy {
+    /// Keep both files by appending a number to the name of the new one
+    #[default]
+    Rename,
+    Overwrite,
+    Ask,
+}
+
+/// How symbolic links inside of folders are added to an archive
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SymlinkPolicy {
+    /// Add the file or folder the link points to
+    #[default]
+    Follow,
+    /// Add the link itself, which only works if the receiver has the target as well
+    Store,
+}
+
+/// How much of a QR code can be damaged or covered and still be scanned
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum QrErrorCorrection {
+    /// About 7% of the code can be restored
+    Low,
+    /// About 15%
+    #[default]
+    Medium,
+    /// About 25%
+    Quartile,
+    /// About 30%, for printed codes
+    High,
+}
+
+/// How large the QR code next to a transmit code is shown
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum QrCodeSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl QrCodeSize {
+    /// Size of the QR code on screen
+    pub fn pixels(self) -> i32 {
+        match self {
+            QrCodeSize::Small => 128,
+            QrCodeSize::Medium => 192,
+            QrCodeSize::Large => 256,
+        }
+    }
+
+    /// Minimum size the QR code is rendered with, to stay sharp when scaled
+    pub fn render_dimensions(self) -> u32 {
+        match self {
+            QrCodeSize::Small => 600,
+            QrCodeSize::Medium => 800,
+            QrCodeSize::Large => 1000,
+        }
+    }
+}
+
+/// Whether the app is light or dark
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorScheme {
+    /// Follow the system style
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// What the text on the progress bar shows during a transfer
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProgressText {
+    /// The transferred size and the time remaining
+    #[default]
+    Both,
+    TimeRemaining,
+    /// Only the transferred size, which is known before there is an estimate
+    Bytes,
+}
+
+/// The kinds of notifications we send while the window is in the background
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationCategory {
+    ReadyToReceive,
+    Complete,
+    Error,
+}
+
+/// Which notifications are enabled. Missing fields are enabled, which matches older versions.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub ready_to_receive: bool,
+    pub complete: bool,
+    pub error: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            ready_to_receive: true,
+            complete: true,
+            error: true,
+        }
+    }
+}
+
+impl NotificationSettings {
+    pub fn enabled(&self, category: NotificationCategory) -> bool {
+        match category {
+            NotificationCategory::ReadyToReceive => self.ready_to_receive,
+            NotificationCategory::Complete => self.complete,
+            NotificationCategory::Error => self.error,
+        }
+    }
+}
+
+/// Sounds played for transfer events, each a file path or a name from the freedesktop sound theme
+/// like `complete`. Events without a sound are silent.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct NotificationSounds {
+    pub ready_to_receive: Option<String>,
+    pub complete: Option<String>,
+    pub error: Option<String>,
+}
+
+impl NotificationSounds {
+    pub fn sound(&self, category: NotificationCategory) -> Option<&str> {
+        match category {
+            NotificationCategory::ReadyToReceive => self.ready_to_receive.as_deref(),
+            NotificationCategory::Complete => self.complete.as_deref(),
+            NotificationCategory::Error => self.error.as_deref(),
+        }
+    }
+
+    pub fn set_sound(&mut self, category: NotificationCategory, sound: Option<String>) {
+        let entry = match category {
+            NotificationCategory::ReadyToReceive => &mut self.ready_to_receive,
+            NotificationCategory::Complete => &mut self.complete,
+            NotificationCategory::Error => &mut self.error,
+        };
+        *entry = sound;
+    }
+
+    /// Unsets sound files that were moved or deleted. Names from the sound theme are kept.
+    fn remove_missing_files(&mut self) {
+        for sound in [
+            &mut self.ready_to_receive,
+            &mut self.complete,
+            &mut self.error,
+        ] {
+            if let Some(path) = sound.as_deref().map(Path::new) {
+                if path.is_absolute() && !path.is_file() {
+                    log::warn!("Sound file '{}' not found", path.display());
+                    *sound = None;
+                }
+            }
+        }
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct WindowConfig {
@@ -23,17 +197,259 @@ impl Default for WindowConfig {
 pub struct Config {
     pub window: WindowConfig,
     pub welcome_window_shown: bool,
+    /// The app version the user was last shown, to present the changes after an update
+    pub last_seen_version: Option<String>,
 
     pub rendezvous_server_url: Option<String>,
     pub transit_server_url: Option<String>,
 
     pub code_length: Option<usize>,
+    /// Show a phonetic spelling of the code words below the code
+    pub spell_out_code: Option<bool>,
+    /// Show the code above the code entry with the channel number emphasized
+    pub highlight_code_number: Option<bool>,
+    /// Copied codes and links are removed from the clipboard after this many seconds, if still
+    /// there. They are kept if unset.
+    pub clear_clipboard_secs: Option<u32>,
+    pub qr_error_correction: Option<QrErrorCorrection>,
+    pub qr_code_size: Option<QrCodeSize>,
+
+    pub allow_send_folders: Option<bool>,
+    pub symlink_policy: Option<SymlinkPolicy>,
+    /// Add files and folders starting with a dot when sending folders
+    pub include_hidden_files: Option<bool>,
+    /// Send the file of a folder that contains nothing else, instead of an archive of the folder
+    pub send_single_file_directly: Option<bool>,
+    pub confirm_relayed_transfers: Option<bool>,
+    /// Never use a relay server, only transfer via a direct connection
+    pub direct_connections_only: Option<bool>,
+    /// Log the relay hints, offered connection types and chosen connection of every transfer
+    pub debug_transit: Option<bool>,
+    pub save_as_by_default: Option<bool>,
+    /// Show the QR code scanner when switching to the receive tab
+    pub open_camera_on_receive: Option<bool>,
+    pub speed_in_bits: Option<bool>,
+    pub progress_text: Option<ProgressText>,
+    pub reduce_progress_updates: Option<bool>,
+    pub return_home_after_completion: Option<bool>,
+    /// Close the window after this many seconds without input and without a transfer, which quits
+    /// the app for the last window. Never closed if unset.
+    pub idle_quit_secs: Option<u64>,
+    pub color_scheme: Option<ColorScheme>,
+    /// Stronger contrast on the progress and code screens, without the seasonal progress colors
+    pub high_contrast: Option<bool>,
+    /// Ask before canceling a running transfer, from the cancel button or by closing the window
+    pub confirm_cancel: Option<bool>,
+    pub notifications: Option<NotificationSettings>,
+    pub notification_sounds: Option<NotificationSounds>,
+    /// Files larger than this many bytes are rejected without asking. No limit if unset.
+    pub max_receive_size: Option<u64>,
+    pub overwrite_policy: Option<OverwritePolicy>,
+    /// The folder of the last “Save As” selection, to start there for the next transfer
+    pub last_save_as_dir: Option<PathBuf>,
+    /// The folder of the last file or folder selected for sending, to start there next time
+    pub last_send_dir: Option<PathBuf>,
+    /// The name of the camera last picked for scanning QR codes, to select it again if connected
+    pub last_camera: Option<String>,
+
+    /// Overrides the wormhole app id for interoperability testing. Only honored in debug builds.
+    pub debug_app_id: Option<String>,
+}
+
+/// Fields that describe the state of this installation instead of preferences. They are never
+/// exported or replaced by imported settings.
+const STATE_FIELDS: &[&str] = &[
+    "welcome_window_shown",
+    "last_seen_version",
+    "last_save_as_dir",
+    "last_send_dir",
+    "last_camera",
+    "debug_app_id",
+];
+
+/// The window size is only exported on request, as it depends on the screen
+const WINDOW_FIELD: &str = "window";
+
+impl Config {
+    /// Serializes the preferences to share them with another installation
+    pub fn export_settings(&self, include_window_size: bool) -> Result<String, AppError> {
+        let mut value = serde_json::to_value(self).map_err(settings_error)?;
+        if let Some(fields) = value.as_object_mut() {
+            fields.retain(|field, _| {
+                !STATE_FIELDS.contains(&field.as_str())
+                    && (include_window_size || field != WINDOW_FIELD)
+            });
+        }
+
+        serde_json::to_string_pretty(&value).map_err(settings_error)
+    }
+
+    /// Applies exported settings on top of this config. Returns the new config and the names of
+    /// the changed fields, or an error if the settings are invalid.
+    pub fn import_settings(&self, json: &str) -> Result<(Config, Vec<String>), AppError> {
+        let imported: serde_json::Value = serde_json::from_str(json).map_err(settings_error)?;
+        let Some(imported) = imported.as_object() else {
+            return Err(UiError::new(&gettext("The file does not contain Warp settings")).into());
+        };
+
+        let mut value = serde_json::to_value(self).map_err(settings_error)?;
+        let mut changed = Vec::new();
+        if let Some(fields) = value.as_object_mut() {
+            for (field, imported_value) in imported {
+                if STATE_FIELDS.contains(&field.as_str()) {
+                    continue;
+                }
+
+                match fields.get_mut(field) {
+                    Some(value) if value != imported_value => {
+                        *value = imported_value.clone();
+                        changed.push(field.clone());
+                    }
+                    Some(_) => {}
+                    None => log::warn!("Ignoring unknown setting '{field}'"),
+                }
+            }
+        }
+
+        let config: Config = serde_json::from_value(value).map_err(settings_error)?;
+        config.validate()?;
+
+        Ok((config, changed))
+    }
+
+    /// Checks values that can't be entered in preferences, but could come from a file
+    fn validate(&self) -> Result<(), AppError> {
+        if let Some(url) = &self.rendezvous_server_url {
+            if util::validate_rendezvous_url(url).is_none() {
+                return Err(
+                    UiError::new(&gettextf("Invalid rendezvous server URL “{}”", &[url])).into(),
+                );
+            }
+        }
+
+        if let Some(urls) = &self.transit_server_url {
+            for (url, hint) in parse_transit_relay_hints(urls) {
+                if hint.is_err() {
+                    return Err(UiError::new(&gettextf(
+                        "Invalid transit server URL “{}”",
+                        &[&url],
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        if let Some(code_length) = self.code_length {
+            let valid = i32::try_from(code_length).is_ok_and(|length| {
+                (globals::CODE_LENGTH_MIN..=globals::CODE_LENGTH_MAX).contains(&length)
+            });
+            if !valid {
+                return Err(UiError::new(&gettextf(
+                    "Invalid code word count {0}, it must be between {1} and {2}",
+                    &[
+                        &code_length,
+                        &globals::CODE_LENGTH_MIN,
+                        &globals::CODE_LENGTH_MAX,
+                    ],
+                ))
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn settings_error(err: serde_json::Error) -> AppError {
+    UiError::new(&gettextf("Invalid settings file: {}", &[&err])).into()
+}
+
+/// Values set via environment variables. They take precedence over the config file but are never persisted.
+#[derive(Clone, Default, Debug)]
+pub struct EnvOverrides {
+    pub rendezvous_server_url: Option<String>,
+    pub transit_server_url: Option<String>,
+    pub download_dir: Option<PathBuf>,
+    /// Don't show the welcome dialog on the first start, for managed installations
+    pub skip_welcome: bool,
+}
+
+impl EnvOverrides {
+    pub fn from_env() -> Self {
+        let rendezvous_server_url = Self::var(globals::ENV_RENDEZVOUS_URL).filter(|url| {
+            let is_valid = util::validate_rendezvous_url(url).is_some();
+            if !is_valid {
+                log::warn!(
+                    "Ignoring invalid URL in {}: '{}'",
+                    globals::ENV_RENDEZVOUS_URL,
+                    url
+                );
+            }
+            is_valid
+        });
+
+        let transit_server_url = Self::var(globals::ENV_TRANSIT_URL).filter(|urls| {
+            let hints = parse_transit_relay_hints(urls);
+            let is_valid = !hints.is_empty() && hints.iter().all(|(_, hint)| hint.is_ok());
+            if !is_valid {
+                log::warn!(
+                    "Ignoring invalid transit URL in {}: '{}'",
+                    globals::ENV_TRANSIT_URL,
+                    urls
+                );
+            }
+            is_valid
+        });
+
+        let download_dir = Self::var(globals::ENV_DOWNLOAD_DIR)
+            .map(PathBuf::from)
+            .filter(|dir| {
+                let is_valid = dir.is_absolute();
+                if !is_valid {
+                    log::warn!(
+                        "Ignoring relative path in {}: '{}'",
+                        globals::ENV_DOWNLOAD_DIR,
+                        dir.display()
+                    );
+                }
+                is_valid
+            });
+
+        let skip_welcome = Self::var(globals::ENV_SKIP_WELCOME).is_some_and(|value| value != "0");
+
+        let overrides = Self {
+            rendezvous_server_url,
+            transit_server_url,
+            download_dir,
+            skip_welcome,
+        };
+
+        if let Some(url) = &overrides.rendezvous_server_url {
+            log::info!("Using rendezvous server URL from environment: '{url}'");
+        }
+        if let Some(url) = &overrides.transit_server_url {
+            log::info!("Using transit server URL from environment: '{url}'");
+        }
+        if let Some(dir) = &overrides.download_dir {
+            log::info!(
+                "Using download directory from environment: '{}'",
+                dir.display()
+            );
+        }
+
+        overrides
+    }
+
+    fn var(name: &str) -> Option<String> {
+        std::env::var(name).ok().filter(|value| !value.is_empty())
+    }
 }
 
 #[derive(Clone, Default, Debug)]
 pub struct PersistentConfig {
     pub config: Config,
     pub persisted_config: Config,
+    pub env: EnvOverrides,
 }
 
 impl Deref for PersistentConfig {
@@ -52,7 +468,56 @@ impl DerefMut for PersistentConfig {
 
 impl PersistentConfig {
     pub fn from_file() -> Result<Self, std::io::Error> {
-        let path = Self::path();
+        let mut config = Self::load_file(&Self::path())?;
+        if let Some(sounds) = &mut config.config.notification_sounds {
+            sounds.remove_missing_files();
+        }
+        config.env = EnvOverrides::from_env();
+        if config.env.skip_welcome {
+            config.skip_welcome();
+        }
+
+        Ok(config)
+    }
+
+    /// Like `from_file`, but a config file that isn't valid JSON is renamed to `config.json.bak`.
+    /// The error is still returned, and the next save writes a clean file.
+    pub fn from_file_or_back_up() -> Result<Self, std::io::Error> {
+        Self::from_file().inspect_err(|err| Self::back_up_corrupt_file(&Self::path(), err))
+    }
+
+    fn back_up_corrupt_file(path: &Path, err: &std::io::Error) {
+        // Syntax and data errors of the JSON parser, not errors reading the file
+        if !matches!(
+            err.kind(),
+            std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof
+        ) {
+            return;
+        }
+
+        let mut backup_path = path.as_os_str().to_owned();
+        backup_path.push(".bak");
+        let backup_path = PathBuf::from(backup_path);
+
+        log::warn!("Moving corrupt config file to '{}'", backup_path.display());
+        if let Err(err) = std::fs::rename(path, &backup_path) {
+            log::error!("Error moving corrupt config file: {}", err);
+        }
+    }
+
+    /// Marks the welcome dialog as shown. The persisted state is changed as well, so this is only
+    /// written to disk if the config is saved for another reason.
+    fn skip_welcome(&mut self) {
+        for config in [&mut self.config, &mut self.persisted_config] {
+            if !config.welcome_window_shown {
+                config.welcome_window_shown = true;
+                // A new install doesn't need to be told what changed
+                config.last_seen_version = Some(globals::VERSION.to_owned());
+            }
+        }
+    }
+
+    fn load_file(path: &Path) -> Result<Self, std::io::Error> {
         log::info!("Loading config file: '{}'", path.display());
 
         let file = std::fs::File::open(path);
@@ -70,6 +535,7 @@ impl PersistentConfig {
         Ok(Self {
             config: cfg.clone(),
             persisted_config: cfg,
+            env: EnvOverrides::default(),
         })
     }
 
@@ -106,29 +572,188 @@ impl PersistentConfig {
         path
     }
 
+    /// Optional stylesheet to override the bundled styles. This is unsupported and may break.
+    pub fn user_css_path() -> PathBuf {
+        let mut path = Self::dir();
+        path.push("style.css");
+        path
+    }
+
     pub fn rendezvous_server_url(&self) -> Result<url::Url, url::ParseError> {
-        if let Some(url) = &self.rendezvous_server_url {
+        if let Some(url) = self
+            .env
+            .rendezvous_server_url
+            .as_ref()
+            .or(self.rendezvous_server_url.as_ref())
+        {
             url.parse()
         } else {
             Ok(globals::WORMHOLE_DEFAULT_RENDEZVOUS_SERVER.clone())
         }
     }
 
+    pub fn has_custom_rendezvous_server_url(&self) -> bool {
+        self.env.rendezvous_server_url.is_some() || self.rendezvous_server_url.is_some()
+    }
+
+    pub fn download_dir_override(&self) -> Option<&Path> {
+        self.env.download_dir.as_deref()
+    }
+
     pub fn transit_relay_hints(&self) -> Result<Vec<wormhole::transit::RelayHint>, AppError> {
-        if let Some(url) = &self.transit_server_url {
-            Ok(vec![wormhole::transit::RelayHint::from_urls(
-                None,
-                [url.parse()?],
-            )?])
+        if let Some(urls) = self
+            .env
+            .transit_server_url
+            .as_ref()
+            .or(self.transit_server_url.as_ref())
+        {
+            let hints = parse_transit_relay_hints(urls)
+                .into_iter()
+                .map(|(_, hint)| hint)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if hints.is_empty() {
+                Ok(globals::WORMHOLE_DEFAULT_TRANSIT_RELAY_HINTS.clone())
+            } else {
+                Ok(hints)
+            }
         } else {
             Ok(globals::WORMHOLE_DEFAULT_TRANSIT_RELAY_HINTS.clone())
         }
     }
 
+    /// Ports of the transit relays in use, to point out which ones a firewall might block
+    pub fn transit_relay_ports(&self) -> Vec<u16> {
+        let urls = self
+            .env
+            .transit_server_url
+            .as_deref()
+            .or(self.transit_server_url.as_deref())
+            .filter(|urls| !parse_transit_relay_hints(urls).is_empty())
+            .unwrap_or(globals::WORMHOLE_DEFAULT_TRANSIT_RELAY_URL_STR);
+
+        let mut ports: Vec<u16> = parse_transit_relay_hints(urls)
+            .into_iter()
+            .filter_map(|(url, _)| util::validate_transit_url(url)?.port())
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+        ports
+    }
+
     pub fn code_length_or_default(&self) -> usize {
         self.code_length.unwrap_or(4)
     }
 
+    pub fn spell_out_code_or_default(&self) -> bool {
+        self.spell_out_code.unwrap_or(false)
+    }
+
+    pub fn highlight_code_number_or_default(&self) -> bool {
+        self.highlight_code_number.unwrap_or(false)
+    }
+
+    pub fn qr_error_correction_or_default(&self) -> QrErrorCorrection {
+        self.qr_error_correction.unwrap_or_default()
+    }
+
+    pub fn qr_code_size_or_default(&self) -> QrCodeSize {
+        self.qr_code_size.unwrap_or_default()
+    }
+
+    pub fn allow_send_folders_or_default(&self) -> bool {
+        self.allow_send_folders.unwrap_or(true)
+    }
+
+    pub fn include_hidden_files_or_default(&self) -> bool {
+        self.include_hidden_files.unwrap_or(true)
+    }
+
+    pub fn send_single_file_directly_or_default(&self) -> bool {
+        self.send_single_file_directly.unwrap_or(false)
+    }
+
+    pub fn confirm_relayed_transfers_or_default(&self) -> bool {
+        self.confirm_relayed_transfers.unwrap_or(false)
+    }
+
+    pub fn direct_connections_only_or_default(&self) -> bool {
+        self.direct_connections_only.unwrap_or(false)
+    }
+
+    pub fn debug_transit_or_default(&self) -> bool {
+        self.debug_transit.unwrap_or(false)
+    }
+
+    /// The ways we offer to connect to the peer for the file transfer
+    pub fn transit_abilities(&self) -> wormhole::transit::Abilities {
+        if self.direct_connections_only_or_default() {
+            wormhole::transit::Abilities::FORCE_DIRECT
+        } else {
+            wormhole::transit::Abilities::ALL
+        }
+    }
+
+    pub fn save_as_by_default_or_default(&self) -> bool {
+        self.save_as_by_default.unwrap_or(false)
+    }
+
+    pub fn open_camera_on_receive_or_default(&self) -> bool {
+        self.open_camera_on_receive.unwrap_or(false)
+    }
+
+    pub fn speed_in_bits_or_default(&self) -> bool {
+        self.speed_in_bits.unwrap_or(false)
+    }
+
+    pub fn progress_text_or_default(&self) -> ProgressText {
+        self.progress_text.unwrap_or_default()
+    }
+
+    pub fn reduce_progress_updates_or_default(&self) -> bool {
+        self.reduce_progress_updates.unwrap_or(false)
+    }
+
+    pub fn return_home_after_completion_or_default(&self) -> bool {
+        self.return_home_after_completion.unwrap_or(false)
+    }
+
+    pub fn color_scheme_or_default(&self) -> ColorScheme {
+        self.color_scheme.unwrap_or_default()
+    }
+
+    pub fn high_contrast_or_default(&self) -> bool {
+        self.high_contrast.unwrap_or(false)
+    }
+
+    pub fn confirm_cancel_or_default(&self) -> bool {
+        self.confirm_cancel.unwrap_or(true)
+    }
+
+    pub fn notifications_or_default(&self) -> NotificationSettings {
+        self.notifications.unwrap_or_default()
+    }
+
+    pub fn notification_sounds_or_default(&self) -> NotificationSounds {
+        self.notification_sounds.clone().unwrap_or_default()
+    }
+
+    pub fn symlink_policy_or_default(&self) -> SymlinkPolicy {
+        self.symlink_policy.unwrap_or_default()
+    }
+
+    /// Which entries of folders are added to archives
+    pub fn zip_options(&self) -> util::zip::ZipOptions {
+        util::zip::ZipOptions {
+            symlink_policy: self.symlink_policy_or_default(),
+            include_hidden: self.include_hidden_files_or_default(),
+        }
+    }
+
+    pub fn overwrite_policy_or_default(&self) -> OverwritePolicy {
+        self.overwrite_policy.unwrap_or_default()
+    }
+
     pub fn app_cfg(&self) -> wormhole::AppConfig<wormhole::transfer::AppVersion> {
         let mut rendezvous_url = self
             .rendezvous_server_url()
@@ -139,9 +764,262 @@ impl PersistentConfig {
         log::debug!("Creating AppConfig with server url '{}'", rendezvous_url);
 
         wormhole::AppConfig {
-            id: wormhole::AppID::new(globals::WORMHOLE_DEFAULT_APPID_STR),
+            id: self.app_id(),
             rendezvous_url: rendezvous_url.to_string().into(),
             app_version: wormhole::transfer::AppVersion::default(),
         }
     }
+
+    fn app_id(&self) -> wormhole::AppID {
+        match &self.debug_app_id {
+            Some(app_id) if globals::DEBUG_BUILD => {
+                log::warn!("Using app id override '{}'", app_id);
+                wormhole::AppID::new(app_id.clone())
+            }
+            _ => wormhole::AppID::new(globals::WORMHOLE_DEFAULT_APPID_STR),
+        }
+    }
+}
+
+/// Parses a comma or newline separated list of transit relay URLs. Every entry is validated on its
+/// own and returned alongside its parse result, so invalid entries can be pointed out individually.
+pub fn parse_transit_relay_hints(
+    urls: &str,
+) -> Vec<(&str, Result<wormhole::transit::RelayHint, AppError>)> {
+    urls.split([',', '\n'])
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(|url| {
+            let hint = util::validate_transit_url(url)
+                .ok_or_else(|| {
+                    AppError::from(UiError::new(&gettextf(
+                        "Invalid transit server URL “{}”",
+                        &[&url],
+                    )))
+                })
+                .and_then(|url| Ok(wormhole::transit::RelayHint::from_urls(None, [url])?));
+            (url, hint)
+        })
+        .collect()
+}
+
+/// Whether the entered rendezvous server URL is empty or the same as the app default
+pub fn is_default_rendezvous_url(url: &str) -> bool {
+    let url = url.trim();
+    url.is_empty()
+        || util::validate_rendezvous_url(url).as_ref()
+            == Some(&*globals::WORMHOLE_DEFAULT_RENDEZVOUS_SERVER)
+}
+
+/// Whether the entered transit server URLs are empty or only the app default
+pub fn is_default_transit_urls(urls: &str) -> bool {
+    let default_url = util::validate_transit_url(globals::WORMHOLE_DEFAULT_TRANSIT_RELAY_URL_STR);
+    parse_transit_relay_hints(urls)
+        .iter()
+        .all(|(url, _)| util::validate_transit_url(url) == default_url)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_transit_relay_hints() {
+        assert!(parse_transit_relay_hints("").is_empty());
+        assert!(parse_transit_relay_hints(" , \n").is_empty());
+
+        let hints = parse_transit_relay_hints(
+            "tcp://transit.magic-wormhole.io:4001,\n tcp://relay.example.org:4001 ",
+        );
+        assert_eq!(hints.len(), 2);
+        assert_eq!(hints[0].0, "tcp://transit.magic-wormhole.io:4001");
+        assert_eq!(hints[1].0, "tcp://relay.example.org:4001");
+        assert!(hints.iter().all(|(_, hint)| hint.is_ok()));
+
+        let hints = parse_transit_relay_hints(
+            "tcp://transit.magic-wormhole.io:4001, not a url\nftp://relay.example.org:21",
+        );
+        assert_eq!(hints.len(), 3);
+        assert!(hints[0].1.is_ok());
+        assert_eq!(hints[1].0, "not a url");
+        assert!(hints[1].1.is_err());
+        assert!(hints[2].1.is_err());
+    }
+
+    #[test]
+    fn test_is_default_server_url() {
+        assert!(is_default_rendezvous_url(""));
+        assert!(is_default_rendezvous_url(
+            globals::WORMHOLE_DEFAULT_RENDEZVOUS_SERVER_STR
+        ));
+        assert!(!is_default_rendezvous_url("ws://relay.example.org:4000"));
+        assert!(!is_default_rendezvous_url("not a url"));
+
+        assert!(is_default_transit_urls(""));
+        assert!(is_default_transit_urls(&format!(
+            " {} ",
+            globals::WORMHOLE_DEFAULT_TRANSIT_RELAY_URL_STR
+        )));
+        assert!(!is_default_transit_urls(&format!(
+            "{},tcp://relay.example.org:4001",
+            globals::WORMHOLE_DEFAULT_TRANSIT_RELAY_URL_STR
+        )));
+        assert!(!is_default_transit_urls("invalid"));
+    }
+
+    #[test]
+    fn test_transit_relay_hints_invalid_entry() {
+        let mut config = PersistentConfig::default();
+        config.transit_server_url =
+            Some("tcp://transit.magic-wormhole.io:4001,tcp://relay.example.org:4001".to_owned());
+        assert_eq!(config.transit_relay_hints().unwrap().len(), 2);
+
+        config.transit_server_url = Some("tcp://transit.magic-wormhole.io:4001,invalid".to_owned());
+        assert!(config.transit_relay_hints().is_err());
+    }
+
+    #[test]
+    fn test_transit_relay_ports() {
+        let mut config = PersistentConfig::default();
+        assert_eq!(config.transit_relay_ports(), [4001]);
+
+        config.transit_server_url = Some(
+            "tls://relay.example.org:443\ntcp://relay.example.net:4001,tcp://other.example.org:443"
+                .to_owned(),
+        );
+        assert_eq!(config.transit_relay_ports(), [443, 4001]);
+
+        config.transit_server_url = Some(" ".to_owned());
+        assert_eq!(config.transit_relay_ports(), [4001]);
+    }
+
+    #[test]
+    fn test_skip_welcome() {
+        let mut config = PersistentConfig::default();
+        config.skip_welcome();
+        assert!(config.welcome_window_shown);
+        assert_eq!(config.last_seen_version.as_deref(), Some(globals::VERSION));
+        // Nothing to save because of this
+        assert_eq!(config.config, config.persisted_config);
+
+        let mut config = PersistentConfig::default();
+        config.config.welcome_window_shown = true;
+        config.persisted_config.welcome_window_shown = true;
+        config.skip_welcome();
+        assert_eq!(config.last_seen_version, None);
+    }
+
+    #[test]
+    fn test_back_up_corrupt_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "{\"code_length\": 4,").unwrap();
+
+        let err = PersistentConfig::load_file(&path).unwrap_err();
+        PersistentConfig::back_up_corrupt_file(&path, &err);
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("config.json.bak")).unwrap(),
+            "{\"code_length\": 4,"
+        );
+
+        // Starts over with the defaults
+        let config = PersistentConfig::load_file(&path).unwrap();
+        assert_eq!(config.config, PersistentConfig::default().config);
+
+        // A file that can't be read is left alone
+        std::fs::create_dir(&path).unwrap();
+        let err = PersistentConfig::load_file(&path).unwrap_err();
+        PersistentConfig::back_up_corrupt_file(&path, &err);
+        assert!(path.is_dir());
+    }
+
+    #[test]
+    fn test_notification_settings() {
+        let config = PersistentConfig::default();
+        let notifications = config.notifications_or_default();
+        assert!(notifications.enabled(NotificationCategory::ReadyToReceive));
+        assert!(notifications.enabled(NotificationCategory::Complete));
+        assert!(notifications.enabled(NotificationCategory::Error));
+
+        let notifications: NotificationSettings =
+            serde_json::from_str(r#"{"ready_to_receive": false}"#).unwrap();
+        assert!(!notifications.enabled(NotificationCategory::ReadyToReceive));
+        assert!(notifications.enabled(NotificationCategory::Complete));
+    }
+
+    #[test]
+    fn test_notification_sounds_remove_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("done.oga");
+        std::fs::write(&existing, b"").unwrap();
+
+        let mut sounds = NotificationSounds {
+            ready_to_receive: Some("bell".to_owned()),
+            complete: Some(existing.display().to_string()),
+            error: Some(dir.path().join("missing.oga").display().to_string()),
+        };
+        sounds.remove_missing_files();
+
+        assert_eq!(
+            sounds.sound(NotificationCategory::ReadyToReceive),
+            Some("bell")
+        );
+        assert_eq!(
+            sounds.sound(NotificationCategory::Complete),
+            Some(existing.to_str().unwrap())
+        );
+        assert_eq!(sounds.sound(NotificationCategory::Error), None);
+    }
+
+    #[test]
+    fn test_export_settings() {
+        let config = Config {
+            welcome_window_shown: true,
+            code_length: Some(5),
+            ..Default::default()
+        };
+
+        let json = config.export_settings(false).unwrap();
+        assert!(json.contains("code_length"));
+        assert!(!json.contains("welcome_window_shown"));
+        assert!(!json.contains("window"));
+
+        let json = config.export_settings(true).unwrap();
+        assert!(json.contains("\"window\""));
+    }
+
+    #[test]
+    fn test_import_settings() {
+        let config = Config {
+            welcome_window_shown: true,
+            ..Default::default()
+        };
+        let exported = Config {
+            code_length: Some(6),
+            speed_in_bits: Some(true),
+            ..Default::default()
+        }
+        .export_settings(false)
+        .unwrap();
+
+        let (imported, changed) = config.import_settings(&exported).unwrap();
+        assert_eq!(imported.code_length, Some(6));
+        assert_eq!(imported.speed_in_bits, Some(true));
+        assert!(imported.welcome_window_shown);
+        assert_eq!(changed, vec!["code_length", "speed_in_bits"]);
+
+        assert!(config.import_settings("[]").is_err());
+        assert!(config.import_settings(r#"{"code_length": 20}"#).is_err());
+        assert!(
+            config
+                .import_settings(r#"{"rendezvous_server_url": "http://example.org"}"#)
+                .is_err()
+        );
+        assert!(
+            config
+                .import_settings(r#"{"transit_server_url": "tcp://example.org"}"#)
+                .is_err()
+        );
+    }
 }
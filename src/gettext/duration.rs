@@ -2,17 +2,29 @@ use super::*;
 
 /// From <https://gitlab.gnome.org/World/pika-backup/-/blob/main/src/ui/utils/duration.rs>
 pub fn left(done_bytes: usize, total_bytes: usize, d: &chrono::Duration) -> String {
+    pgettextf(
+        "Combine bytes progress {0} and time remaining {1}",
+        // Translators: {0} = 11.3MB / 20.7MB, {1} = 3 seconds left
+        "{0} — {1}",
+        &[&transferred(done_bytes, total_bytes), &time_remaining(d)],
+    )
+}
+
+/// The transferred and the total size, like `17.3 MB / 20.5 MB`
+pub fn transferred(done_bytes: usize, total_bytes: usize) -> String {
     let bytes_str = glib::format_size(done_bytes as u64);
     let total_str = glib::format_size(total_bytes as u64);
 
-    let progress = pgettextf(
+    pgettextf(
         "File size transferred",
         // Translators: {0} = file size transferred, {1} = total file size, Example: 17.3MB / 20.5MB
         "{0} / {1}",
         &[&bytes_str, &total_str],
-    );
+    )
+}
 
-    let time_remaining = if d.num_minutes() < 2 {
+pub fn time_remaining(d: &chrono::Duration) -> String {
+    if d.num_minutes() < 2 {
         ngettextf(
             // Translators: File transfer time left
             "One second left",
@@ -44,12 +56,35 @@ pub fn left(done_bytes: usize, total_bytes: usize, d: &chrono::Duration) -> Stri
             (d.num_days() + 1) as u32,
             &[&(d.num_days() + 1).to_string()],
         )
-    };
+    }
+}
 
-    pgettextf(
-        "Combine bytes progress {0} and time remaining {1}",
-        // Translators: {0} = 11.3MB / 20.7MB, {1} = 3 seconds left
-        "{0} — {1}",
-        &[&progress, &time_remaining],
-    )
+/// How long a finished transfer took, like `3 minutes`
+pub fn elapsed(d: &chrono::Duration) -> String {
+    if d.num_minutes() < 2 {
+        let seconds = d.num_seconds().max(1);
+        ngettextf(
+            // Translators: Duration of a finished transfer
+            "One second",
+            "{} seconds",
+            seconds as u32,
+            &[&seconds.to_string()],
+        )
+    } else if d.num_hours() < 2 {
+        ngettextf(
+            // Translators: Duration of a finished transfer
+            "One minute",
+            "{} minutes",
+            d.num_minutes() as u32,
+            &[&d.num_minutes().to_string()],
+        )
+    } else {
+        ngettextf(
+            // Translators: Duration of a finished transfer
+            "One hour",
+            "{} hours",
+            d.num_hours() as u32,
+            &[&d.num_hours().to_string()],
+        )
+    }
 }
@@ -21,10 +21,27 @@ pub static WORMHOLE_DEFAULT_TRANSIT_RELAY_HINTS: LazyLock<Vec<wormhole::transit:
 
 pub const WORMHOLE_DEFAULT_APPID_STR: &str = "lothar.com/wormhole/text-or-file-xfer";
 
+/// Environment variables that override the values from the config file
+pub const ENV_RENDEZVOUS_URL: &str = "WARP_RENDEZVOUS_URL";
+pub const ENV_TRANSIT_URL: &str = "WARP_TRANSIT_URL";
+pub const ENV_DOWNLOAD_DIR: &str = "WARP_DOWNLOAD_DIR";
+pub const ENV_SKIP_WELCOME: &str = "WARP_SKIP_WELCOME";
+
+/// Range of the number of words in a transmit code
+pub const CODE_LENGTH_MIN: i32 = 2;
+pub const CODE_LENGTH_MAX: i32 = 8;
+
+/// Maximum length of the note that can be attached to a sent file
+pub const NOTE_MAX_CHARS: usize = 80;
+
 pub static TRANSMIT_URI_FIND_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"wormhole-transfer:\d+-\S+").unwrap());
+/// Finds a code in pasted text. The number has to start a word, so `v2-beta` is not a code.
 pub static TRANSMIT_CODE_FIND_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"\d+-\S+").unwrap());
+    LazyLock::new(|| Regex::new(r"\b\d+-\S+").unwrap());
+/// Spaces around the dashes of a code, like in `7 - guitarist - revenge`
+pub static TRANSMIT_CODE_DASH_SPACING_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[ \t]*-[ \t]*").unwrap());
 pub static TRANSMIT_CODE_MATCH_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^\d+-\S+$").unwrap());
 
@@ -134,5 +134,6 @@ fn main() {
     setup_gresources();
 
     let app = WarpApplication::new();
+    app.add_command_line_options();
     app.run();
 }
@@ -1,10 +1,16 @@
 mod action_view;
 pub mod application;
+#[cfg(target_os = "linux")]
+mod event_socket;
 mod fs;
 mod licenses;
 mod preferences;
 mod pride;
 mod progress;
+mod receive;
+mod self_test;
+mod send_queue;
+mod sound;
 mod welcome_dialog;
 pub mod window;
 
@@ -1,19 +1,26 @@
+use super::application::{InhibitGuard, TransferEvent, TransferResult};
 use super::fs;
-use super::progress::FileTransferProgress;
+use super::progress::{FileTransferProgress, LOW_POWER_UPDATE_INTERVAL, format_speed};
+use super::receive;
+use super::send_queue;
+use crate::config::{NotificationCategory, OverwritePolicy};
 use crate::gettext::*;
-use crate::ui::fs::safe_persist_tempfile;
-use crate::ui::window::WarpApplicationWindow;
+use crate::ui::window::{Action, WarpApplicationWindow};
 use crate::util::error::*;
 use crate::util::future::*;
-use crate::util::{TransferDirection, WormholeTransferURI, show_dir};
+use crate::util::relay_gate::RelayGate;
+use crate::util::zip::ZipProgress;
+use crate::util::{TransferDirection, WormholeTransferURI, spell_word};
 use crate::{WarpApplication, globals};
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::clone;
-use std::ffi::OsString;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
 use std::fmt::Debug;
 use std::future::Future;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::time::Duration;
@@ -21,28 +28,69 @@ use std::time::Duration;
 // 5 seconds timeout
 const TIMEOUT_MS: u64 = 5000;
 
+/// Seconds until we return to the start screen after a completed transfer, if enabled
+const RETURN_HOME_DELAY_SECS: u32 = 5;
+
+/// Seconds without a receiver after which we warn that the code may expire
+const CODE_EXPIRY_WARNING_SECS: u32 = 10 * 60;
+
+/// How often we try to reach the rendezvous server before giving up on network errors
+const CONNECT_MAX_ATTEMPTS: u32 = 3;
+/// Delay before the first retry, which doubles with every further attempt
+const CONNECT_RETRY_DELAY: Duration = Duration::from_secs(2);
+
 #[derive(Debug)]
 pub enum UIState {
     Initial,
-    Archive(OsString),
+    /// Archive name and the number of selected files and folders it is created from
+    Archive(OsString, usize),
+    /// Fetching a file from the web before sending it
+    Download(url::Url),
+    /// Reviewing the selected files and folders before sending them
+    Queue,
     RequestCode,
     HasCode(WormholeTransferURI),
     Connected,
+    /// The file was offered to the receiver, who still has to accept it
+    AwaitingAcceptance,
     AskConfirmation(String, u64),
     Transmitting(String, wormhole::transit::TransitInfo),
     Done(OsString),
     Error(AppError),
 }
 
+impl UIState {
+    /// Name of the state for the event socket
+    fn name(&self) -> &'static str {
+        match self {
+            UIState::Initial => "initial",
+            UIState::Archive(..) => "archive",
+            UIState::Download(..) => "download",
+            UIState::Queue => "queue",
+            UIState::RequestCode => "request_code",
+            UIState::HasCode(..) => "has_code",
+            UIState::Connected => "connected",
+            UIState::AwaitingAcceptance => "awaiting_acceptance",
+            UIState::AskConfirmation(..) => "ask_confirmation",
+            UIState::Transmitting(..) => "transmitting",
+            UIState::Done(..) => "done",
+            UIState::Error(..) => "error",
+        }
+    }
+}
+
 /// We are only interested about the state, not the context information
 impl PartialEq for UIState {
     fn eq(&self, other: &Self) -> bool {
         match self {
             UIState::Initial => matches!(other, UIState::Initial),
-            UIState::Archive(_) => matches!(other, UIState::Archive(..)),
+            UIState::Archive(..) => matches!(other, UIState::Archive(..)),
+            UIState::Download(..) => matches!(other, UIState::Download(..)),
+            UIState::Queue => matches!(other, UIState::Queue),
             UIState::RequestCode => matches!(other, UIState::RequestCode),
             UIState::HasCode(..) => matches!(other, UIState::HasCode(..)),
             UIState::Connected => matches!(other, UIState::Connected),
+            UIState::AwaitingAcceptance => matches!(other, UIState::AwaitingAcceptance),
             UIState::AskConfirmation(..) => matches!(other, UIState::AskConfirmation(..)),
             UIState::Transmitting(..) => matches!(other, UIState::Transmitting(..)),
             UIState::Done(_) => matches!(other, UIState::Done(..)),
@@ -57,6 +105,20 @@ impl Default for UIState {
     }
 }
 
+/// Sending the same files to several receivers in turn, with a new code for each
+#[derive(Clone, Copy, Debug)]
+pub struct Broadcast {
+    pub delivered: u32,
+    pub total: u32,
+}
+
+/// An archive created for sending, which is deleted when dropped
+#[derive(Debug)]
+pub struct SendArchive {
+    pub temp_file: tempfile::NamedTempFile,
+    pub filename: OsString,
+}
+
 /// Mutable state for the `ActionView`
 #[derive(Debug)]
 pub struct UIContext {
@@ -90,6 +152,10 @@ pub struct UIContext {
     /// Whether we are currently sending or receiving
     pub direction: TransferDirection,
 
+    /// Note describing the file being sent. The transfer protocol can't carry it, so it is only
+    /// shown to the sender next to the code.
+    pub note: Option<String>,
+
     /// The current UI mode
     pub ui_state: Rc<UIState>,
 
@@ -101,6 +167,59 @@ pub struct UIContext {
 
     /// The transit url in use
     pub relay_hints: Vec<wormhole::transit::RelayHint>,
+
+    /// How far the configured relays were rotated, so the first one in `relay_hints` is tried first
+    pub relay_offset: usize,
+
+    /// How we are connected to the peer, once the transit connection is established
+    pub connection_type: Option<wormhole::transit::ConnectionType>,
+
+    /// The address of the peer or relay we are connected to
+    pub peer_addr: Option<SocketAddr>,
+
+    /// How the transit connection was negotiated, if logging connection details is turned on
+    pub transit_log: Vec<String>,
+
+    /// Whether we created the code as the receiver, so it is shown for the sender to enter
+    pub receive_code_allocated: bool,
+
+    /// The code of the transfer, which is not inserted from the clipboard until shortly after the
+    /// transfer ended
+    pub code: Option<wormhole::Code>,
+
+    /// Number of special files or broken links that were left out of the sent archive
+    pub skipped_files: usize,
+
+    /// Number of hidden files and folders that were excluded from the sent archive
+    pub hidden_files: usize,
+
+    /// ID of the countdown timer that returns to the start screen after a completed transfer
+    pub return_home_source_id: Option<glib::source::SourceId>,
+
+    /// Toast showing the return countdown, which allows to stay on the success page
+    pub return_home_toast: Option<adw::Toast>,
+
+    /// ID of the timer that shows how long we have been waiting for the receiver
+    pub code_waiting_source_id: Option<glib::source::SourceId>,
+
+    /// The files being sent, to send them again with a new code
+    pub send_paths: Option<Vec<PathBuf>>,
+
+    /// Set while sending to several receivers
+    pub broadcast: Option<Broadcast>,
+
+    /// The archive of the sent files, to send it to further receivers without creating it again
+    pub send_archive: Option<SendArchive>,
+
+    /// The files and folders selected for sending, while reviewing them, and their sizes
+    pub send_queue: Vec<PathBuf>,
+    pub send_queue_sizes: HashMap<PathBuf, u64>,
+
+    /// ID of the timer that shows how long we have been connecting, while no code is shown
+    pub connecting_source_id: Option<glib::source::SourceId>,
+    /// The current connection step and the seconds since connecting started
+    pub connecting_step: String,
+    pub connecting_seconds: u32,
 }
 
 impl Default for UIContext {
@@ -123,22 +242,41 @@ impl Default for UIContext {
             file_path_received_successfully: None,
             file_name: None,
             direction: TransferDirection::default(),
+            note: None,
             ui_state: Rc::default(),
             progress: None,
             relay_hints: globals::WORMHOLE_DEFAULT_TRANSIT_RELAY_HINTS.clone(),
+            relay_offset: 0,
             rendezvous_url: globals::WORMHOLE_DEFAULT_RENDEZVOUS_SERVER.clone(),
+            connection_type: None,
+            peer_addr: None,
+            transit_log: Vec::new(),
+            receive_code_allocated: false,
+            code: None,
+            skipped_files: 0,
+            hidden_files: 0,
+            return_home_source_id: None,
+            return_home_toast: None,
+            code_waiting_source_id: None,
+            send_paths: None,
+            broadcast: None,
+            send_archive: None,
+            send_queue: Vec::new(),
+            send_queue_sizes: HashMap::new(),
+            connecting_source_id: None,
+            connecting_step: String::new(),
+            connecting_seconds: 0,
         }
     }
 }
 
-static TRANSIT_ABILITIES: wormhole::transit::Abilities = wormhole::transit::Abilities::ALL;
-
 mod imp {
     use super::*;
-    use gtk::gdk::AppLaunchContext;
-    use std::cell::RefCell;
+    use glib::Properties;
+    use std::cell::{Cell, RefCell};
 
-    #[derive(Debug, Default, gtk::CompositeTemplate)]
+    #[derive(Debug, Default, Properties, gtk::CompositeTemplate)]
+    #[properties(wrapper_type = super::ActionView)]
     #[template(file = "action_view.ui")]
     pub struct ActionView {
         #[template_child]
@@ -147,8 +285,12 @@ mod imp {
         #[template_child]
         pub status_page_progress: TemplateChild<adw::StatusPage>,
         #[template_child]
+        pub progress_compact_box: TemplateChild<gtk::Box>,
+        #[template_child]
         pub status_page_code: TemplateChild<gtk::ScrolledWindow>,
         #[template_child]
+        pub status_page_queue: TemplateChild<adw::StatusPage>,
+        #[template_child]
         pub status_page_ask_confirmation: TemplateChild<adw::StatusPage>,
         #[template_child]
         pub status_page_success: TemplateChild<adw::StatusPage>,
@@ -160,12 +302,48 @@ mod imp {
         #[template_child]
         pub open_button: TemplateChild<gtk::Button>,
         #[template_child]
+        pub open_with_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub open_dir_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub copy_error_button: TemplateChild<gtk::Button>,
         #[template_child]
+        pub copy_transit_log_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub retry_relay_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub transit_preferences_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub transit_help_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub send_again_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub save_poster_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub code_waiting_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub code_spelling_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub code_highlight_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub regenerate_code_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub stop_broadcast_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub send_queue_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub send_queue_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub ask_confirmation_button_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub accept_transfer_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub save_as_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub progress_bar: TemplateChild<gtk::ProgressBar>,
         #[template_child]
+        pub progress_bar_compact: TemplateChild<gtk::ProgressBar>,
+        #[template_child]
         pub code_entry: TemplateChild<gtk::Entry>,
         #[template_child]
         pub save_as_file_dialog: TemplateChild<gtk::FileDialog>,
@@ -175,8 +353,29 @@ mod imp {
         pub code_description: TemplateChild<gtk::Label>,
         #[template_child]
         pub code_detail_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub progress_details_expander: TemplateChild<gtk::Expander>,
+        #[template_child]
+        pub progress_details_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub success_details_expander: TemplateChild<gtk::Expander>,
+        #[template_child]
+        pub success_summary_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub success_details_label: TemplateChild<gtk::Label>,
 
         pub context: RefCell<UIContext>,
+
+        /// Rotation of the relays for the next transfer, set when retrying with a different relay
+        pub next_relay_offset: Cell<usize>,
+
+        /// Progress of sending to several receivers, carried over to the next round
+        pub next_broadcast: Cell<Option<Broadcast>>,
+        pub next_send_archive: Cell<Option<SendArchive>>,
+
+        /// Use a more space efficient layout for the transfer progress on small screens
+        #[property(get, set = Self::set_compact)]
+        pub compact: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -196,13 +395,58 @@ mod imp {
         }
     }
 
-    impl ObjectImpl for ActionView {}
+    impl ObjectImpl for ActionView {
+        fn properties() -> &'static [glib::ParamSpec] {
+            Self::derived_properties()
+        }
+
+        fn set_property(&self, id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            self.derived_set_property(id, value, pspec);
+        }
+
+        fn property(&self, id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            self.derived_property(id, pspec)
+        }
+    }
+
+    impl WidgetImpl for ActionView {
+        fn map(&self) {
+            self.parent_map();
+
+            // The window may have been hidden in the meantime
+            if matches!(&*self.context.borrow().ui_state, UIState::Transmitting(..)) {
+                self.obj().show_latest_progress();
+            }
+        }
+    }
 
-    impl WidgetImpl for ActionView {}
-    impl NavigationPageImpl for ActionView {}
+    impl NavigationPageImpl for ActionView {
+        fn hiding(&self) {
+            self.obj().cancel_return_home();
+            self.obj().stop_code_waiting_timer();
+            self.obj().stop_connecting_timer();
+        }
+    }
 
     #[gtk::template_callbacks]
     impl ActionView {
+        fn set_compact(&self, compact: bool) {
+            self.compact.set(compact);
+
+            if matches!(&*self.context.borrow().ui_state, UIState::Transmitting(..)) {
+                self.show_progress_page();
+            }
+        }
+
+        /// Shows either the regular or the compact progress page
+        pub(super) fn show_progress_page(&self) {
+            if self.compact.get() {
+                self.stack.set_visible_child(&*self.progress_compact_box);
+            } else {
+                self.stack.set_visible_child(&*self.status_page_progress);
+            }
+        }
+
         #[template_callback]
         fn back_button_clicked(&self) {
             self.obj().window().navigate_home();
@@ -215,6 +459,12 @@ mod imp {
             }
         }
 
+        #[template_callback]
+        async fn send_queue_button_clicked(&self) {
+            let continue_sender = self.context.borrow().continue_sender.clone();
+            continue_sender.broadcast(None).await.unwrap();
+        }
+
         #[template_callback]
         async fn accept_transfer_button_clicked(&self) {
             let continue_sender = self.context.borrow().continue_sender.clone();
@@ -223,57 +473,120 @@ mod imp {
 
         #[template_callback]
         async fn save_as_button_clicked(&self) {
-            match self
-                .save_as_file_dialog
-                .save_future(Some(&self.obj().window()))
-                .await
-            {
-                Ok(file) => {
-                    if let Some(path) = file.path() {
-                        log::debug!("Selected path: '{}'", path.display());
-                        let continue_sender = self.context.borrow().continue_sender.clone();
-                        continue_sender.broadcast(Some(path)).await.unwrap();
-                    }
-                }
-                Err(err) => {
-                    log::debug!("Save As file chooser error: {:?}", err);
-                }
+            let path = self.obj().choose_save_as_path().await;
+
+            // The peer may have canceled while the file chooser was open. The selection must not
+            // be passed on to the channel of the next transfer then.
+            if !matches!(
+                *self.context.borrow().ui_state,
+                UIState::AskConfirmation(..)
+            ) {
+                log::debug!("Transfer is no longer waiting for confirmation, ignoring selection");
+                return;
             }
+
+            if let Some(path) = path {
+                let continue_sender = self.context.borrow().continue_sender.clone();
+                continue_sender.broadcast(Some(path)).await.unwrap();
+            } else {
+                // Still deciding. Accepting saves to the Downloads folder as before.
+                self.accept_transfer_button.grab_focus();
+            }
+        }
+
+        #[template_callback]
+        fn progress_details_expander_expanded(&self) {
+            self.obj().update_transfer_details();
         }
 
         #[template_callback]
         fn code_copy_button_clicked(&self) {
-            let UIState::HasCode(uri) = &*self.obj().ui_state() else {
-                return;
-            };
+            self.obj().copy_code();
+        }
 
-            let window = self.obj().window();
-            let clipboard = window.clipboard();
+        #[template_callback]
+        fn link_copy_button_clicked(&self) {
+            self.obj().copy_link();
+        }
 
-            clipboard.set_text(uri.code.as_ref());
+        #[template_callback]
+        fn copy_code_and_link_button_clicked(&self) {
+            self.obj().copy_code_and_link();
+        }
 
-            // Translators: Notification when clicking on "Copy Code to Clipboard" button
-            let toast = adw::Toast::new(&gettext("Copied Code to Clipboard"));
-            toast.set_timeout(3);
-            toast.set_priority(adw::ToastPriority::Normal);
-            window.toast_overlay().add_toast(toast);
+        #[template_callback]
+        async fn regenerate_code_button_clicked(&self) {
+            let obj = self.obj();
+            let Some(paths) = self.context.borrow().send_paths.clone() else {
+                return;
+            };
+
+            log::info!("Sending again with a new code");
+            obj.cancel_transmit().await;
+            obj.send_files_again(paths, obj.window().config().app_cfg());
         }
 
         #[template_callback]
-        fn link_copy_button_clicked(&self) {
+        async fn save_poster_button_clicked(&self) {
             let UIState::HasCode(uri) = &*self.obj().ui_state() else {
                 return;
             };
+            let uri = uri.clone();
 
             let window = self.obj().window();
-            let clipboard = window.clipboard();
-            clipboard.set_text(&uri.create_uri());
 
-            // Translators: Notification when clicking on "Copy Link to Clipboard" button
-            let toast = adw::Toast::new(&gettext("Copied Link to Clipboard"));
-            toast.set_timeout(3);
-            toast.set_priority(adw::ToastPriority::Normal);
-            window.toast_overlay().add_toast(toast);
+            let filter = gtk::FileFilter::new();
+            filter.set_name(Some(&gettext("SVG Images")));
+            filter.add_mime_type("image/svg+xml");
+
+            let dialog = gtk::FileDialog::builder()
+                .title(gettext("Save QR Code Poster"))
+                .initial_name("warp-code.svg")
+                .default_filter(&filter)
+                .modal(true)
+                .build();
+
+            let path = match dialog.save_future(Some(&window)).await {
+                Ok(file) => file.path(),
+                Err(err) => {
+                    log::debug!("Poster file chooser error: {:?}", err);
+                    return;
+                }
+            };
+
+            let Some(path) = path else {
+                log::error!("Selected poster file has no path");
+                return;
+            };
+
+            // Translators: Title of the printable QR code poster
+            let title = gettext("Receive a File with Warp");
+            let instructions = vec![
+                // Translators: Instructions on the printable QR code poster, line 1
+                gettext("Open Warp and scan this QR code,"),
+                // Translators: Instructions on the printable QR code poster, line 2
+                gettext("or enter the code above to receive the file."),
+            ];
+
+            let error_correction = window.config().qr_error_correction_or_default();
+            let res = spawn_async(async move {
+                let svg = smol::unblock(move || {
+                    uri.to_qr_poster_svg(&title, &instructions, error_correction)
+                })
+                .await;
+                smol::fs::write(&path, svg).await
+            })
+            .await;
+
+            match res {
+                Ok(()) => {
+                    let toast = adw::Toast::new(&gettext("Saved QR Code Poster"));
+                    toast.set_timeout(3);
+                    toast.set_priority(adw::ToastPriority::Normal);
+                    window.toast_overlay().add_toast(toast);
+                }
+                Err(err) => err.handle(),
+            }
         }
 
         #[template_callback]
@@ -295,6 +608,78 @@ mod imp {
             window.toast_overlay().add_toast(toast);
         }
 
+        #[template_callback]
+        fn copy_transit_log_button_clicked(&self) {
+            let window = self.obj().window();
+            let log = self.context.borrow().transit_log.join("\n");
+            window.clipboard().set_text(&log);
+
+            let toast = adw::Toast::new(&gettext("Copied Connection Log to Clipboard"));
+            toast.set_timeout(3);
+            toast.set_priority(adw::ToastPriority::Normal);
+            window.toast_overlay().add_toast(toast);
+        }
+
+        #[template_callback]
+        async fn stop_broadcast_button_clicked(&self) {
+            let obj = self.obj();
+            let Some(broadcast) = self.context.borrow().broadcast else {
+                return;
+            };
+
+            log::info!("Stopping to send to more receivers");
+            obj.cancel().await;
+
+            let toast = adw::Toast::new(&ngettextf(
+                // Translators: Toast after stopping to send a file to several receivers
+                "Sent to {} receiver",
+                "Sent to {} receivers",
+                broadcast.delivered,
+                &[&broadcast.delivered],
+            ));
+            obj.window().toast_overlay().add_toast(toast);
+        }
+
+        #[template_callback]
+        fn send_again_button_clicked(&self) {
+            let obj = self.obj();
+            let Some(paths) = self.context.borrow().send_paths.clone() else {
+                return;
+            };
+
+            log::info!("Sending again after the transfer was rejected");
+            obj.send_files_again(paths, obj.window().config().app_cfg());
+        }
+
+        #[template_callback]
+        fn transit_help_button_clicked(&self) {
+            if let Some(app) = self.obj().app() {
+                app.open_help(Some("details-glossary"));
+            }
+        }
+
+        #[template_callback]
+        fn retry_relay_button_clicked(&self) {
+            let obj = self.obj();
+            let (direction, relay_offset, send_paths) = {
+                let context = self.context.borrow();
+                (
+                    context.direction,
+                    context.relay_offset,
+                    context.send_paths.clone(),
+                )
+            };
+
+            log::info!("Retrying the transfer with a different relay");
+            self.next_relay_offset.set(relay_offset + 1);
+            let app_cfg = obj.window().config().app_cfg();
+            match (direction, send_paths) {
+                (TransferDirection::Send, Some(paths)) => obj.send_files_again(paths, app_cfg),
+                (TransferDirection::Receive, _) => obj.receive_file_new_code(app_cfg),
+                (TransferDirection::Send, None) => self.next_relay_offset.set(0),
+            }
+        }
+
         #[template_callback]
         fn code_entry_select_all(entry: &gtk::Entry) {
             // Select all text when entry is focused
@@ -307,72 +692,81 @@ mod imp {
 
         #[template_callback]
         async fn open_button_clicked(&self) {
+            self.obj().cancel_return_home();
             let maybe_path = self
                 .context
                 .borrow()
                 .file_path_received_successfully
                 .clone();
 
-            if let Some(filename) = maybe_path {
-                let uri = glib::filename_to_uri(filename.clone(), None);
-                if let Ok(uri) = uri {
-                    log::debug!("Opening file with uri '{}'", uri);
-                    let none: Option<&AppLaunchContext> = None;
-                    let res = gio::AppInfo::launch_default_for_uri(&uri, none);
-                    if let Err(err) = res {
-                        log::error!("Error opening file: {}", err);
-                        let window = self.obj().window();
-
-                        let answer = self
-                            .obj()
-                            .window()
-                            .no_registered_application_error_dialog(err.message())
-                            .choose_future(&window)
-                            .await;
-
-                        if answer == "show-in-folder" {
-                            if let Err(err) = show_dir(&filename).await {
-                                log::error!("Error showing directory: {}", err);
-                                err.handle();
-                            }
-                        }
-                    }
-                } else {
-                    log::error!("Filename to open is not a valid uri");
-                }
+            if let Some(path) = maybe_path {
+                self.obj().window().open_file(&path).await;
             } else {
                 log::error!("Open button clicked but no filename set");
             };
         }
 
+        #[template_callback]
+        async fn open_with_button_clicked(&self) {
+            self.obj().cancel_return_home();
+            let maybe_path = self
+                .context
+                .borrow()
+                .file_path_received_successfully
+                .clone();
+
+            if let Some(path) = maybe_path {
+                self.obj().window().open_file_with(&path).await;
+            }
+        }
+
         #[template_callback]
         async fn open_dir_button_clicked(&self) {
-            let filename = self
+            self.obj().cancel_return_home();
+            let maybe_path = self
                 .context
-                .borrow_mut()
+                .borrow()
                 .file_path_received_successfully
                 .clone();
-            if let Some(filename) = filename {
-                if let Err(err) = show_dir(&filename).await {
-                    err.handle();
-                }
+
+            if let Some(path) = maybe_path {
+                self.obj().window().open_dir(&path).await;
             };
         }
 
         pub fn send_notification_if_background(
             &self,
+            category: NotificationCategory,
             id: Option<&str>,
             notification: &gio::Notification,
         ) {
-            if let Some(app) = self.obj().app() {
-                if let Some(window) = app.active_window() {
-                    if window.is_active() {
-                        return;
-                    }
-                }
+            let window = self.obj().window();
+            let Some(app) = self.obj().app() else {
+                return;
+            };
+
+            if app.do_not_disturb() {
+                log::debug!("Notifications are paused for this session");
+                return;
+            }
+
+            // Sounds are played in the foreground as well, so they are heard when looking away
+            let sounds = window.config().notification_sounds_or_default();
+            if let Some(sound) = sounds.sound(category) {
+                app.play_event_sound(sound);
+            }
 
-                app.send_notification(id, notification);
+            if !window.config().notifications_or_default().enabled(category) {
+                log::debug!("Notifications of category {:?} are disabled", category);
+                return;
+            }
+
+            // Every window runs its own transfer, so only skip this if our window is focused
+            if window.is_active() {
+                return;
             }
+
+            app.send_notification(id, notification);
         }
     }
 }
@@ -400,8 +794,133 @@ impl ActionView {
     }
 
     fn set_ui_state(&self, ui_state: UIState) {
+        if !matches!(ui_state, UIState::HasCode(..)) {
+            self.stop_code_waiting_timer();
+        }
+        if !matches!(ui_state, UIState::RequestCode | UIState::HasCode(..)) {
+            self.stop_connecting_timer();
+        }
+        self.window().note_activity();
+
+        let event = TransferEvent::State {
+            state: ui_state.name(),
+            direction: self.direction_name(),
+            error: match &ui_state {
+                UIState::Error(err) => Some(err.to_string()),
+                _ => None,
+            },
+        };
+
         self.imp().context.borrow_mut().ui_state = Rc::new(ui_state);
         self.update_ui();
+        self.update_copy_actions();
+
+        if let Some(app) = self.app() {
+            app.emit_transfer_event(&event);
+        }
+    }
+
+    /// `send` or `receive`, for scripts and apps embedding Warp
+    fn direction_name(&self) -> &'static str {
+        match self.transfer_direction() {
+            TransferDirection::Send => "send",
+            TransferDirection::Receive => "receive",
+        }
+    }
+
+    fn update_copy_actions(&self) {
+        let Some(window) = self.root().and_downcast::<WarpApplicationWindow>() else {
+            return;
+        };
+
+        let can_copy_code = self.can_copy_code();
+        window.action_set_enabled(Action::CopyCode.as_ref(), can_copy_code);
+        window.action_set_enabled(Action::CopyLink.as_ref(), can_copy_code);
+        window.action_set_enabled(Action::CopyCodeAndLink.as_ref(), can_copy_code);
+    }
+
+    /// Whether the code of a send is shown, so it can be copied with the keyboard shortcuts
+    pub fn can_copy_code(&self) -> bool {
+        matches!(&*self.ui_state(), UIState::HasCode(..))
+            && self.transfer_direction() == TransferDirection::Send
+    }
+
+    pub fn copy_code(&self) {
+        let UIState::HasCode(uri) = &*self.ui_state() else {
+            return;
+        };
+
+        let window = self.window();
+        self.copy_to_clipboard(uri.code.as_ref());
+
+        // Translators: Notification when clicking on "Copy Code to Clipboard" button
+        let toast = adw::Toast::new(&gettext("Copied Code to Clipboard"));
+        toast.set_timeout(3);
+        toast.set_priority(adw::ToastPriority::Normal);
+        window.toast_overlay().add_toast(toast);
+    }
+
+    /// Copies a code or link. It is cleared again after the time set in preferences, unless
+    /// something else was copied in the meantime.
+    fn copy_to_clipboard(&self, text: &str) {
+        let window = self.window();
+        let clipboard = window.clipboard();
+        clipboard.set_text(text);
+
+        let Some(secs) = window.config().clear_clipboard_secs else {
+            return;
+        };
+
+        let text = text.to_owned();
+        glib::timeout_add_seconds_local_once(secs, move || {
+            glib::spawn_future_local(async move {
+                match clipboard.read_text_future().await {
+                    Ok(Some(current)) if current == text => {
+                        log::debug!("Clearing the copied code from the clipboard");
+                        if let Err(err) = clipboard.set_content(None) {
+                            log::warn!("Error clearing the clipboard: {}", err);
+                        }
+                    }
+                    Ok(_) => log::debug!("The clipboard changed, not clearing it"),
+                    Err(err) => log::debug!("Error reading the clipboard: {}", err),
+                }
+            });
+        });
+    }
+
+    pub fn copy_link(&self) {
+        let UIState::HasCode(uri) = &*self.ui_state() else {
+            return;
+        };
+
+        let window = self.window();
+        self.copy_to_clipboard(&uri.create_uri());
+
+        // Translators: Notification when clicking on "Copy Link to Clipboard" button
+        let toast = adw::Toast::new(&gettext("Copied Link to Clipboard"));
+        toast.set_timeout(3);
+        toast.set_priority(adw::ToastPriority::Normal);
+        window.toast_overlay().add_toast(toast);
+    }
+
+    /// Copies the code and the link together, ready to be pasted into a chat
+    pub fn copy_code_and_link(&self) {
+        let UIState::HasCode(uri) = &*self.ui_state() else {
+            return;
+        };
+
+        let window = self.window();
+        self.copy_to_clipboard(&gettextf(
+            // Translators: Copied to share a transfer, {0} = 4-hurricane-equipment, {1} = wormhole-transfer:4-hurricane-equipment
+            "Transmit code: {0}\nOr open this link: {1}",
+            &[&uri.code, &uri.create_uri()],
+        ));
+
+        // Translators: Notification when clicking on "Copy Code and Link" button
+        let toast = adw::Toast::new(&gettext("Copied Code and Link to Clipboard"));
+        toast.set_timeout(3);
+        toast.set_priority(adw::ToastPriority::Normal);
+        window.toast_overlay().add_toast(toast);
     }
 
     fn ui_state(&self) -> Rc<UIState> {
@@ -412,7 +931,7 @@ impl ActionView {
         self.imp().context.borrow_mut().direction = direction;
     }
 
-    fn transfer_direction(&self) -> TransferDirection {
+    pub fn transfer_direction(&self) -> TransferDirection {
         self.imp().context.borrow().direction
     }
 
@@ -442,10 +961,11 @@ impl ActionView {
                 imp.progress_bar.set_show_text(false);
 
                 super::pride::apply_seasonal_style(&*imp.progress_bar);
+                super::pride::apply_seasonal_style(&*imp.progress_bar_compact);
                 self.enable_back_button(false);
                 self.show_progress_indeterminate(true);
             }
-            UIState::Archive(filename) => match direction {
+            UIState::Archive(filename, count) => match direction {
                 TransferDirection::Send => {
                     imp.stack.set_visible_child(&*imp.status_page_progress);
                     imp.status_page_progress
@@ -453,12 +973,20 @@ impl ActionView {
                     // Translators: Title
                     imp.status_page_progress
                         .set_title(&gettext("Creating Archive"));
-                    imp.status_page_progress
+
+                    let description = if *count > 1 {
+                        ngettextf(
+                            // Translators: Description, argument is the number of selected files
+                            "Compressing {} file",
+                            "Compressing {} files",
+                            *count as u32,
+                            &[count],
+                        )
+                    } else {
                         // Translators: Description
-                        .set_description(Some(&gettextf(
-                            "Compressing folder “{}”",
-                            &[&filename.to_string_lossy()],
-                        )));
+                        gettextf("Compressing folder “{}”", &[&filename.to_string_lossy()])
+                    };
+                    imp.status_page_progress.set_description(Some(&description));
                     imp.progress_bar.set_text(None);
                     imp.progress_bar.set_show_text(true);
                 }
@@ -466,27 +994,42 @@ impl ActionView {
                     // We don't create archives here
                 }
             },
-            UIState::RequestCode => match direction {
-                TransferDirection::Send => {
-                    imp.stack.set_visible_child(&*imp.status_page_progress);
-                    imp.status_page_progress
-                        .set_icon_name(Some("arrows-questionmark-symbolic"));
-                    // Translators: Title
-                    imp.status_page_progress.set_title(&gettext("Connecting"));
-                    imp.status_page_progress
-                        // Translators: Description, Filename
-                        .set_description(Some(&gettext("Requesting file transfer")));
-                    imp.progress_bar.set_text(None);
-                    imp.progress_bar.set_show_text(false);
-                }
-                TransferDirection::Receive => {}
-            },
+            UIState::Download(url) => {
+                imp.stack.set_visible_child(&*imp.status_page_progress);
+                imp.status_page_progress
+                    .set_icon_name(Some("folder-download-symbolic"));
+                // Translators: Title
+                imp.status_page_progress
+                    .set_title(&gettext("Downloading File"));
+                imp.status_page_progress.set_description(Some(&gettextf(
+                    // Translators: Description, argument is the host name of the URL
+                    "Fetching the file from “{}” to send it",
+                    &[&url.host_str().unwrap_or_default()],
+                )));
+                imp.progress_bar.set_text(None);
+                imp.progress_bar.set_show_text(true);
+                self.show_progress_indeterminate(true);
+            }
+            UIState::RequestCode => {
+                imp.stack.set_visible_child(&*imp.status_page_progress);
+                imp.status_page_progress
+                    .set_icon_name(Some("arrows-questionmark-symbolic"));
+                // Translators: Title
+                imp.status_page_progress.set_title(&gettext("Connecting"));
+                let description = match direction {
+                    // Translators: Description, Filename
+                    TransferDirection::Send => gettext("Requesting file transfer"),
+                    // Translators: Description, when receiving with a code created by us
+                    TransferDirection::Receive => gettext("Requesting transmit code"),
+                };
+                self.show_connecting_step(description);
+                imp.progress_bar.set_text(None);
+                imp.progress_bar.set_show_text(false);
+            }
             UIState::HasCode(uri) => {
+                let code_allocated = imp.context.borrow().receive_code_allocated;
                 match direction {
                     TransferDirection::Send => {
-                        imp.stack.set_visible_child(&*imp.status_page_code);
-                        imp.code_image.set_paintable(Some(&uri.to_paintable_qr()));
-
                         let filename = imp
                             .context
                             .borrow()
@@ -494,11 +1037,30 @@ impl ActionView {
                             .clone()
                             .unwrap_or_else(|| "?".into());
 
-                        imp.code_description.set_label(&gettextf(
+                        let mut code_description = gettextf(
                             // Translators: Description, argument is filename
                             "Ready to send “{}”.",
                             &[&filename.to_string_lossy()],
-                        ));
+                        );
+
+                        if let Some(note) = &imp.context.borrow().note {
+                            code_description += "\n";
+                            code_description += &gettextf(
+                                // Translators: Below the filename on the code page, argument is the note entered by the sender
+                                "Note: {}",
+                                &[note],
+                            );
+                        }
+
+                        let broadcast = imp.context.borrow().broadcast;
+                        if let Some(broadcast) = broadcast {
+                            code_description += "\n";
+                            code_description += &gettextf(
+                                // Translators: On the code page when sending to several receivers in turn
+                                "Delivered to {0} of {1} receivers",
+                                &[&broadcast.delivered, &broadcast.total],
+                            );
+                        }
 
                         // Translators: Help dialog line 1, Code words and QR code visible,
                         let mut description = gettext(
@@ -520,19 +1082,33 @@ impl ActionView {
                             );
                         }
 
-                        description += "\n\n";
-                        description +=
-                            &gettext("Click the QR code to copy the link to the clipboard.");
+                        self.stop_connecting_timer();
+                        self.show_code_page(uri, &code_description, description);
+                        self.start_code_waiting_timer();
+                        imp.stop_broadcast_button.set_visible(
+                            broadcast.is_some_and(|broadcast| broadcast.delivered > 0),
+                        );
+                    }
+                    TransferDirection::Receive if code_allocated => {
+                        // Translators: Description on the code page when receiving
+                        let code_description = gettext("Ready to receive a file.");
 
-                        imp.code_detail_label.set_label(&description);
+                        // Translators: Help dialog, Code words and QR code visible, when receiving
+                        let mut description = gettext(
+                            "The sender needs to enter or scan this code to begin the file transfer.",
+                        );
 
-                        #[cfg(not(feature = "demo"))]
-                        imp.code_entry.set_text(uri.code.as_ref());
-                        #[cfg(feature = "demo")]
-                        imp.code_entry.set_text("0-code-words");
+                        if imp.context.borrow().rendezvous_url
+                            != *globals::WORMHOLE_DEFAULT_RENDEZVOUS_SERVER
+                        {
+                            description += "\n";
+                            description += &gettext(
+                                "You have entered a custom rendezvous server URL in preferences. Please verify the sender also uses the same rendezvous server.",
+                            );
+                        }
 
-                        imp.code_entry
-                            .set_max_width_chars(imp.code_entry.text().len() as i32);
+                        self.stop_connecting_timer();
+                        self.show_code_page(uri, &code_description, description);
                     }
                     TransferDirection::Receive => {
                         imp.stack.set_visible_child(&*imp.status_page_progress);
@@ -540,11 +1116,11 @@ impl ActionView {
                             .set_icon_name(Some("arrows-questionmark-symbolic"));
                         // Translators: Title
                         imp.status_page_progress.set_title(&gettext("Connecting"));
-                        imp.status_page_progress.set_description(Some(&gettextf(
+                        self.show_connecting_step(gettextf(
                             // Translators: Description, Transfer Code
                             "Connecting to peer with code “{}”",
                             &[&uri.code],
-                        )));
+                        ));
                     }
                 }
             }
@@ -574,18 +1150,68 @@ impl ActionView {
                     }
                 }
             }
+            UIState::AwaitingAcceptance => {
+                imp.stack.set_visible_child(&*imp.status_page_progress);
+                imp.status_page_progress
+                    .set_title(&gettext("Connected to Peer"));
+                imp.status_page_progress
+                    // Translators: Description, the receiver is asked whether to accept the file
+                    .set_description(Some(&gettext("Waiting for the other side to accept…")));
+                imp.status_page_progress
+                    .set_icon_name(Some("send-to-symbolic"));
+
+                self.show_progress_indeterminate(true);
+                imp.progress_bar.set_show_text(false);
+            }
+            UIState::Queue => {
+                imp.stack.set_visible_child(&*imp.status_page_queue);
+                self.show_progress_indeterminate(false);
+                self.update_send_queue();
+            }
             UIState::AskConfirmation(filename, size) => {
                 imp.stack
                     .set_visible_child(&*imp.status_page_ask_confirmation);
                 self.show_progress_indeterminate(false);
                 self.enable_back_button(true);
 
-                imp.status_page_ask_confirmation.set_description(Some(&gettextf(
-                    // Translators: File receive confirmation message dialog; Filename, File size
-                    "Your peer wants to send you “{0}” (Size: {1}).\nDo you want to download this file? The default action will save the file to your Downloads folder.",
-                    &[&filename,
-                        &glib::format_size(*size)]
-                )));
+                let save_as_by_default = self.window().config().save_as_by_default_or_default();
+                let (default_button, other_button) = if save_as_by_default {
+                    (&imp.save_as_button, &imp.accept_transfer_button)
+                } else {
+                    (&imp.accept_transfer_button, &imp.save_as_button)
+                };
+
+                default_button.add_css_class("suggested-action");
+                other_button.remove_css_class("suggested-action");
+                imp.ask_confirmation_button_box
+                    .reorder_child_after(&**other_button, Some(&**default_button));
+
+                let mut description = if save_as_by_default {
+                    gettextf(
+                        // Translators: File receive confirmation message dialog; Filename, File size
+                        "Your peer wants to send you “{0}” (Size: {1}).\nDo you want to download this file? The default action will let you choose where to save the file.",
+                        &[&filename, &glib::format_size(*size)],
+                    )
+                } else {
+                    gettextf(
+                        // Translators: File receive confirmation message dialog; Filename, File size
+                        "Your peer wants to send you “{0}” (Size: {1}).\nDo you want to download this file? The default action will save the file to your Downloads folder.",
+                        &[&filename, &glib::format_size(*size)],
+                    )
+                };
+
+                if let Some(max_size) = self.window().config().max_receive_size {
+                    description.push_str("\n\n");
+                    description.push_str(&gettextf(
+                        // Translators: File receive confirmation message dialog; File size
+                        "Files larger than {} are rejected automatically.",
+                        &[&glib::format_size(max_size)],
+                    ));
+                }
+
+                imp.status_page_ask_confirmation
+                    .set_description(Some(&description));
+                default_button.grab_focus();
 
                 let notification = gio::Notification::new(&gettext("Ready to Receive File"));
                 notification.set_body(Some(&gettext(
@@ -594,11 +1220,16 @@ impl ActionView {
                 notification.set_priority(gio::NotificationPriority::Urgent);
                 notification.set_category(Some("transfer"));
 
-                self.imp()
-                    .send_notification_if_background(Some("receive-ready"), &notification);
+                self.imp().send_notification_if_background(
+                    NotificationCategory::ReadyToReceive,
+                    Some("receive-ready"),
+                    &notification,
+                );
             }
             UIState::Transmitting(filename, info) => {
-                imp.stack.set_visible_child(&*imp.status_page_progress);
+                // The window is not kept above others during the transfer: GTK 4 removed
+                // `gtk_window_set_keep_above`, and neither Wayland nor the portals offer it
+                imp.show_progress_page();
                 self.show_progress_indeterminate(false);
                 imp.progress_bar.set_show_text(true);
                 self.enable_back_button(false);
@@ -640,6 +1271,21 @@ impl ActionView {
 
                 imp.status_page_progress.set_description(Some(&description));
 
+                if matches!(
+                    info.conn_type,
+                    wormhole::transit::ConnectionType::Relay { .. }
+                ) && !is_site_local
+                {
+                    self.show_relay_info();
+                }
+
+                imp.context.borrow_mut().connection_type = Some(info.conn_type.clone());
+                imp.context.borrow_mut().peer_addr = Some(info.peer_addr);
+                imp.progress_details_expander.set_expanded(false);
+                imp.progress_details_expander.set_visible(true);
+                self.update_transfer_details();
+                self.show_latest_progress();
+
                 if direction == TransferDirection::Send {
                     // Translators: Title
                     imp.status_page_progress.set_title(&gettext("Sending File"));
@@ -657,26 +1303,61 @@ impl ActionView {
                 imp.stack.set_visible_child(&*imp.status_page_success);
                 self.enable_back_button(true);
 
+                let summary = self.transfer_summary();
+                imp.success_summary_label.set_visible(summary.is_some());
+                imp.success_summary_label
+                    .set_label(&summary.unwrap_or_default());
+
+                let details = self.transfer_details();
+                imp.success_details_expander.set_expanded(false);
+                imp.success_details_expander.set_visible(details.is_some());
+                imp.success_details_label
+                    .set_label(&details.unwrap_or_default());
+
                 let notification = gio::Notification::new(&gettext("File Transfer Complete"));
 
                 notification.set_priority(gio::NotificationPriority::High);
                 notification.set_category(Some("transfer.complete"));
 
                 if direction == TransferDirection::Send {
-                    let description = gettextf(
+                    let mut description = gettextf(
                         // Translators: Description, Filename
                         "Successfully sent file “{}”",
                         &[&filename.to_string_lossy()],
                     );
 
+                    let skipped_files = imp.context.borrow().skipped_files;
+                    if skipped_files > 0 {
+                        description.push('\n');
+                        description.push_str(&ngettextf_(
+                            // Translators: Description, files in a sent folder that are not regular files
+                            "{} item was not included, because it is not a regular file.",
+                            "{} items were not included, because they are not regular files.",
+                            skipped_files as u32,
+                        ));
+                    }
+
+                    let hidden_files = imp.context.borrow().hidden_files;
+                    if hidden_files > 0 {
+                        description.push('\n');
+                        description.push_str(&ngettextf_(
+                            // Translators: Description, hidden files in a sent folder that were excluded in preferences
+                            "{} hidden item was not included.",
+                            "{} hidden items were not included.",
+                            hidden_files as u32,
+                        ));
+                    }
+
                     imp.status_page_success.set_description(Some(&description));
                     notification.set_body(Some(&description));
                     imp.open_button.set_visible(false);
+                    imp.open_with_button.set_visible(false);
                     imp.open_dir_button.set_visible(false);
                 } else {
                     match imp.context.borrow().file_path_received_successfully.clone() {
                         Some(path) => {
-                            let description = super::fs::default_download_dir()
+                            let description = self
+                                .download_dir()
                                 .ok()
                                 .filter(|download_dir| path.parent() == Some(download_dir))
                                 .map_or_else(
@@ -699,62 +1380,234 @@ impl ActionView {
                             imp.status_page_success.set_description(Some(&description));
                             notification.set_body(Some(&description));
 
+                            // A file manager can't show the folder the document portal
+                            // mounts selected files in
+                            let show_dir = !fs::is_portal_path(&path);
                             imp.open_button.set_visible(true);
-                            imp.open_dir_button.set_visible(true);
+                            imp.open_with_button.set_visible(true);
+                            imp.open_dir_button.set_visible(show_dir);
                             notification.set_default_action_and_target_value(
-                                crate::ui::window::Action::ShowFile.as_ref(),
+                                "app.show-received-file",
+                                Some(&path.to_variant()),
+                            );
+                            notification.add_button_with_target_value(
+                                &gettext("Open"),
+                                "app.open-received-file",
                                 Some(&path.to_variant()),
                             );
+                            if show_dir {
+                                notification.add_button_with_target_value(
+                                    &gettext("Show in Folder"),
+                                    "app.show-received-file",
+                                    Some(&path.to_variant()),
+                                );
+                            }
                         }
                         _ => {}
                     }
                 }
 
-                self.imp()
-                    .send_notification_if_background(Some("transfer-complete"), &notification);
+                self.imp().send_notification_if_background(
+                    NotificationCategory::Complete,
+                    Some("transfer-complete"),
+                    &notification,
+                );
+
+                if self
+                    .window()
+                    .config()
+                    .return_home_after_completion_or_default()
+                {
+                    self.schedule_return_home();
+                }
             }
             UIState::Error(error) => {
                 imp.stack.set_visible_child(&*imp.status_page_error);
-                imp.status_page_error
-                    .set_description(Some(&error.gettext_error()));
+                // The message may point out preferences of this window, not of the active one
+                let message = error.gettext_error_for(&self.window().config());
+                imp.status_page_error.set_description(Some(&message));
                 self.enable_back_button(true);
 
                 let notification = gio::Notification::new(&gettext("File Transfer Failed"));
-                notification.set_body(Some(&gettextf(
-                    "The file transfer failed: {}",
-                    &[&error.gettext_error()],
-                )));
+                notification.set_body(Some(&gettextf("The file transfer failed: {}", &[&message])));
                 notification.set_priority(gio::NotificationPriority::High);
                 notification.set_category(Some("transfer.error"));
 
-                self.imp()
-                    .send_notification_if_background(Some("transfer-error"), &notification);
+                self.imp().send_notification_if_background(
+                    NotificationCategory::Error,
+                    Some("transfer-error"),
+                    &notification,
+                );
+
+                // A cancellation has no error worth copying, unlike a rejection
+                imp.copy_error_button
+                    .set_visible(!error.is_user_canceled() && !error.is_peer_canceled());
+                imp.send_again_button
+                    .set_visible(self.can_send_again_after_rejection(error));
+
+                imp.copy_transit_log_button.set_visible(
+                    error.is_transit_error() && !imp.context.borrow().transit_log.is_empty(),
+                );
+                imp.retry_relay_button
+                    .set_visible(self.can_retry_with_other_relay(error));
+
+                // Relays are not used at all with direct connections only
+                let relay_blocked = error.is_transit_blocked_error()
+                    && !self.window().config().direct_connections_only_or_default();
+                imp.transit_preferences_button.set_visible(relay_blocked);
+                imp.transit_help_button.set_visible(relay_blocked);
+            }
+        }
+    }
+
+    /// Whether the receiver rejected files that we still have, so they can be offered again with a
+    /// new code. Rejecting is a single click and easily happens by accident.
+    fn can_send_again_after_rejection(&self, error: &AppError) -> bool {
+        let context = self.imp().context.borrow();
+        error.is_peer_rejected()
+            && context.direction == TransferDirection::Send
+            && context.send_paths.is_some()
+    }
 
-                let peer_canceled = if let AppError::Transfer {
-                    source: wormhole::transfer::TransferError::PeerError(msg),
-                } = error
-                {
-                    msg == "Task has been cancelled"
-                } else {
-                    false
-                };
+    /// Whether a transfer failed because of the relay and can be started again with another one.
+    /// The failed transfer can't be resumed, so this needs a new code: either one we show to the
+    /// receiver for files we still have, or one we show to the sender when we created the code.
+    fn can_retry_with_other_relay(&self, error: &AppError) -> bool {
+        let relay_failed = error.is_transit_error();
 
-                if !error.is_user_canceled() && !peer_canceled {
-                    imp.copy_error_button.set_visible(true);
-                }
-            }
+        let context = self.imp().context.borrow();
+        let can_restart = match context.direction {
+            TransferDirection::Send => context.send_paths.is_some(),
+            TransferDirection::Receive => context.receive_code_allocated,
+        };
+
+        relay_failed && can_restart && context.relay_hints.len() > 1
+    }
+
+    /// Explains why a relayed transfer may be slow. This is only shown once per session.
+    fn show_relay_info(&self) {
+        let Some(app) = self.app() else {
+            return;
+        };
+
+        if app.imp().relay_info_shown.replace(true) {
+            return;
+        }
+
+        let toast = adw::Toast::builder()
+            .title(gettext(
+                "A direct connection was not possible, the file is sent via a relay. This can be slower.",
+            ))
+            // Translators: Button in the toast explaining relayed transfers
+            .button_label(gettext("Learn More"))
+            .timeout(10)
+            .build();
+        toast.connect_button_clicked(move |_| {
+            app.open_help(Some("details-glossary"));
+        });
+        self.window().toast_overlay().add_toast(toast);
+    }
+
+    /// Shows the code and its QR code for the peer to enter or scan
+    fn show_code_page(
+        &self,
+        uri: &WormholeTransferURI,
+        code_description: &str,
+        description: String,
+    ) {
+        let imp = self.imp();
+        imp.stack.set_visible_child(&*imp.status_page_code);
+        let error_correction = self.window().config().qr_error_correction_or_default();
+        let qr_size = self.window().config().qr_code_size_or_default();
+        imp.code_image.set_paintable(Some(
+            &uri.to_paintable_qr_with_opts(error_correction, qr_size),
+        ));
+        imp.code_image.set_pixel_size(qr_size.pixels());
+        imp.code_description.set_label(code_description);
+        // The poster asks people to receive a file, which only fits codes for sending
+        imp.save_poster_button
+            .set_visible(uri.direction == TransferDirection::Receive);
+        imp.code_waiting_label.set_visible(false);
+        imp.regenerate_code_button.set_visible(false);
+        imp.stop_broadcast_button.set_visible(false);
+
+        let mut description = description;
+        description += "\n\n";
+        description += &gettext("Click the QR code to copy the link to the clipboard.");
+        imp.code_detail_label.set_label(&description);
+
+        #[cfg(not(feature = "demo"))]
+        imp.code_entry.set_text(uri.code.as_ref());
+        #[cfg(feature = "demo")]
+        imp.code_entry.set_text("0-code-words");
+
+        imp.code_entry
+            .set_max_width_chars(imp.code_entry.text().len() as i32);
+
+        // The entry stays below, to copy the code
+        let highlight_code_number = self.window().config().highlight_code_number_or_default();
+        if highlight_code_number {
+            imp.code_highlight_label
+                .set_markup(&Self::code_highlight_markup(&imp.code_entry.text()));
+        }
+        imp.code_highlight_label.set_visible(highlight_code_number);
+
+        let spell_out_code = self.window().config().spell_out_code_or_default();
+        if spell_out_code {
+            imp.code_spelling_label
+                .set_markup(&Self::code_spelling_markup(&imp.code_entry.text()));
+        }
+        imp.code_spelling_label.set_visible(spell_out_code);
+    }
+
+    /// The code with its channel number in bold, so a number like 7 isn't read as part of the words
+    fn code_highlight_markup(code: &str) -> String {
+        match code.split_once('-') {
+            Some((nameplate, words)) => format!(
+                "<span weight=\"heavy\" size=\"x-large\">{}</span>-{}",
+                glib::markup_escape_text(nameplate),
+                glib::markup_escape_text(words)
+            ),
+            None => glib::markup_escape_text(code).to_string(),
+        }
+    }
+
+    /// The channel number of a code and the spelling of its words, to read the code aloud
+    fn code_spelling_markup(code: &str) -> String {
+        let mut parts = code.split('-');
+        let nameplate = parts.next().unwrap_or_default();
+
+        let mut markup = format!(
+            "<span size=\"x-large\" weight=\"bold\">{}</span>",
+            // Translators: Above the spelling of the code words, the argument is the number at the start of the code
+            glib::markup_escape_text(&gettextf("Channel {}", &[&nameplate]))
+        );
+
+        for word in parts {
+            markup += &format!(
+                "\n<b>{}</b>: {}",
+                glib::markup_escape_text(word),
+                glib::markup_escape_text(&spell_word(word))
+            );
         }
+
+        markup
     }
 
-    /// This will ask whether the transfer should be cancelled.
+    /// This will ask whether the transfer should be cancelled, unless confirming is turned off.
     pub async fn cancel_request(&self) -> bool {
         if matches!(
             &*self.imp().context.borrow().ui_state,
-            UIState::AskConfirmation(..) | UIState::Done(..) | UIState::Error(..)
+            UIState::Queue | UIState::AskConfirmation(..) | UIState::Done(..) | UIState::Error(..)
         ) {
             return true;
         }
 
+        if !self.window().config().confirm_cancel_or_default() {
+            log::debug!("Canceling without confirmation");
+            return true;
+        }
+
         let response = self.window().ask_abort_dialog().choose_future(self).await;
 
         response == "abort"
@@ -772,7 +1625,32 @@ impl ActionView {
         }
     }
 
+    /// Waits until a cancellation in progress is complete, so the temporary files are deleted and
+    /// the session is no longer inhibited
+    pub async fn wait_for_cancellation(&self) {
+        if !self.imp().context.borrow().canceled {
+            return;
+        }
+
+        log::debug!("Waiting for the cancellation to complete");
+        if cancelable_future(
+            self.wait_for_cancellation_future(),
+            glib::timeout_future(Duration::from_millis(TIMEOUT_MS)),
+        )
+        .await
+        .is_err()
+        {
+            log::warn!("Cancellation did not complete in time");
+        }
+    }
+
     pub async fn cancel(&self) {
+        self.cancel_transmit().await;
+        self.window().navigate_home();
+    }
+
+    /// Aborts the transfer without leaving the view
+    async fn cancel_transmit(&self) {
         log::info!("Cancelling transfer");
         self.imp().cancel_button.set_sensitive(false);
         self.imp().context.borrow_mut().canceled = true;
@@ -782,7 +1660,315 @@ impl ActionView {
         let cancel_sender = imp.context.borrow().cancel_sender.clone();
         cancel_sender.broadcast(()).await.unwrap();
         self.wait_for_cancellation_future().await;
-        self.window().navigate_home();
+    }
+
+    fn code_waiting_text(seconds: u32) -> String {
+        let elapsed = format!("{}:{:02}", seconds / 60, seconds % 60);
+
+        let note = if seconds < CODE_EXPIRY_WARNING_SECS {
+            // Translators: Below the code while waiting for the receiver
+            gettext("Codes are meant to be used right away.")
+        } else {
+            // Translators: Below the code, after waiting a long time for the receiver
+            gettext("No receiver has connected yet. The code may expire soon.")
+        };
+
+        note + "\n"
+            + &gettextf(
+                // Translators: Below the code, argument is the elapsed time, e.g. 2:05
+                "Waiting for the receiver for {}",
+                &[&elapsed],
+            )
+    }
+
+    /// Shows how long the code has been waiting for a receiver and offers a new code eventually
+    fn start_code_waiting_timer(&self) {
+        self.stop_code_waiting_timer();
+
+        let imp = self.imp();
+        imp.code_waiting_label
+            .set_label(&Self::code_waiting_text(0));
+        imp.code_waiting_label.remove_css_class("warning");
+        imp.code_waiting_label.set_visible(true);
+
+        let elapsed = Cell::new(0);
+        let source_id = glib::timeout_add_seconds_local(
+            1,
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    let imp = obj.imp();
+                    elapsed.set(elapsed.get() + 1);
+                    imp.code_waiting_label
+                        .set_label(&Self::code_waiting_text(elapsed.get()));
+
+                    if elapsed.get() == CODE_EXPIRY_WARNING_SECS {
+                        log::debug!("No receiver connected, offering a new code");
+                        imp.code_waiting_label.add_css_class("warning");
+                        imp.regenerate_code_button
+                            .set_visible(imp.context.borrow().send_paths.is_some());
+                    }
+
+                    glib::ControlFlow::Continue
+                }
+            ),
+        );
+
+        imp.context.borrow_mut().code_waiting_source_id = Some(source_id);
+    }
+
+    /// Stops the elapsed time display once we leave the code page
+    pub fn stop_code_waiting_timer(&self) {
+        let source_id = self
+            .imp()
+            .context
+            .borrow_mut()
+            .code_waiting_source_id
+            .take();
+        if let Some(source_id) = source_id {
+            source_id.remove();
+        }
+    }
+
+    fn return_home_title(seconds: u32) -> String {
+        ngettextf(
+            // Translators: Toast shown after a completed transfer, counting down
+            "Returning to start screen in {} second",
+            "Returning to start screen in {} seconds",
+            seconds,
+            &[&seconds],
+        )
+    }
+
+    fn connecting_text(step: &str, seconds: u32) -> String {
+        let elapsed = format!("{}:{:02}", seconds / 60, seconds % 60);
+
+        step.to_owned()
+            + "\n"
+            + &gettextf(
+                // Translators: Below the connection step, argument is the elapsed time, e.g. 0:05
+                "Connecting for {}",
+                &[&elapsed],
+            )
+    }
+
+    /// Shows a step of establishing the connection, with the time since connecting started. The
+    /// wormhole library doesn't report the progress of the handshake, so the time shows it
+    /// is still running.
+    fn show_connecting_step(&self, step: String) {
+        let imp = self.imp();
+        let mut context = imp.context.borrow_mut();
+        context.connecting_step = step;
+        let text = Self::connecting_text(&context.connecting_step, context.connecting_seconds);
+        let running = context.connecting_source_id.is_some();
+        drop(context);
+
+        imp.status_page_progress.set_description(Some(&text));
+        if running {
+            return;
+        }
+
+        let source_id = glib::timeout_add_seconds_local(
+            1,
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    let imp = obj.imp();
+                    let mut context = imp.context.borrow_mut();
+                    context.connecting_seconds += 1;
+                    let text =
+                        Self::connecting_text(&context.connecting_step, context.connecting_seconds);
+                    drop(context);
+
+                    imp.status_page_progress.set_description(Some(&text));
+                    glib::ControlFlow::Continue
+                }
+            ),
+        );
+
+        imp.context.borrow_mut().connecting_source_id = Some(source_id);
+    }
+
+    /// The rendezvous server connected us with the peer and the keys are exchanged. Not shown
+    /// while a code is displayed, as the wait is for the peer to enter it.
+    fn show_key_exchange(&self) {
+        if self.imp().context.borrow().connecting_source_id.is_some() {
+            log::debug!("Exchanging keys");
+            // Translators: Description while connecting, after the rendezvous server was reached
+            self.show_connecting_step(gettext("Exchanging keys with the peer…"));
+        }
+    }
+
+    /// Stops the elapsed time display once the connection is established
+    pub fn stop_connecting_timer(&self) {
+        let mut context = self.imp().context.borrow_mut();
+        context.connecting_seconds = 0;
+        if let Some(source_id) = context.connecting_source_id.take() {
+            source_id.remove();
+        }
+    }
+
+    /// Counts down and then navigates back to the start screen, unless the user interacts first
+    fn schedule_return_home(&self) {
+        self.cancel_return_home();
+
+        let remaining = Rc::new(Cell::new(RETURN_HOME_DELAY_SECS));
+        let toast = adw::Toast::builder()
+            .title(Self::return_home_title(remaining.get()))
+            // Translators: Button in the toast counting down to return to the start screen
+            .button_label(gettext("Stay"))
+            .priority(adw::ToastPriority::High)
+            .timeout(0)
+            .build();
+        toast.connect_button_clicked(clone!(
+            #[weak(rename_to = obj)]
+            self,
+            move |_| obj.cancel_return_home()
+        ));
+        self.window().toast_overlay().add_toast(toast.clone());
+
+        let source_id = glib::timeout_add_seconds_local(
+            1,
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                #[weak]
+                toast,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    remaining.set(remaining.get().saturating_sub(1));
+                    if remaining.get() > 0 {
+                        toast.set_title(&Self::return_home_title(remaining.get()));
+                        return glib::ControlFlow::Continue;
+                    }
+
+                    // The source is removed by returning Break
+                    let mut context = obj.imp().context.borrow_mut();
+                    context.return_home_source_id = None;
+                    context.return_home_toast = None;
+                    drop(context);
+
+                    toast.dismiss();
+                    obj.window().navigate_home();
+                    glib::ControlFlow::Break
+                }
+            ),
+        );
+
+        let mut context = self.imp().context.borrow_mut();
+        context.return_home_source_id = Some(source_id);
+        context.return_home_toast = Some(toast);
+    }
+
+    /// Stops a pending return to the start screen
+    pub fn cancel_return_home(&self) {
+        let mut context = self.imp().context.borrow_mut();
+        let source_id = context.return_home_source_id.take();
+        let toast = context.return_home_toast.take();
+        drop(context);
+
+        if let Some(source_id) = source_id {
+            log::debug!("Cancelling return to start screen");
+            source_id.remove();
+        }
+
+        if let Some(toast) = toast {
+            toast.dismiss();
+        }
+    }
+
+    fn connection_name(connection_type: &wormhole::transit::ConnectionType) -> String {
+        match connection_type {
+            // Translators: Transfer details, connection type
+            wormhole::transit::ConnectionType::Direct => gettext("Direct"),
+            wormhole::transit::ConnectionType::Relay { name: Some(name) } => {
+                // Translators: Transfer details, connection type, argument is the relay name
+                gettextf("Relay “{}”", &[name])
+            }
+            // Translators: Transfer details, connection type
+            wormhole::transit::ConnectionType::Relay { name: None } => gettext("Relay"),
+            // Translators: Transfer details, connection type
+            _ => gettext("Unknown"),
+        }
+    }
+
+    /// Connection information and transferred bytes of the current transfer
+    fn transfer_details(&self) -> Option<String> {
+        let context = self.imp().context.borrow();
+        let connection = Self::connection_name(context.connection_type.as_ref()?);
+
+        let mut lines = vec![gettextf("Connection: {}", &[&connection])];
+
+        if let Some(peer_addr) = context.peer_addr {
+            lines.push(gettextf("Peer Address: {}", &[&peer_addr]));
+        }
+
+        lines.push(gettextf(
+            "Rendezvous Server: {}",
+            &[&context.rendezvous_url],
+        ));
+
+        if let Some(progress) = &context.progress {
+            let done_bytes = if matches!(*context.ui_state, UIState::Done(_)) {
+                progress.total_bytes()
+            } else {
+                progress.done_bytes()
+            };
+
+            lines.push(gettextf(
+                "Transferred: {0} of {1}",
+                &[
+                    &glib::format_size(done_bytes as u64),
+                    &glib::format_size(progress.total_bytes() as u64),
+                ],
+            ));
+        }
+
+        Some(lines.join("\n"))
+    }
+
+    /// Size, duration, average speed and connection of the finished transfer
+    fn transfer_summary(&self) -> Option<String> {
+        let context = self.imp().context.borrow();
+        let progress = context.progress.as_ref()?;
+        let connection = Self::connection_name(context.connection_type.as_ref()?);
+
+        // The last progress sample can be older than the end of the transfer, so this uses the
+        // total size
+        let secs = progress.elapsed().as_secs_f64();
+        if secs <= 0.0 {
+            return None;
+        }
+        let average_bytes_s = (progress.total_bytes() as f64 / secs) as usize;
+        let speed = format_speed(
+            average_bytes_s,
+            self.window().config().speed_in_bits_or_default(),
+        );
+        let elapsed = chrono::Duration::from_std(progress.elapsed()).ok()?;
+
+        Some(gettextf(
+            // Translators: Summary of a finished transfer, {0} = 20.7 MB, {1} = 3 seconds, {2} = 6.9 MB/s, {3} = Direct
+            "{0} in {1} ({2}) · {3}",
+            &[
+                &glib::format_size(progress.total_bytes() as u64),
+                &duration::elapsed(&elapsed),
+                &speed,
+                &connection,
+            ],
+        ))
+    }
+
+    fn update_transfer_details(&self) {
+        self.imp()
+            .progress_details_label
+            .set_label(&self.transfer_details().unwrap_or_default());
     }
 
     fn show_progress_indeterminate(&self, pulse: bool) {
@@ -793,8 +1979,14 @@ impl ActionView {
 
         if pulse {
             // 50 ms was mainly chosen for performance of the progress bar
+            let interval = if self.window().low_power_mode() {
+                LOW_POWER_UPDATE_INTERVAL
+            } else {
+                Duration::from_millis(50)
+            };
+
             imp.context.borrow_mut().progress_timeout_source_id = Some(glib::timeout_add_local(
-                Duration::from_millis(50),
+                interval,
                 clone!(
                     #[strong(rename_to = obj)]
                     self,
@@ -808,30 +2000,124 @@ impl ActionView {
         }
     }
 
+    async fn prepare_and_open_files(
+        &self,
+        paths: Vec<PathBuf>,
+    ) -> Result<(smol::fs::File, PathBuf, OsString), AppError> {
+        if paths.len() == 1 {
+            self.prepare_and_open_file(&paths[0]).await
+        } else {
+            self.prepare_and_open_archive(paths).await
+        }
+    }
+
+    /// Combines multiple files and folders into a single archive to send
+    async fn prepare_and_open_archive(
+        &self,
+        paths: Vec<PathBuf>,
+    ) -> Result<(smol::fs::File, PathBuf, OsString), AppError> {
+        for path in &paths {
+            if path.is_dir() && !self.window().config().allow_send_folders_or_default() {
+                return Err(UiError::new(&gettext(
+                    "Sending folders is disabled in preferences. Please select individual files instead.",
+                ))
+                .into());
+            } else if !path.exists() {
+                return Err(UiError::new(&gettextf(
+                    // Translators: When opening multiple files, argument is the path
+                    "“{}” does not exist",
+                    &[&path.display()],
+                ))
+                .into());
+            }
+        }
+
+        // Translators: File name of the archive when sending multiple files
+        let mut filename = OsString::from(gettext("Files"));
+        self.set_ui_state(UIState::Archive(filename.clone(), paths.len()));
+        filename.push(".zip");
+
+        let options = self.window().config().zip_options();
+        let temp_file = fs::compress_files_cancelable(
+            paths,
+            options,
+            self.cancel_future(),
+            self.zip_progress_handler(),
+        )
+        .await?;
+
+        self.open_send_archive(SendArchive {
+            temp_file,
+            filename,
+        })
+    }
+
+    /// Opens an archive to send and keeps it until the transfer is over
+    fn open_send_archive(
+        &self,
+        archive: SendArchive,
+    ) -> Result<(smol::fs::File, PathBuf, OsString), AppError> {
+        let file = smol::fs::File::from(archive.temp_file.reopen()?);
+        let path = archive.temp_file.path().to_path_buf();
+        let filename = archive.filename.clone();
+        self.imp().context.borrow_mut().send_archive = Some(archive);
+
+        Ok((file, path, filename))
+    }
+
     async fn prepare_and_open_file(
         &self,
         path: &Path,
     ) -> Result<(smol::fs::File, PathBuf, OsString), AppError> {
-        let mut filename = if let Some(filename) = path.file_name() {
-            filename.to_os_string()
+        // The receiver doesn't have to extract an archive with just one file then
+        let single_file = if path.is_dir()
+            && self.window().config().allow_send_folders_or_default()
+            && self
+                .window()
+                .config()
+                .send_single_file_directly_or_default()
+        {
+            let include_hidden = self.window().config().include_hidden_files_or_default();
+            fs::single_file_in_folder(path, include_hidden)
         } else {
-            return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+            None
         };
-
+        let path = if let Some(file) = &single_file {
+            log::info!("Sending the only file of the folder: {}", file.display());
+            file
+        } else {
+            path
+        };
+
+        let mut filename = if let Some(filename) = path.file_name() {
+            filename.to_os_string()
+        } else {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+        };
+
         let (file, path) = if path.is_dir() {
-            self.set_ui_state(UIState::Archive(filename.clone()));
+            if !self.window().config().allow_send_folders_or_default() {
+                return Err(UiError::new(&gettext(
+                    "Sending folders is disabled in preferences. Please select individual files instead.",
+                ))
+                .into());
+            }
+
+            self.set_ui_state(UIState::Archive(filename.clone(), 1));
             filename.push(".zip");
 
+            let options = self.window().config().zip_options();
             let temp_file = fs::compress_folder_cancelable(
                 path,
+                options,
                 self.cancel_future(),
-                Self::zip_progress_handler,
+                self.zip_progress_handler(),
             )
             .await?;
-            (
-                smol::fs::File::from(temp_file.reopen()?),
-                temp_file.path().to_path_buf(),
-            )
+            return self.open_send_archive(SendArchive {
+                temp_file,
+                filename,
+            });
         } else if path.is_file() {
             (
                 smol::fs::OpenOptions::new().read(true).open(path).await?,
@@ -845,13 +2131,109 @@ impl ActionView {
         Ok((file, path, filename))
     }
 
-    fn prepare_transmit(&self, direction: TransferDirection) -> Result<(), AppError> {
-        self.reset();
+    /// The directory files are saved to when accepting a transfer without choosing a location
+    fn download_dir(&self) -> Result<PathBuf, AppError> {
+        if let Some(dir) = self.window().config().download_dir_override() {
+            return Ok(dir.to_path_buf());
+        }
 
-        if let Some(app) = self.app() {
-            app.inhibit_transfer(direction);
+        let (dir, home_fallback) = fs::default_download_dir()?;
+        if home_fallback {
+            let toast = adw::Toast::new(&gettext(
+                "The downloads folder is not available, saving to the home folder instead",
+            ));
+            toast.set_priority(adw::ToastPriority::High);
+            self.window().toast_overlay().add_toast(toast);
         }
 
+        Ok(dir)
+    }
+
+    /// Asks where to save the received file. The file chooser itself confirms replacing an
+    /// existing file, which is only replaced once the download is complete.
+    async fn choose_save_as_path(&self) -> Option<PathBuf> {
+        let imp = self.imp();
+        let window = self.window();
+
+        // Start in the folder used last time, unless it was removed since
+        let last_save_as_dir = window
+            .config()
+            .last_save_as_dir
+            .clone()
+            .filter(|dir| dir.is_dir());
+        if let Some(dir) = last_save_as_dir {
+            imp.save_as_file_dialog
+                .set_initial_folder(Some(&gio::File::for_path(dir)));
+        }
+
+        match imp.save_as_file_dialog.save_future(Some(&window)).await {
+            Ok(file) => {
+                let path = file.path()?;
+                if fs::is_portal_path(&path) {
+                    log::debug!("Selected portal path: '{}'", path.display());
+                } else {
+                    log::debug!("Selected path: '{}'", path.display());
+                }
+                window.config().last_save_as_dir = path.parent().map(Path::to_path_buf);
+                Some(path)
+            }
+            Err(err) => {
+                log::debug!("Save As file chooser error: {:?}", err);
+                None
+            }
+        }
+    }
+
+    /// Makes sure the file can be stored in the downloads folder before accepting it. Otherwise
+    /// we ask for another location right away instead of failing after the download.
+    async fn writable_download_path(&self) -> Result<Option<PathBuf>, AppError> {
+        let dir = self.download_dir()?;
+        let sandboxed = WarpApplication::is_flatpak();
+        let Err(err) = fs::probe_writable(&dir) else {
+            if fs::is_portal_path(&dir) {
+                log::debug!("Saving to portal path '{}'", dir.display());
+            }
+            return Ok(None);
+        };
+
+        log::warn!("Download dir '{}' is not writable: {}", dir.display(), err);
+        let message = if sandboxed
+            && matches!(
+                err.kind(),
+                std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::NotFound
+            ) {
+            // The file chooser portal grants access to the location selected below
+            gettextf("The folder “{}” is not shared with Warp", &[&dir.display()])
+        } else {
+            fs::unwritable_dir_message(&dir, &err)
+        };
+
+        let toast = adw::Toast::new(&gettextf(
+            // Translators: Notification before asking for another location, argument is the reason
+            "{}. Choose another location.",
+            &[&message],
+        ));
+        toast.set_priority(adw::ToastPriority::High);
+        self.window().toast_overlay().add_toast(toast);
+
+        match self.choose_save_as_path().await {
+            Some(path) => Ok(Some(path)),
+            None => Err(UiError::new(&message).into()),
+        }
+    }
+
+    /// Resets the context for a new transfer. The returned guard keeps the session from suspending
+    /// and has to be held until the transfer is over.
+    fn prepare_transmit(
+        &self,
+        direction: TransferDirection,
+    ) -> Result<Option<InhibitGuard>, AppError> {
+        self.reset();
+
+        let inhibit_guard = self
+            .app()
+            .map(|app| app.inhibit_transfer(&self.window(), direction));
+
         self.set_transfer_direction(direction);
         self.set_ui_state(UIState::Initial);
 
@@ -866,38 +2248,120 @@ impl ActionView {
             })?;
         self.imp().context.borrow_mut().rendezvous_url = rendezvous_url;
 
-        let transit_url = self.window().config().transit_relay_hints().map_err(|_| {
+        let mut transit_url = self.window().config().transit_relay_hints().map_err(|_| {
             UiError::new(&gettext(
                 "Error parsing transit URL. An invalid URL was entered in the settings.",
             ))
         })?;
+        // Start with the next relay when retrying after a relay failed
+        let relay_offset = self.imp().next_relay_offset.take();
+        if !transit_url.is_empty() {
+            transit_url.rotate_left(relay_offset % transit_url.len());
+        }
         self.imp().context.borrow_mut().relay_hints = transit_url;
+        self.imp().context.borrow_mut().relay_offset = relay_offset;
 
         self.window().show_action_view();
-        Ok(())
+        Ok(inhibit_guard)
     }
 
+    /// Connects to the rendezvous server. Network errors are retried with a growing delay, while
+    /// errors reported by the server are returned right away.
+    async fn connect_mailbox<F, Fut>(
+        &self,
+        connect: F,
+    ) -> Result<wormhole::MailboxConnection<wormhole::transfer::AppVersion>, AppError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<
+                Output = Result<
+                    wormhole::MailboxConnection<wormhole::transfer::AppVersion>,
+                    wormhole::WormholeError,
+                >,
+            > + Send
+            + 'static,
+    {
+        let mut delay = CONNECT_RETRY_DELAY;
+        let mut attempt = 1;
+
+        loop {
+            let res = Box::pin(spawn_async(cancelable_future(
+                connect(),
+                self.cancel_future(),
+            )))
+            .await?;
+
+            match res {
+                Err(err)
+                    if attempt < CONNECT_MAX_ATTEMPTS
+                        && AppError::is_transient_wormhole_error(&err) =>
+                {
+                    attempt += 1;
+                    log::warn!(
+                        "Connecting to the rendezvous server failed: {err}. Retrying in {} s ({attempt}/{CONNECT_MAX_ATTEMPTS})",
+                        delay.as_secs()
+                    );
+
+                    let toast = adw::Toast::new(&gettextf(
+                        // Translators: Notification when the rendezvous server can't be reached, {0} is the attempt, {1} the maximum attempts
+                        "Retrying ({0}/{1})…",
+                        &[&attempt, &CONNECT_MAX_ATTEMPTS],
+                    ));
+                    toast.set_timeout(delay.as_secs().try_into().unwrap_or(u32::MAX));
+                    self.window().toast_overlay().add_toast(toast);
+
+                    cancelable_future(glib::timeout_future(delay), self.cancel_future()).await?;
+                    delay *= 2;
+                }
+                res => return Ok(res?),
+            }
+        }
+    }
+
+    /// Receives a file. Without a code, we create one that is shown for the sender to enter.
     async fn transmit_receive(
         &self,
-        code: wormhole::Code,
+        code: Option<wormhole::Code>,
         app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
     ) -> Result<(), AppError> {
-        self.prepare_transmit(TransferDirection::Receive)?;
-        let uri = WormholeTransferURI::from_app_cfg_with_code_direction(
-            &app_cfg,
-            code.clone(),
-            TransferDirection::Send,
-        );
-        self.set_ui_state(UIState::HasCode(uri));
+        let _inhibit_guard = self.prepare_transmit(TransferDirection::Receive)?;
 
-        self.window().add_code(&code);
+        let connection = if let Some(code) = code {
+            let uri = WormholeTransferURI::from_app_cfg_with_code_direction(
+                &app_cfg,
+                code.clone(),
+                TransferDirection::Send,
+            );
+            self.set_ui_state(UIState::HasCode(uri));
+            self.add_code(&code);
 
-        let connection = Box::pin(spawn_async(cancelable_future(
-            wormhole::MailboxConnection::connect(app_cfg, code, false),
-            self.cancel_future(),
-        )))
-        .await??;
+            self.connect_mailbox(|| {
+                wormhole::MailboxConnection::connect(app_cfg.clone(), code.clone(), false)
+            })
+            .await?
+        } else {
+            self.imp().context.borrow_mut().receive_code_allocated = true;
+            self.set_ui_state(UIState::RequestCode);
+
+            let code_length = self.window().config().code_length_or_default();
+            let connection = self
+                .connect_mailbox(|| {
+                    wormhole::MailboxConnection::create(app_cfg.clone(), code_length)
+                })
+                .await?;
 
+            self.add_code(connection.code());
+            // The sender takes the leader role, which the link tells scanning apps
+            let uri = WormholeTransferURI::from_app_cfg_with_code_direction(
+                &app_cfg,
+                connection.code().to_owned(),
+                TransferDirection::Send,
+            );
+            self.set_ui_state(UIState::HasCode(uri));
+            connection
+        };
+
+        self.show_key_exchange();
         let wormhole = Box::pin(spawn_async(cancelable_future(
             wormhole::Wormhole::connect(connection),
             self.cancel_future(),
@@ -907,53 +2371,69 @@ impl ActionView {
         self.set_ui_state(UIState::Connected);
 
         let relay_url = self.imp().context.borrow().relay_hints.clone();
+        let transit_abilities = self.window().config().transit_abilities();
+        self.log_transit_setup(&relay_url, transit_abilities);
 
-        let request = spawn_async(wormhole::transfer::request_file(
+        let offer = spawn_async(receive::request(
             wormhole,
             relay_url,
-            TRANSIT_ABILITIES,
+            transit_abilities,
             self.cancel_future(),
         ))
         .await?
         .ok_or(AppError::Canceled)?;
 
-        // Only use the last filename component otherwise the other side can overwrite
-        // files in different directories
-        let offer_filename = request.file_name();
-        let offer_filename = if offer_filename.is_empty() {
-            // This shouldn't happen realistically
-            "Unknown Filename.bin".to_owned()
+        if offer.shortened {
+            let toast = adw::Toast::new(&gettext(
+                // Translators: Notification when the name of a received file is too long for the file system
+                "The file name is too long and was shortened",
+            ));
+            toast.set_timeout(5);
+            self.window().toast_overlay().add_toast(toast);
+        }
+        let offer_filename = offer.file_name.clone();
+
+        // Files over the size limit are rejected the same way as a declined confirmation
+        let max_size = self.window().config().max_receive_size;
+        let res = if offer.size_allowed(max_size) {
+            self.set_ui_state(UIState::AskConfirmation(
+                offer_filename.clone(),
+                offer.size(),
+            ));
+
+            // Continue or cancel
+            self.imp()
+                .save_as_file_dialog
+                .set_initial_name(Some(&offer_filename));
+            self.ask_confirmation_future().await
         } else {
-            offer_filename
+            log::info!(
+                "Rejecting file of {} bytes, which exceeds the maximum file size",
+                offer.size()
+            );
+            Err(self.file_too_large_error(&offer_filename, offer.size()))
         };
 
-        self.set_ui_state(UIState::AskConfirmation(
-            offer_filename.clone(),
-            request.file_size(),
-        ));
+        let res = match res {
+            Ok(None) => self.writable_download_path().await,
+            res => res,
+        };
 
-        // Continue or cancel
-        self.imp()
-            .save_as_file_dialog
-            .set_initial_name(Some(&offer_filename));
-        let res = self.ask_confirmation_future().await;
         let selected_download_file_path = match res {
             Ok(selected_path) => selected_path,
             Err(err) => {
-                smol::spawn(async move {
-                    if let Err(err) = request.reject().await {
-                        log::error!("An error occurred when rejecting a transfer: {}", err);
-                    }
-                })
-                .await;
-
+                smol::spawn(offer.reject()).await;
                 return Err(err);
             }
         };
 
-        let use_temp_path = selected_download_file_path.is_none();
-        let download_file_path = selected_download_file_path
-            .unwrap_or(super::fs::default_download_dir()?.join(offer_filename));
+        // A file chosen with “Save As” was already confirmed to be replaced
+        let save_as = selected_download_file_path.is_some();
+        let download_file_path = match selected_download_file_path {
+            Some(path) => path,
+            None => self.download_dir()?.join(&offer_filename),
+        };
+        let target = receive::Target::temporary(&download_file_path)?;
 
         self.set_ui_state(UIState::Connected);
 
@@ -961,72 +2441,44 @@ impl ActionView {
             app.withdraw_notification("receive-ready");
         }
 
-        let download_file_name =
-            PathBuf::from(download_file_path.file_name().ok_or_else(|| {
-                UiError::new(&gettextf(
-                    "Invalid path selected: {}",
-                    &[&download_file_path.display()],
-                ))
-            })?);
-
-        let temp_file = if use_temp_path {
-            let mut tempfile_prefix = download_file_name.as_os_str().to_os_string();
-            tempfile_prefix.push(".");
-
-            tempfile::Builder::new()
-                .prefix(&tempfile_prefix)
-                .suffix(&".warpdownload")
-                .tempfile_in(download_file_path.parent().unwrap_or(&PathBuf::from("./")))?
-        } else {
-            let file = std::fs::File::create(&download_file_path)?;
-            tempfile::NamedTempFile::from_parts(
-                file,
-                tempfile::TempPath::from_path(&download_file_path),
-            )
-        };
-
-        let async_file = smol::fs::File::from(temp_file.reopen()?);
-
         self.imp().context.borrow_mut().file_name =
-            Some(download_file_name.as_os_str().to_os_string());
-
-        log::info!(
-            "Downloading file to {:?}",
-            temp_file.path().to_string_lossy()
+            download_file_path.file_name().map(ToOwned::to_owned);
+
+        log::info!("Downloading file to {:?}", target.path().to_string_lossy());
+
+        let async_file = target.open()?;
+        let (transit_handler, relay_gate, relay_confirmation) = self.transit_handler();
+        let progress_handler = self.progress_handler();
+        let cancel_future = self.cancel_future();
+        let (res, ()) = futures::join!(
+            cancelable_future(
+                spawn_async(receive::accept(
+                    offer,
+                    async_file,
+                    relay_gate,
+                    transit_handler,
+                    progress_handler,
+                    cancel_future,
+                )),
+                self.cancel_timeout_future(TIMEOUT_MS),
+            ),
+            relay_confirmation
         );
 
-        let file = cancelable_future(
-            spawn_async(async move {
-                let mut file = async_file;
-                Box::pin(request.accept(
-                    Self::transit_handler_main,
-                    Self::progress_handler_main,
-                    &mut file,
-                    Self::cancel_future_main().await,
-                ))
-                .await?;
-                AppError::ok(file)
-            }),
-            Self::cancel_timeout_future(TIMEOUT_MS),
-        )
-        .await??;
-
+        // Refusing a relayed connection cancels the transfer, which then fails at the file
         if self.imp().context.borrow().canceled {
             return Err(AppError::Canceled);
         }
 
-        // Windows requires the file to be closed before renaming it
-        file.sync_all().await?;
-        drop(file);
+        res??;
 
         // Rename the file to its final name
-        let path = if use_temp_path {
-            safe_persist_tempfile(temp_file, &download_file_name)?
-        } else {
-            temp_file.keep().map_err(|err| err.error)?.1
-        };
+        let overwrite = save_as
+            || self.overwrite_policy(&download_file_path).await == OverwritePolicy::Overwrite;
+        let path = target.persist(overwrite)?;
 
         self.imp().context.borrow_mut().file_name = Some(path.file_name().unwrap().to_os_string());
+        self.window().set_recently_received(Some(path.clone()));
         self.imp()
             .context
             .borrow_mut()
@@ -1036,34 +2488,216 @@ impl ActionView {
         Ok(())
     }
 
+    /// Sends files. Without a code, we create one that is shown for the receiver to enter.
+    /// With `review`, multiple files are listed first to reorder or remove some.
     async fn transmit_send(
         &self,
-        path: PathBuf,
+        paths: Vec<PathBuf>,
+        code: Option<wormhole::Code>,
+        review: bool,
+        app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    ) -> Result<(), AppError> {
+        let send_archive = self.imp().next_send_archive.take();
+        let _inhibit_guard = self.prepare_transmit(TransferDirection::Send)?;
+        self.imp().context.borrow_mut().broadcast = self.imp().next_broadcast.take();
+        self.imp().context.borrow_mut().send_archive = send_archive;
+        let paths = if review && paths.len() > 1 {
+            self.review_send_queue(paths).await?
+        } else {
+            paths
+        };
+
+        // Downloaded files are gone after cancelling, so only these can be sent again
+        self.imp().context.borrow_mut().send_paths = Some(paths.clone());
+        self.transmit_send_prepared(paths, code, app_cfg).await
+    }
+
+    /// Shows the selected files and folders and waits until the user starts sending them
+    async fn review_send_queue(&self, paths: Vec<PathBuf>) -> Result<Vec<PathBuf>, AppError> {
+        let size_paths = paths.clone();
+        let sizes = spawn_async(smol::unblock(move || {
+            Ok::<_, AppError>(
+                size_paths
+                    .into_iter()
+                    .map(|path| {
+                        let size = send_queue::path_size(&path);
+                        (path, size)
+                    })
+                    .collect::<HashMap<_, _>>(),
+            )
+        }))
+        .await?;
+
+        {
+            let mut context = self.imp().context.borrow_mut();
+            context.send_queue = paths;
+            context.send_queue_sizes = sizes;
+        }
+        self.set_ui_state(UIState::Queue);
+
+        let mut continue_receiver = self.imp().context.borrow().continue_receiver.clone();
+        cancelable_future(continue_receiver.recv(), self.cancel_future()).await??;
+
+        let paths = std::mem::take(&mut self.imp().context.borrow_mut().send_queue);
+        log::info!("Sending {} reviewed files", paths.len());
+        Ok(paths)
+    }
+
+    /// Lists the queued files with their sizes and the total
+    fn update_send_queue(&self) {
+        let imp = self.imp();
+        imp.send_queue_list.remove_all();
+
+        let context = imp.context.borrow();
+        let size = |path: &PathBuf| {
+            context
+                .send_queue_sizes
+                .get(path)
+                .copied()
+                .unwrap_or_default()
+        };
+
+        for (index, path) in context.send_queue.iter().enumerate() {
+            let row = send_queue::row(
+                path,
+                size(path),
+                index,
+                context.send_queue.len(),
+                clone!(
+                    #[weak(rename_to = obj)]
+                    self,
+                    move |index| {
+                        obj.imp().context.borrow_mut().send_queue.remove(index);
+                        obj.update_send_queue();
+                    }
+                ),
+                clone!(
+                    #[weak(rename_to = obj)]
+                    self,
+                    move |from, to| {
+                        send_queue::move_item(
+                            &mut obj.imp().context.borrow_mut().send_queue,
+                            from,
+                            to,
+                        );
+                        obj.update_send_queue();
+
+                        // Keep the focus on the moved item when moving it with the keyboard
+                        if let Some(row) = i32::try_from(to)
+                            .ok()
+                            .and_then(|to| obj.imp().send_queue_list.row_at_index(to))
+                        {
+                            row.grab_focus();
+                        }
+                    }
+                ),
+            );
+            imp.send_queue_list.append(&row);
+        }
+
+        let count = u32::try_from(context.send_queue.len()).unwrap_or(u32::MAX);
+        let total = context.send_queue.iter().map(size).sum::<u64>();
+        imp.status_page_queue.set_description(Some(&ngettextf(
+            // Translators: Description when reviewing the files to send, arguments are the number of items and the total size
+            "{0} item, {1} in total",
+            "{0} items, {1} in total",
+            count,
+            &[&count, &glib::format_size(total)],
+        )));
+        imp.send_queue_button.set_sensitive(count > 0);
+    }
+
+    async fn transmit_send_url(
+        &self,
+        url: url::Url,
+        app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    ) -> Result<(), AppError> {
+        let _inhibit_guard = self.prepare_transmit(TransferDirection::Send)?;
+        self.set_ui_state(UIState::Download(url.clone()));
+
+        // The downloaded file is deleted together with the directory once the transfer is over
+        let (_download_dir, path) = fs::download_url_cancelable(
+            &url,
+            self.cancel_future(),
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move |current, total| obj.download_progress(current, total)
+            ),
+        )
+        .await?;
+
+        self.show_progress_indeterminate(false);
+        self.transmit_send_prepared(vec![path], None, app_cfg).await
+    }
+
+    fn download_progress(&self, current: i64, total: i64) {
+        let imp = self.imp();
+        if total <= 0 {
+            return;
+        }
+
+        self.show_progress_indeterminate(false);
+        imp.progress_bar.set_fraction(current as f64 / total as f64);
+        imp.progress_bar.set_text(Some(&pgettextf(
+            "File size transferred",
+            "{0} / {1}",
+            &[
+                &glib::format_size(current as u64),
+                &glib::format_size(total as u64),
+            ],
+        )));
+    }
+
+    async fn transmit_send_prepared(
+        &self,
+        paths: Vec<PathBuf>,
+        code: Option<wormhole::Code>,
         app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
     ) -> Result<(), AppError> {
-        self.prepare_transmit(TransferDirection::Send)?;
         self.set_ui_state(UIState::RequestCode);
 
         let window = self.window();
 
-        let (mut file, path, filename) = self.prepare_and_open_file(&path).await?;
+        let send_archive = self.imp().context.borrow_mut().send_archive.take();
+        let (file, path, filename) = match send_archive {
+            Some(archive) => {
+                log::info!("Sending the archive again");
+                self.open_send_archive(archive)?
+            }
+            None => self.prepare_and_open_files(paths).await?,
+        };
         self.imp().context.borrow_mut().file_name = Some(filename.clone());
+        self.imp().context.borrow_mut().note = window.send_note();
         let code_length = window.config().code_length_or_default();
 
-        let connection = spawn_async(cancelable_future(
-            wormhole::MailboxConnection::create(app_cfg.clone(), code_length),
-            self.cancel_future(),
-        ))
-        .await??;
+        let connection = if let Some(code) = code {
+            // The receiver created the code, so there is nothing to show
+            self.set_ui_state(UIState::RequestCode);
+            self.add_code(&code);
 
-        window.add_code(connection.code());
-        let uri = WormholeTransferURI::from_app_cfg_with_code_direction(
-            &app_cfg,
-            connection.code().to_owned(),
-            TransferDirection::Receive,
-        );
-        self.set_ui_state(UIState::HasCode(uri));
+            self.connect_mailbox(|| {
+                wormhole::MailboxConnection::connect(app_cfg.clone(), code.clone(), false)
+            })
+            .await?
+        } else {
+            let connection = self
+                .connect_mailbox(|| {
+                    wormhole::MailboxConnection::create(app_cfg.clone(), code_length)
+                })
+                .await?;
 
+            self.add_code(connection.code());
+            let uri = WormholeTransferURI::from_app_cfg_with_code_direction(
+                &app_cfg,
+                connection.code().to_owned(),
+                TransferDirection::Receive,
+            );
+            self.set_ui_state(UIState::HasCode(uri));
+            connection
+        };
+
+        self.show_key_exchange();
         let wormhole = spawn_async(cancelable_future(
             wormhole::Wormhole::connect(connection),
             self.cancel_future(),
@@ -1074,32 +2708,45 @@ impl ActionView {
 
         self.imp().context.borrow_mut().file_path = Some(path);
         let transit_url = self.imp().context.borrow().relay_hints.clone();
+        let transit_abilities = window.config().transit_abilities();
+        self.log_transit_setup(&transit_url, transit_abilities);
 
         let metadata = file.metadata().await?;
+        let (transit_handler, relay_gate, relay_confirmation) = self.transit_handler();
+        let progress_handler = self.progress_handler();
+        let cancel_future = self.cancel_future();
+
+        // Sending starts with the offer, the transit connection follows once it was accepted
+        self.set_ui_state(UIState::AwaitingAcceptance);
+        let (res, ()) = futures::join!(
+            cancelable_future(
+                spawn_async(async move {
+                    let mut file = relay_gate.wrap(file);
+                    Box::pin(wormhole::transfer::send_file(
+                        wormhole,
+                        transit_url,
+                        &mut file,
+                        filename.to_string_lossy(),
+                        metadata.len(),
+                        transit_abilities,
+                        transit_handler,
+                        progress_handler,
+                        cancel_future,
+                    ))
+                    .await
+                }),
+                self.cancel_timeout_future(TIMEOUT_MS),
+            ),
+            relay_confirmation
+        );
 
-        cancelable_future(
-            spawn_async(async move {
-                Box::pin(wormhole::transfer::send_file(
-                    wormhole,
-                    transit_url,
-                    &mut file,
-                    filename.to_string_lossy(),
-                    metadata.len(),
-                    TRANSIT_ABILITIES,
-                    Self::transit_handler_main,
-                    Self::progress_handler_main,
-                    Self::cancel_future_main().await,
-                ))
-                .await
-            }),
-            Self::cancel_timeout_future(TIMEOUT_MS),
-        )
-        .await??;
-
+        // Refusing a relayed connection cancels the transfer, which then fails at the file
         if self.imp().context.borrow().canceled {
             return Err(AppError::Canceled);
         }
 
+        res??;
+
         self.transmit_success();
 
         Ok(())
@@ -1118,11 +2765,6 @@ impl ActionView {
         }
     }
 
-    /// This future will finish when a message is received in the cancellation channel
-    async fn cancel_future_main() -> impl Future<Output = ()> {
-        block_on_main_with_app_async(|app| app.main_window().action_view().cancel_future()).await
-    }
-
     /// This future will finish when a message is received in the cancellation channel
     fn cancel_future(&self) -> impl Future<Output = ()> + use<> {
         let cancel_receiver = self.imp().context.borrow().cancel_receiver.clone();
@@ -1134,11 +2776,12 @@ impl ActionView {
     /// We will wait until a cancellation event is received. Then we give the wormhole code
     /// `timeout_ms` milliseconds to properly respond. When there is no response the future will
     /// be finished
-    fn cancel_timeout_future(timeout_ms: u64) -> impl Future<Output = ()> {
+    fn cancel_timeout_future(&self, timeout_ms: u64) -> impl Future<Output = ()> + use<> {
         let (sender, receiver) = async_broadcast::broadcast(1);
+        let cancel_future = self.cancel_future();
         async move {
             // Wait for a cancellation event
-            Self::cancel_future_main().await.await;
+            cancel_future.await;
 
             // Then do a timeout
             glib::timeout_add_once(Duration::from_millis(timeout_ms), move || {
@@ -1152,10 +2795,211 @@ impl ActionView {
         }
     }
 
+    /// How to save a received file to `path`, asking the user on a collision if requested
+    async fn overwrite_policy(&self, path: &Path) -> OverwritePolicy {
+        let policy = self.window().config().overwrite_policy_or_default();
+        if policy != OverwritePolicy::Ask {
+            return policy;
+        }
+
+        if !path.exists() {
+            return OverwritePolicy::Rename;
+        }
+
+        let filename = path
+            .file_name()
+            .map(OsStr::to_string_lossy)
+            .unwrap_or_default();
+        // The download folder can be changed, or fall back to the home folder
+        let folder = path
+            .parent()
+            .and_then(Path::file_name)
+            .map(OsStr::to_string_lossy)
+            .unwrap_or_default();
+        let dialog = adw::AlertDialog::builder()
+            .heading(gettext("Replace Existing File?"))
+            .body(gettextf(
+                // Translators: {0} = filename, {1} = folder name
+                "A file named “{0}” already exists in the folder “{1}”. Replacing it will overwrite its contents.",
+                &[&filename, &folder],
+            ))
+            .close_response("rename")
+            .default_response("rename")
+            .build();
+
+        dialog.add_response("rename", &gettext("_Keep Both"));
+        dialog.add_response("overwrite", &gettext("_Replace"));
+        dialog.set_response_appearance("overwrite", adw::ResponseAppearance::Destructive);
+
+        if dialog.choose_future(self).await == "overwrite" {
+            OverwritePolicy::Overwrite
+        } else {
+            OverwritePolicy::Rename
+        }
+    }
+
+    /// Asks whether a relayed connection should be used for the transfer. No file data is
+    /// transferred until the user answered.
+    async fn ask_relay_confirmation(
+        &self,
+        relay_name: Option<&str>,
+        peer_addr: SocketAddr,
+    ) -> bool {
+        let body = if let Some(relay_name) = relay_name {
+            gettextf(
+                // Translators: {0} = relay name, {1} = IP address and port
+                "A direct connection to your peer could not be established. The file would be transferred via the relay “{0}”.\n\nPeer address: {1}",
+                &[&relay_name, &peer_addr],
+            )
+        } else {
+            gettextf(
+                // Translators: {} = IP address and port
+                "A direct connection to your peer could not be established. The file would be transferred via a relay.\n\nPeer address: {}",
+                &[&peer_addr],
+            )
+        };
+
+        let dialog = adw::AlertDialog::builder()
+            .heading(gettext("Transfer via Relay?"))
+            .body(body)
+            .close_response("abort")
+            .default_response("abort")
+            .build();
+
+        dialog.add_response("abort", &gettext("_Abort"));
+        dialog.add_response("continue", &gettext("_Continue"));
+        dialog.set_response_appearance("abort", adw::ResponseAppearance::Destructive);
+
+        // The transfer may be canceled otherwise while we wait, e.g. when closing the window
+        match cancelable_future(dialog.choose_future(self), self.cancel_future()).await {
+            Ok(response) => response == "continue",
+            Err(_) => {
+                dialog.force_close();
+                false
+            }
+        }
+    }
+
+    /// A thread safe reference to this view, for callbacks of transfers running on other threads.
+    ///
+    /// Every window has its own view, so callbacks must not look up the view via the application.
+    fn send_weak_ref(&self) -> glib::SendWeakRef<Self> {
+        self.downgrade().into()
+    }
+
+    /// The callback for the transit connection of a transfer, the gate for its file and a future
+    /// that asks to confirm a relayed connection if this was requested in preferences. The gate
+    /// holds the file data until the user answered. The future has to be awaited alongside the
+    /// transfer and finishes with it.
+    fn transit_handler(
+        &self,
+    ) -> (
+        impl Fn(wormhole::transit::TransitInfo) + Send + Sync + 'static,
+        RelayGate,
+        impl Future<Output = ()> + use<>,
+    ) {
+        let view = self.send_weak_ref();
+        let gate = RelayGate::default();
+        let (relay_sender, relay_receiver) = if self
+            .window()
+            .config()
+            .confirm_relayed_transfers_or_default()
+        {
+            let (sender, receiver) = async_channel::bounded(1);
+            (Some(sender), Some(receiver))
+        } else {
+            (None, None)
+        };
+
+        let handler_gate = gate.clone();
+        let handler = move |info: wormhole::transit::TransitInfo| {
+            if let (Some(sender), wormhole::transit::ConnectionType::Relay { name }) =
+                (&relay_sender, &info.conn_type)
+            {
+                // Without a request, the answer is dropped and the connection refused
+                let answer = handler_gate.ask();
+                if let Err(err) = sender.try_send((name.clone(), info.peer_addr, answer)) {
+                    log::error!("Error sending relay confirmation request: {:?}", err);
+                }
+            }
+
+            Self::transit_handler_main(&view, info);
+        };
+
+        let obj = self.clone();
+        let relay_confirmation = async move {
+            let Some(receiver) = relay_receiver else {
+                return;
+            };
+
+            // The channel is closed without a relay when the transfer ends
+            let Ok((name, peer_addr, answer)) = receiver.recv().await else {
+                return;
+            };
+
+            let confirmed = obj.ask_relay_confirmation(name.as_deref(), peer_addr).await;
+            if !confirmed && !obj.imp().context.borrow().canceled {
+                log::info!("Relayed connection was not confirmed, cancelling transfer");
+                // Marked before the refused gate ends the transfer, so it counts as canceled
+                obj.imp().context.borrow_mut().canceled = true;
+                // Cancelling waits for the transfer to end, which includes this future
+                let cancel_obj = obj.clone();
+                main_async_local_infallible(async move { cancel_obj.cancel().await });
+            }
+
+            if answer.send(confirmed).is_err() {
+                log::debug!("The transfer ended before the relayed connection was confirmed");
+            }
+        };
+
+        (handler, gate, relay_confirmation)
+    }
+
+    /// Adds a line to the transit log of this transfer, if logging connection details is turned on
+    fn log_transit(&self, message: String) {
+        if !self.window().config().debug_transit_or_default() {
+            return;
+        }
+
+        log::info!("Transit: {}", message);
+        let time = glib::DateTime::now_local()
+            .and_then(|now| now.format("%T"))
+            .map_or_else(|_| String::new(), |time| time.to_string());
+        self.imp()
+            .context
+            .borrow_mut()
+            .transit_log
+            .push(format!("[{time}] {message}"));
+    }
+
+    /// Logs what we offer to the peer before the transit connection is negotiated
+    fn log_transit_setup(
+        &self,
+        relay_hints: &[wormhole::transit::RelayHint],
+        abilities: wormhole::transit::Abilities,
+    ) {
+        self.log_transit(format!("Offering {abilities:?}"));
+        for hint in relay_hints {
+            self.log_transit(format!("Relay hint: {hint:?}"));
+        }
+        if relay_hints.is_empty() {
+            self.log_transit("No relay hints".to_owned());
+        }
+    }
+
+    fn progress_handler(&self) -> impl Fn(u64, u64) + Send + Sync + 'static {
+        let view = self.send_weak_ref();
+        move |sent, total| Self::progress_handler_main(&view, sent, total)
+    }
+
     /// Callback with information about the currently running transfer
-    fn transit_handler_main(info: wormhole::transit::TransitInfo) {
-        invoke_main_with_app(move |app| {
-            let obj = app.main_window().action_view();
+    ///
+    /// This is called from within the transfer task, so it must not block.
+    fn transit_handler_main(view: &glib::SendWeakRef<Self>, info: wormhole::transit::TransitInfo) {
+        let message = format!("Connected via {:?} to {}", info.conn_type, info.peer_addr);
+        invoke_main_with(view, move |obj: Self| obj.log_transit(message));
+
+        invoke_main_with(view, move |obj: Self| {
             let imp = obj.imp();
 
             let filename = imp
@@ -1170,44 +3014,95 @@ impl ActionView {
     }
 
     /// Handles progress information updates
-    fn progress_handler_main(sent: u64, total: u64) {
-        invoke_main_with_app(move |app| {
-            let obj = app.main_window().action_view();
+    fn progress_handler_main(view: &glib::SendWeakRef<Self>, sent: u64, total: u64) {
+        invoke_main_with(view, move |obj: Self| {
             let imp = obj.imp();
 
             if imp.context.borrow().progress.is_none() {
-                imp.context.borrow_mut().progress =
-                    Some(FileTransferProgress::begin(total as usize));
+                let mut progress = FileTransferProgress::begin(total as usize);
+                if obj.window().low_power_mode() {
+                    progress.set_min_update_interval(LOW_POWER_UPDATE_INTERVAL);
+                }
+
+                imp.context.borrow_mut().progress = Some(progress);
             }
 
-            let mut update_progress = false;
-            let Some((fraction, progress_str)) =
-                imp.context.borrow_mut().progress.as_mut().map(|progress| {
-                    update_progress = progress.set_progress(sent as usize);
-                    (
-                        progress.progress_fraction(),
-                        progress.pretty_time_remaining(),
-                    )
-                })
-            else {
-                return;
-            };
+            let update_progress = imp
+                .context
+                .borrow_mut()
+                .progress
+                .as_mut()
+                .is_some_and(|progress| progress.set_progress(sent as usize));
 
             if update_progress {
-                imp.progress_bar.set_fraction(fraction);
-                imp.progress_bar
-                    .set_text(Some(&progress_str.unwrap_or_default()));
+                if imp.progress_details_expander.is_expanded() {
+                    obj.update_transfer_details();
+                }
+
+                obj.show_latest_progress();
+
+                if let Some(app) = obj.app() {
+                    app.emit_transfer_event(&TransferEvent::Progress {
+                        direction: obj.direction_name(),
+                        bytes: sent,
+                        total,
+                    });
+                    app.print_receive_progress(&obj.window(), sent, total);
+                }
             }
         });
     }
 
-    fn zip_progress_handler(count: usize, size: usize) {
-        invoke_main_with_app(move |app| {
-            let obj = app.main_window().action_view();
-            let imp = obj.imp();
+    /// Shows the latest known progress, which is otherwise only updated with new samples
+    fn show_latest_progress(&self) {
+        let imp = self.imp();
+        let speed_in_bits = self.window().config().speed_in_bits_or_default();
+        let progress_text = self.window().config().progress_text_or_default();
+        let Some(snapshot) = imp
+            .context
+            .borrow()
+            .progress
+            .as_ref()
+            .map(|progress| progress.snapshot(speed_in_bits, progress_text))
+        else {
+            return;
+        };
 
-            let size_str = glib::format_size(size as u64);
-            let data_str = ngettextf(
+        imp.progress_bar.set_fraction(snapshot.fraction);
+        imp.progress_bar
+            .set_text(Some(&snapshot.text.unwrap_or_default()));
+    }
+
+    fn zip_progress_handler(&self) -> impl Fn(ZipProgress) + Send + 'static {
+        let view = self.send_weak_ref();
+        move |progress| {
+            Self::zip_progress_handler_main(&view, progress);
+        }
+    }
+
+    fn zip_progress_handler_main(view: &glib::SendWeakRef<Self>, progress: ZipProgress) {
+        invoke_main_with(view, move |obj: Self| {
+            let imp = obj.imp();
+            let ZipProgress {
+                files: count,
+                size,
+                estimated_total: total,
+                skipped,
+                hidden,
+            } = progress;
+            imp.context.borrow_mut().skipped_files = skipped;
+            imp.context.borrow_mut().hidden_files = hidden;
+
+            let size_str = if total > 0 {
+                // Translators: Size of the files added to the archive so far, estimated size of the archive
+                gettextf(
+                    "{0} of about {1}",
+                    &[&glib::format_size(size), &glib::format_size(total)],
+                )
+            } else {
+                glib::format_size(size)
+            };
+            let mut data_str = ngettextf(
                 // Translators: Above progress bar for creating an archive to send as a folder
                 "{} File - Size: {}",
                 "{} Files - Size: {}",
@@ -1215,23 +3110,63 @@ impl ActionView {
                 &[&count.to_string(), &size_str],
             );
 
+            if skipped > 0 {
+                data_str.push_str(" - ");
+                data_str.push_str(&ngettextf_(
+                    // Translators: Above progress bar for creating an archive, files that are not included
+                    "{} Skipped",
+                    "{} Skipped",
+                    skipped as u32,
+                ));
+            }
+
+            if hidden > 0 {
+                data_str.push_str(" - ");
+                data_str.push_str(&ngettextf_(
+                    // Translators: Above progress bar for creating an archive, hidden files that are excluded
+                    "{} Hidden Excluded",
+                    "{} Hidden Excluded",
+                    hidden as u32,
+                ));
+            }
+
             log::trace!("ZIP progress: {}", data_str);
             imp.progress_bar.set_text(Some(&data_str));
         });
     }
 
+    fn file_too_large_error(&self, filename: &str, size: u64) -> AppError {
+        let max_size = self.window().config().max_receive_size.unwrap_or_default();
+        UiError::new(&gettextf(
+            // Translators: Filename, File size, Maximum file size
+            "Your peer wanted to send you “{0}” (Size: {1}). The file was rejected, because it is larger than the maximum file size of {2} set in preferences.",
+            &[
+                &filename,
+                &glib::format_size(size),
+                &glib::format_size(max_size),
+            ],
+        ))
+        .into()
+    }
+
     async fn ask_confirmation_future(&self) -> Result<Option<PathBuf>, AppError> {
         let mut continue_receiver = self.imp().context.borrow().continue_receiver.clone();
         let result = cancelable_future(continue_receiver.recv(), self.cancel_future()).await??;
         Ok(result)
     }
 
+    /// Remembers the code of this transfer, so the window doesn't insert it from the clipboard
+    fn add_code(&self, code: &wormhole::Code) {
+        self.imp().context.borrow_mut().code = Some(code.clone());
+        self.window().add_code(code);
+    }
+
     /// Any post-transfer cleanup operations that are shared between success and failure states
     pub fn transmit_cleanup(&self) {
         log::debug!("Transmit cleanup");
-
-        if let Some(app) = self.app() {
-            app.uninhibit_transfer();
+        let code = self.imp().context.borrow_mut().code.take();
+        if let Some(code) = code {
+            self.window().end_generated_code(&code);
         }
 
         if self.imp().context.borrow().canceled {
@@ -1253,8 +3188,12 @@ impl ActionView {
         log::debug!("Reset");
         let imp = self.imp();
         self.show_progress_indeterminate(false);
+        self.cancel_return_home();
+        self.stop_code_waiting_timer();
+        self.stop_connecting_timer();
 
         imp.context.replace(UIContext::default());
+        self.update_copy_actions();
 
         // Deletes any temporary files if required
         imp.context.borrow_mut().file_path = None;
@@ -1263,6 +3202,10 @@ impl ActionView {
     fn transmit_success(&self) {
         log::debug!("Transmit success");
 
+        if self.offer_to_next_receiver() {
+            return;
+        }
+
         self.imp().progress_bar.set_fraction(1.0);
 
         let file_name = self
@@ -1276,9 +3219,60 @@ impl ActionView {
         self.set_ui_state(UIState::Done(file_name));
 
         self.transmit_cleanup();
+        self.report_result(&self.transfer_result(None));
+    }
+
+    /// When sending to several receivers, this starts the next round with a new code until all
+    /// of them got the files. Returns whether there is another round.
+    fn offer_to_next_receiver(&self) -> bool {
+        let (broadcast, send_paths) = {
+            let context = self.imp().context.borrow();
+            (context.broadcast, context.send_paths.clone())
+        };
+        let (Some(broadcast), Some(paths)) = (broadcast, send_paths) else {
+            return false;
+        };
+
+        let delivered = broadcast.delivered + 1;
+        if delivered >= broadcast.total {
+            return false;
+        }
+
+        log::info!(
+            "Delivered to {} of {} receivers, offering again",
+            delivered,
+            broadcast.total
+        );
+        self.imp().context.borrow_mut().broadcast = Some(Broadcast {
+            delivered,
+            ..broadcast
+        });
+        // The result is only reported once all receivers got the files or sending was stopped
+        self.transmit_cleanup();
+        let send_archive = self.imp().context.borrow_mut().send_archive.take();
+        self.imp().next_send_archive.set(send_archive);
+        self.send_files_again(paths, self.window().config().app_cfg());
+        true
     }
 
     pub fn transmit_error(&self, error: AppError) {
+        // Stopping to send to further receivers doesn't undo the delivered transfers
+        let stopped_broadcast = error.is_user_canceled()
+            && self
+                .imp()
+                .context
+                .borrow()
+                .broadcast
+                .is_some_and(|broadcast| broadcast.delivered > 0);
+        let result = if stopped_broadcast {
+            self.transfer_result(None)
+        } else {
+            self.transfer_result(Some(&error))
+        };
+        if error.is_transit_error() {
+            self.log_transit(format!("Failed: {error:?}"));
+        }
+
         if *self.ui_state() == UIState::Initial {
             log::debug!("Transmit error, handle with dialog");
             error.handle();
@@ -1288,12 +3282,48 @@ impl ActionView {
         }
 
         self.transmit_cleanup();
+        self.report_result(&result);
     }
 
-    pub fn transmit_error_handler_main(error: AppError) {
-        invoke_main_with_app(|app| {
-            app.main_window().action_view().transmit_error(error);
-        });
+    /// The outcome of the transfer for scripts, see `--result-file`
+    fn transfer_result(&self, error: Option<&AppError>) -> TransferResult {
+        let context = self.imp().context.borrow();
+        let path = context.file_path_received_successfully.clone();
+        let bytes = path
+            .as_ref()
+            .or(context.file_path.as_ref())
+            .filter(|_| error.is_none())
+            .and_then(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len());
+
+        TransferResult {
+            success: error.is_none(),
+            direction: self.direction_name(),
+            file_name: context
+                .file_name
+                .as_ref()
+                .map(|name| name.to_string_lossy().into_owned()),
+            path,
+            bytes,
+            error: error.map(ToString::to_string),
+        }
+    }
+
+    fn report_result(&self, result: &TransferResult) {
+        if let Some(app) = self.app() {
+            app.report_transfer_result(&self.window(), result);
+        }
+    }
+
+    fn transmit_error_handler(&self) -> impl FnOnce(AppError) + 'static {
+        let obj = self.downgrade();
+        move |error| {
+            if let Some(obj) = obj.upgrade() {
+                obj.transmit_error(error);
+            } else {
+                error.handle();
+            }
+        }
     }
 
     pub fn send_file(
@@ -1302,10 +3332,82 @@ impl ActionView {
         app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
     ) {
         log::info!("Sending file: {}", path.display());
+        self.send_files(vec![path], app_cfg);
+    }
+
+    /// Downloads a file from the web and sends it
+    pub fn send_url(
+        &self,
+        url: url::Url,
+        app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    ) {
+        log::info!("Sending file from URL: {}", url);
+        let obj = self.clone();
+
+        main_async_local(self.transmit_error_handler(), async move {
+            Box::pin(obj.transmit_send_url(url, app_cfg)).await?;
+            Ok(())
+        });
+    }
+
+    /// Sends multiple files and folders by combining them into an archive
+    pub fn send_files(
+        &self,
+        paths: Vec<PathBuf>,
+        app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    ) {
+        if paths.len() > 1 {
+            log::info!("Sending {} files", paths.len());
+        }
+
+        let obj = self.clone();
+
+        let receivers = self.window().send_receivers();
+        if receivers > 1 {
+            log::info!("Sending to {} receivers in turn", receivers);
+        }
+        self.imp()
+            .next_broadcast
+            .set((receivers > 1).then_some(Broadcast {
+                delivered: 0,
+                total: receivers,
+            }));
+
+        main_async_local(self.transmit_error_handler(), async move {
+            Box::pin(obj.transmit_send(paths, None, true, app_cfg)).await?;
+            Ok(())
+        });
+    }
+
+    /// Sends files that were reviewed before, for a new code or another relay
+    fn send_files_again(
+        &self,
+        paths: Vec<PathBuf>,
+        app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    ) {
         let obj = self.clone();
+        self.imp()
+            .next_broadcast
+            .set(self.imp().context.borrow().broadcast);
 
-        main_async_local(Self::transmit_error_handler_main, async move {
-            Box::pin(obj.transmit_send(path, app_cfg)).await?;
+        main_async_local(self.transmit_error_handler(), async move {
+            Box::pin(obj.transmit_send(paths, None, false, app_cfg)).await?;
+            Ok(())
+        });
+    }
+
+    /// Sends a file with a code the receiver created
+    pub fn send_file_with_code(
+        &self,
+        path: PathBuf,
+        code: wormhole::Code,
+        app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    ) {
+        log::info!("Sending file with code '{}': {}", code, path.display());
+        let obj = self.clone();
+
+        main_async_local(self.transmit_error_handler(), async move {
+            Box::pin(obj.transmit_send(vec![path], Some(code), false, app_cfg)).await?;
             Ok(())
         });
     }
@@ -1318,8 +3420,22 @@ impl ActionView {
         log::info!("Receiving file with code '{}'", code);
         let obj = self.clone();
 
-        main_async_local(Self::transmit_error_handler_main, async move {
-            Box::pin(obj.transmit_receive(code, app_cfg)).await?;
+        main_async_local(self.transmit_error_handler(), async move {
+            Box::pin(obj.transmit_receive(Some(code), app_cfg)).await?;
+            Ok(())
+        });
+    }
+
+    /// Receives a file with a newly created code, which the sender enters or scans
+    pub fn receive_file_new_code(
+        &self,
+        app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    ) {
+        log::info!("Receiving file with a new code");
+        let obj = self.clone();
+
+        main_async_local(self.transmit_error_handler(), async move {
+            Box::pin(obj.transmit_receive(None, app_cfg)).await?;
             Ok(())
         });
     }
@@ -1,23 +1,43 @@
+use crate::config::{ColorScheme, PersistentConfig};
 use crate::gettext::*;
 use crate::globals;
 use crate::ui::window::WarpApplicationWindow;
 use crate::util::TransferDirection;
+use crate::util::error::UiError;
+use crate::util::future::main_async_local_infallible;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::clone;
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
 
 mod imp {
     use super::*;
-    use crate::util::WormholeTransferURI;
-    use crate::util::error::{AppError, UiError};
-    use glib::WeakRef;
-    use std::cell::Cell;
-    use std::cell::OnceCell;
+    use crate::util::error::AppError;
+    use crate::util::{WormholeTransferURI, read_transmit_code};
+    use std::cell::{Cell, RefCell};
+    use std::ops::ControlFlow;
 
     #[derive(Debug, Default)]
     pub struct WarpApplication {
-        pub window: OnceCell<WeakRef<WarpApplicationWindow>>,
-        pub inhibit_cookie: Cell<Option<u32>>,
+        /// Whether the user was told about slower relayed transfers in this session
+        pub relay_info_shown: Cell<bool>,
+
+        /// Where to write the outcome of transfers, set with `--result-file`
+        pub result_file: RefCell<Option<PathBuf>>,
+        /// Quit once a transfer is over, set with `--quit-after-transfer`
+        pub quit_after_transfer: Cell<bool>,
+        /// Code or link to receive right away, set with `--receive`
+        pub receive_code: RefCell<Option<String>>,
+        /// The window receiving the `--receive` code, whose progress and result are printed
+        pub receive_window: glib::WeakRef<WarpApplicationWindow>,
+        /// Where to send transfer events for embedding apps, set with `--event-socket`
+        pub event_socket_path: RefCell<Option<PathBuf>>,
+        #[cfg(target_os = "linux")]
+        pub event_socket: RefCell<Option<crate::ui::event_socket::EventSocket>>,
+        /// The event sound that is playing, which stops when dropped
+        pub event_sound: RefCell<Option<gtk::MediaFile>>,
     }
 
     #[glib::object_subclass]
@@ -30,24 +50,67 @@ mod imp {
     impl ObjectImpl for WarpApplication {}
 
     impl ApplicationImpl for WarpApplication {
+        fn handle_local_options(&self, options: &glib::VariantDict) -> ControlFlow<glib::ExitCode> {
+            if let Ok(Some(path)) = options.lookup::<PathBuf>("result-file") {
+                log::info!("Writing transfer results to '{}'", path.display());
+                self.result_file.replace(Some(path));
+            }
+
+            self.quit_after_transfer
+                .set(options.contains("quit-after-transfer"));
+
+            if let Ok(Some(path)) = options.lookup::<PathBuf>("event-socket") {
+                self.event_socket_path.replace(Some(path));
+            }
+
+            if let Ok(Some(code)) = options.lookup::<String>("receive") {
+                let code = if code == "-" {
+                    match read_transmit_code(std::io::stdin().lock()) {
+                        Ok(Some(code)) => code,
+                        Ok(None) => {
+                            log::error!("No code to receive on standard input");
+                            return ControlFlow::Break(glib::ExitCode::FAILURE);
+                        }
+                        Err(err) => {
+                            log::error!("Error reading the code from standard input: {err}");
+                            return ControlFlow::Break(glib::ExitCode::FAILURE);
+                        }
+                    }
+                } else {
+                    code
+                };
+
+                // The progress and saved path are printed by this process, so it has to run the
+                // transfer itself
+                if let Err(err) = self.obj().register(gio::Cancellable::NONE) {
+                    log::error!("Error registering the application: {err}");
+                    return ControlFlow::Break(glib::ExitCode::FAILURE);
+                }
+                if self.obj().is_remote() {
+                    log::error!("Warp is already running. Close it to receive with --receive.");
+                    return ControlFlow::Break(glib::ExitCode::FAILURE);
+                }
+
+                self.receive_code.replace(Some(code));
+            }
+
+            self.parent_handle_local_options(options)
+        }
+
         fn activate(&self) {
             log::debug!("GtkApplication<WarpApplication>::activate");
             self.parent_activate();
 
-            if let Some(window) = self.window.get() {
-                let window = window.upgrade().unwrap();
+            if let Some(window) = self.obj().active_window() {
                 window.present();
                 return;
             }
 
-            log::debug!("Create window");
-
-            let window = WarpApplicationWindow::new(&self.obj());
-            self.window
-                .set(window.downgrade())
-                .expect("Window already set.");
-
-            self.obj().main_window().present();
+            let window = self.obj().new_window();
+            if let Some(code) = self.receive_code.take() {
+                self.receive_window.set(Some(&window));
+                window.receive_from_text(&code);
+            }
         }
 
         fn open(&self, files: &[gio::File], _hint: &str) {
@@ -58,29 +121,34 @@ mod imp {
                 return;
             };
 
-            if app.main_window().transfer_in_progress() {
-                let err: AppError = UiError::new(&gettext(
-                    "Unable to use transfer link: another transfer already in progress",
-                ))
-                .into();
-                err.show_error_dialog(&app.main_window());
-                return;
-            }
+            log::info!("Received {} files to open", files.len());
+
+            // Don't interrupt a running transfer, start the new one in a separate window instead
+            let window = match app.main_window() {
+                Some(window) if window.running_transfer().is_none() => window,
+                _ => app.new_window(),
+            };
 
             if first.uri_scheme().as_deref() == Some("wormhole-transfer") {
-                match files[0].uri().parse::<WormholeTransferURI>() {
+                if files.len() > 1 {
+                    log::warn!(
+                        "Ignoring {} files passed after a transfer link",
+                        files.len() - 1
+                    );
+                }
+
+                match first.uri().parse::<WormholeTransferURI>() {
                     Ok(uri) => {
-                        app.main_window().open_code_from_uri(uri);
+                        window.open_uri_auto(uri);
                     }
                     Err(err) => {
                         let err: AppError = UiError::new(&err.to_string()).into();
-                        err.show_error_dialog(&app.main_window());
+                        err.show_error_dialog(&window);
                     }
                 }
             } else {
                 // Regular file / file list
-                let window = app.main_window();
-                window.send_file(first);
+                window.send_files(files);
             }
         }
 
@@ -93,9 +161,19 @@ mod imp {
             gtk::Window::set_default_icon_name(globals::APP_ID);
 
             app.cleanup_cache();
+            app.load_user_css();
+            app.apply_color_scheme_from_config();
+            app.start_event_socket();
             app.setup_gactions();
             app.setup_accels();
         }
+
+        fn shutdown(&self) {
+            log::debug!("GtkApplication<WarpApplication>::shutdown");
+            // Temporary files of transfers that were still running when quitting
+            self.obj().cleanup_cache();
+            self.parent_shutdown();
+        }
     }
 
     impl GtkApplicationImpl for WarpApplication {}
@@ -117,8 +195,161 @@ impl WarpApplication {
             .build()
     }
 
-    pub fn main_window(&self) -> WarpApplicationWindow {
-        self.imp().window.get().unwrap().upgrade().unwrap()
+    /// Options to drive the app from scripts. They only apply when starting the first instance.
+    pub fn add_command_line_options(&self) {
+        self.add_main_option(
+            "result-file",
+            glib::Char::from(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::Filename,
+            "Write the result of each transfer as JSON to this file",
+            Some("PATH"),
+        );
+        self.add_main_option(
+            "quit-after-transfer",
+            glib::Char::from(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::None,
+            "Quit once a transfer has finished or failed",
+            None,
+        );
+        self.add_main_option(
+            "receive",
+            glib::Char::from(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::String,
+            "Receive a file with this code or link. Use “-” to read it from standard input",
+            Some("CODE"),
+        );
+        #[cfg(target_os = "linux")]
+        self.add_main_option(
+            "event-socket",
+            glib::Char::from(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::Filename,
+            "Send transfer events as JSON lines to clients of a Unix socket at this path",
+            Some("PATH"),
+        );
+    }
+
+    /// Listens for clients of the event socket, if requested on the command line
+    fn start_event_socket(&self) {
+        let Some(path) = self.imp().event_socket_path.borrow().clone() else {
+            return;
+        };
+
+        #[cfg(target_os = "linux")]
+        match crate::ui::event_socket::EventSocket::bind(&path) {
+            Ok(socket) => {
+                log::info!("Sending transfer events to '{}'", path.display());
+                self.imp().event_socket.replace(Some(socket));
+            }
+            Err(err) => log::error!("Error creating event socket '{}': {}", path.display(), err),
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        log::warn!(
+            "Event socket '{}' is only supported on Linux",
+            path.display()
+        );
+    }
+
+    /// Sends a transfer event to apps embedding Warp, if requested on the command line
+    pub fn emit_transfer_event(&self, event: &TransferEvent) {
+        #[cfg(target_os = "linux")]
+        if let Some(socket) = &*self.imp().event_socket.borrow() {
+            socket.send(event);
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        let _ = event;
+    }
+
+    /// Prints the progress of the transfer started with `--receive` to standard error, which keeps
+    /// standard output for the saved path
+    pub fn print_receive_progress(&self, window: &WarpApplicationWindow, bytes: u64, total: u64) {
+        if self.imp().receive_window.upgrade().as_ref() != Some(window) {
+            return;
+        }
+
+        if let Err(err) = writeln!(
+            std::io::stderr(),
+            "{} / {}",
+            glib::format_size(bytes),
+            glib::format_size(total)
+        ) {
+            log::debug!("Error printing the progress: {}", err);
+        }
+    }
+
+    /// Writes the outcome of a transfer for scripts, if requested on the command line. For the
+    /// transfer started with `--receive`, the saved path is printed to standard output and errors
+    /// to standard error.
+    pub fn report_transfer_result(&self, window: &WarpApplicationWindow, result: &TransferResult) {
+        let imp = self.imp();
+        if imp.receive_window.upgrade().as_ref() == Some(window) {
+            imp.receive_window.set(None);
+            let res = match (&result.path, &result.error) {
+                (Some(path), _) => writeln!(std::io::stdout(), "{}", path.display()),
+                (None, Some(error)) => writeln!(std::io::stderr(), "{error}"),
+                (None, None) => Ok(()),
+            };
+            if let Err(err) = res {
+                log::error!("Error printing the transfer result: {}", err);
+            }
+        }
+
+        if let Some(path) = &*imp.result_file.borrow() {
+            let res = serde_json::to_vec_pretty(result)
+                .map_err(std::io::Error::from)
+                .and_then(|json| std::fs::write(path, json));
+            if let Err(err) = res {
+                log::error!("Error writing result file '{}': {}", path.display(), err);
+            }
+        }
+
+        if imp.quit_after_transfer.get() {
+            log::info!("Quitting after the transfer");
+            self.quit();
+        }
+    }
+
+    /// The window the user interacted with last, if any window is open
+    ///
+    /// Every window runs its own transfers, so this is only meant for app wide actions and dialogs.
+    pub fn main_window(&self) -> Option<WarpApplicationWindow> {
+        self.active_window()
+            .and_downcast()
+            .or_else(|| self.app_windows().next())
+    }
+
+    /// All open application windows
+    pub fn app_windows(&self) -> impl Iterator<Item = WarpApplicationWindow> + use<> {
+        self.windows()
+            .into_iter()
+            .filter_map(|window| window.downcast().ok())
+    }
+
+    /// Opens an additional window that is independent of any transfers in the other windows
+    pub fn new_window(&self) -> WarpApplicationWindow {
+        log::debug!("Create window");
+        let window = WarpApplicationWindow::new(self);
+        window.present();
+        window
+    }
+
+    /// Closes all windows and quits once their transfers are canceled and cleaned up. Stops at the
+    /// first window whose transfer the user wants to keep running.
+    pub async fn quit_gracefully(&self) {
+        for window in self.app_windows() {
+            // This is needed to trigger the delete event and saving the window state
+            if !window.close_gracefully().await {
+                log::info!("Not quitting, the transfer is kept running");
+                return;
+            }
+        }
+
+        self.quit();
     }
 
     pub fn cleanup_cache(&self) {
@@ -135,6 +366,49 @@ impl WarpApplication {
         }
     }
 
+    /// Applies the style from the config file before the first window is shown
+    fn apply_color_scheme_from_config(&self) {
+        match PersistentConfig::from_file() {
+            Ok(config) => self.set_color_scheme(config.color_scheme_or_default()),
+            Err(err) => log::debug!("No style from the config file: {}", err),
+        }
+    }
+
+    pub fn set_color_scheme(&self, color_scheme: ColorScheme) {
+        self.style_manager().set_color_scheme(match color_scheme {
+            ColorScheme::System => adw::ColorScheme::Default,
+            ColorScheme::Light => adw::ColorScheme::ForceLight,
+            ColorScheme::Dark => adw::ColorScheme::ForceDark,
+        });
+    }
+
+    /// Loads the user stylesheet from the config directory, if there is one
+    fn load_user_css(&self) {
+        let path = PersistentConfig::user_css_path();
+        if !path.is_file() {
+            return;
+        }
+
+        let Some(display) = gtk::gdk::Display::default() else {
+            log::error!("No display to apply the user stylesheet to");
+            return;
+        };
+
+        log::info!("Loading user stylesheet: '{}'", path.display());
+        let provider = gtk::CssProvider::new();
+        provider.connect_parsing_error(|_, section, err| {
+            log::error!("Error in user stylesheet at {}: {}", section, err);
+        });
+        provider.load_from_path(&path);
+
+        // The user priority is above the application styles, so the user stylesheet wins
+        gtk::style_context_add_provider_for_display(
+            &display,
+            &provider,
+            gtk::STYLE_PROVIDER_PRIORITY_USER,
+        );
+    }
+
     pub fn open_help(&self, page: Option<&str>) {
         /* `help:` URIs are a Linux specific thing and won't work on Windows. There, we'll just open the path to the
          * respective HTML files and hope that it launches a browser …
@@ -144,13 +418,8 @@ impl WarpApplication {
         } else {
             let file = page.unwrap_or("index");
             let mut uri = globals::WINDOWS_BASE_PATH.clone();
-            /* Hardcode the "C" language for now, so no translated help files *sigh*
-             *
-             * The problem is that gettext is a mess and does not provide us with a good way
-             * to query the currenty used language. In theory it can do that, but the values
-             * it returned on Windows did not work as they should.
-             */
-            uri.push(format!("share\\help\\C\\warp\\{file}.html"));
+            let language = current_help_language();
+            uri.push(format!("share\\help\\{language}\\warp\\{file}.html"));
             /* People with non-UTF-8 paths will at least get a good error message */
             let mut uri = uri.to_string_lossy().into_owned();
             uri.insert_str(0, "file:///");
@@ -189,12 +458,76 @@ impl WarpApplication {
             #[weak(rename_to = app)]
             self,
             move |_, _| {
-                // This is needed to trigger the delete event and saving the window state
-                app.main_window().close();
-                app.quit();
+                main_async_local_infallible(async move { app.quit_gracefully().await });
             }
         ));
         self.add_action(&action_quit);
+
+        // Pauses notifications until the app is restarted, unlike the preferences. A boolean state
+        // without parameter is toggled on activation.
+        let action_do_not_disturb =
+            gio::SimpleAction::new_stateful("do-not-disturb", None, &false.to_variant());
+        self.add_action(&action_do_not_disturb);
+
+        // Notification buttons for received files
+        let action_open_received_file =
+            gio::SimpleAction::new("open-received-file", Some(&PathBuf::static_variant_type()));
+        action_open_received_file.connect_activate(clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, data| {
+                if let (Some(path), Some(window)) =
+                    (Self::received_file_path(data), app.main_window())
+                {
+                    glib::spawn_future_local(async move { window.open_file(&path).await });
+                }
+            }
+        ));
+        self.add_action(&action_open_received_file);
+
+        let action_show_received_file =
+            gio::SimpleAction::new("show-received-file", Some(&PathBuf::static_variant_type()));
+        action_show_received_file.connect_activate(clone!(
+            #[weak(rename_to = app)]
+            self,
+            move |_, data| {
+                if let (Some(path), Some(window)) =
+                    (Self::received_file_path(data), app.main_window())
+                {
+                    glib::spawn_future_local(async move { window.open_dir(&path).await });
+                }
+            }
+        ));
+        self.add_action(&action_show_received_file);
+    }
+
+    /// Plays a sound for a transfer event, replacing one that is still playing
+    pub fn play_event_sound(&self, sound: &str) {
+        self.imp().event_sound.replace(super::sound::play(sound));
+    }
+
+    /// Whether notifications were paused for this session with the main menu
+    pub fn do_not_disturb(&self) -> bool {
+        self.action_state("do-not-disturb")
+            .and_then(|state| state.get::<bool>())
+            .unwrap_or(false)
+    }
+
+    /// Resolves the path of a notification action target, if the file still exists
+    fn received_file_path(data: Option<&glib::Variant>) -> Option<PathBuf> {
+        let path = data.and_then(PathBuf::from_variant)?;
+
+        if path.exists() {
+            Some(path)
+        } else {
+            log::warn!("Received file '{}' no longer exists", path.display());
+            UiError::new(&gettextf(
+                "The file “{}” no longer exists. It may have been moved or deleted.",
+                &[&path.display()],
+            ))
+            .handle();
+            None
+        }
     }
 
     // Sets up keyboard shortcuts
@@ -211,25 +544,30 @@ impl WarpApplication {
         ApplicationExtManual::run(self);
     }
 
-    pub fn inhibit_transfer(&self, transfer_direction: TransferDirection) {
+    /// Prevents logout and suspend until the returned guard is dropped
+    pub fn inhibit_transfer(
+        &self,
+        window: &WarpApplicationWindow,
+        transfer_direction: TransferDirection,
+    ) -> InhibitGuard {
         let inhibit_reason = match transfer_direction {
             TransferDirection::Send => gettext("Sending a File"),
             TransferDirection::Receive => gettext("Receiving a File"),
         };
 
-        let inhibit = self.inhibit(
-            Some(&self.main_window()),
+        let cookie = self.inhibit(
+            Some(window),
             gtk::ApplicationInhibitFlags::LOGOUT | gtk::ApplicationInhibitFlags::SUSPEND,
             Some(&inhibit_reason),
         );
 
-        self.imp().inhibit_cookie.set(Some(inhibit));
-    }
-
-    pub fn uninhibit_transfer(&self) {
-        if let Some(cookie) = self.imp().inhibit_cookie.take() {
-            self.uninhibit(cookie);
-        }
+        let app = self.downgrade();
+        InhibitGuard::new(move || {
+            if let Some(app) = app.upgrade() {
+                log::debug!("Removing inhibit {}", cookie);
+                app.uninhibit(cookie);
+            }
+        })
     }
 
     pub fn is_flatpak() -> bool {
@@ -254,3 +592,132 @@ impl Default for WarpApplication {
             .expect("Application is wrong subclass")
     }
 }
+
+/// The language of the installed help files on Windows. Gettext doesn't tell us the language it
+/// uses there, so we ask GLib for the preferred languages, which also works on Windows.
+fn current_help_language() -> String {
+    let help_dir = globals::WINDOWS_BASE_PATH.join("share").join("help");
+    let languages = glib::language_names();
+
+    help_language(
+        languages.iter().map(|language| language.as_str()),
+        |language| help_dir.join(language).join("warp").is_dir(),
+    )
+}
+
+/// The first of the preferred `languages` that help is installed for, or the untranslated "C" help
+fn help_language<'a>(
+    languages: impl IntoIterator<Item = &'a str>,
+    is_installed: impl Fn(&str) -> bool,
+) -> String {
+    languages
+        .into_iter()
+        // Help is installed as `pt_BR` or `de`, never with a codeset or modifier like `de_DE.UTF-8`
+        .filter(|language| !language.contains(['.', '@']))
+        .find(|language| *language != "C" && is_installed(language))
+        .unwrap_or("C")
+        .to_owned()
+}
+
+/// Outcome of a transfer, written to the `--result-file` for scripts
+#[derive(Debug, Default, Serialize)]
+pub struct TransferResult {
+    pub success: bool,
+    /// `send` or `receive`
+    pub direction: &'static str,
+    pub file_name: Option<String>,
+    /// Where the received file was saved
+    pub path: Option<PathBuf>,
+    pub bytes: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Event for apps embedding Warp, sent to clients of the `--event-socket`
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TransferEvent {
+    /// The transfer moved to another step, like `has_code` or `transmitting`
+    State {
+        state: &'static str,
+        /// `send` or `receive`
+        direction: &'static str,
+        error: Option<String>,
+    },
+    Progress {
+        direction: &'static str,
+        bytes: u64,
+        total: u64,
+    },
+}
+
+/// Releases an inhibit when dropped, so it is released on every way a transfer can end
+pub struct InhibitGuard {
+    release: Option<Box<dyn FnOnce()>>,
+}
+
+impl InhibitGuard {
+    fn new(release: impl FnOnce() + 'static) -> Self {
+        Self {
+            release: Some(Box::new(release)),
+        }
+    }
+}
+
+impl Drop for InhibitGuard {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+impl std::fmt::Debug for InhibitGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InhibitGuard").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{InhibitGuard, help_language};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_inhibit_guard() {
+        let released = Rc::new(Cell::new(0));
+        let new_guard = || {
+            let released = released.clone();
+            InhibitGuard::new(move || released.set(released.get() + 1))
+        };
+
+        // Released exactly once when dropped
+        let guard = new_guard();
+        assert_eq!(released.get(), 0);
+        drop(guard);
+        assert_eq!(released.get(), 1);
+
+        // Also released when unwinding
+        let guard = new_guard();
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+            let _guard = guard;
+            panic!("Transfer panicked");
+        }));
+        assert!(res.is_err());
+        assert_eq!(released.get(), 2);
+    }
+
+    #[test]
+    fn test_help_language() {
+        let installed = |language: &str| ["de", "pt_BR"].contains(&language);
+
+        assert_eq!(
+            help_language(["de_DE.UTF-8", "de_DE", "de.UTF-8", "de", "C"], installed),
+            "de"
+        );
+        assert_eq!(help_language(["pt_BR", "pt", "C"], installed), "pt_BR");
+        assert_eq!(help_language(["sr@latin", "sr", "C"], installed), "C");
+        assert_eq!(help_language(["C"], installed), "C");
+        assert_eq!(help_language([], installed), "C");
+    }
+}
@@ -10,14 +10,15 @@ use std::sync::LazyLock;
 
 use crate::gettext::*;
 use crate::ui::camera_row::CameraRow;
+use crate::ui::window::WarpApplicationWindow;
 use crate::util::error::*;
 
 mod imp {
-    use std::cell::{OnceCell, RefCell};
+    use std::cell::{Cell, OnceCell, RefCell};
 
     use glib::subclass::{InitializingObject, Signal};
 
-    use crate::{globals, ui::window::WarpApplicationWindow, util::future::main_async_local};
+    use crate::{globals, ui::application::WarpApplication, util::future::main_async_local};
 
     use super::*;
 
@@ -31,6 +32,8 @@ mod imp {
         #[template_child]
         pub error_page: TemplateChild<adw::StatusPage>,
         #[template_child]
+        pub open_settings_button: TemplateChild<gtk::Button>,
+        #[template_child]
         pub viewfinder_bin: TemplateChild<adw::Bin>,
         #[template_child]
         pub selection_button: TemplateChild<gtk::MenuButton>,
@@ -38,6 +41,8 @@ mod imp {
         pub viewfinder: OnceCell<aperture::Viewfinder>,
 
         pub portal_cancellable: RefCell<Option<gio::Cancellable>>,
+        /// Opened on switching to the receive tab, so errors go back to the code entry
+        pub opened_automatically: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -74,6 +79,8 @@ mod imp {
     impl WidgetImpl for Camera {}
     impl NavigationPageImpl for Camera {
         fn hidden(&self) {
+            self.opened_automatically.set(false);
+            self.cancel_permission_request();
             if let Some(viewfinder) = self.viewfinder.get() {
                 viewfinder.stop_stream();
             }
@@ -184,13 +191,23 @@ mod imp {
                     #[weak]
                     popover,
                     move |selection| {
+                        let camera = selection.selected_item().and_downcast::<aperture::Camera>();
+                        // Only a choice from the menu is remembered, not the default selection
+                        if popover.is_visible() {
+                            if let (Some(camera), Some(window)) =
+                                (&camera, obj.root().and_downcast::<WarpApplicationWindow>())
+                            {
+                                window.config().last_camera =
+                                    Some(camera.display_name().to_string());
+                            }
+                        }
+
                         popover.popdown();
 
                         let Some(viewfinder) = obj.imp().viewfinder.get() else {
                             return;
                         };
 
-                        let camera = selection.selected_item().and_downcast::<aperture::Camera>();
                         if matches!(
                             viewfinder.state(),
                             aperture::ViewfinderState::Ready | aperture::ViewfinderState::Error
@@ -226,6 +243,12 @@ mod imp {
             );
         }
 
+        #[template_callback]
+        fn on_refresh_clicked(&self) {
+            log::debug!("Refreshing the camera list");
+            self.on_retry_clicked();
+        }
+
         #[template_callback]
         fn on_troubleshooting_clicked(&self) {
             if let Some(app) = self
@@ -238,20 +261,122 @@ mod imp {
             }
         }
 
+        /// The app settings of GNOME Settings allow changing the camera permission of the app.
+        /// There is no portal or desktop-neutral way to open the permissions of an app, so this is
+        /// only available where GNOME Settings is installed. From inside the Flatpak sandbox we
+        /// have no way to launch the host settings app at all.
+        fn settings_commandline() -> Option<String> {
+            if WarpApplication::is_flatpak() {
+                return None;
+            }
+
+            glib::find_program_in_path("gnome-control-center")?;
+            Some(format!(
+                "gnome-control-center applications {}",
+                globals::APP_ID
+            ))
+        }
+
+        #[template_callback]
+        fn on_open_settings_clicked(&self) {
+            let Some(commandline) = Self::settings_commandline() else {
+                return;
+            };
+            let res = gio::AppInfo::create_from_commandline(
+                commandline,
+                None,
+                gio::AppInfoCreateFlags::NONE,
+            )
+            .and_then(|app_info| {
+                let context = self.obj().display().app_launch_context();
+                app_info.launch(&[], Some(&context))
+            });
+
+            if let Err(err) = res {
+                log::error!("Error opening settings: {}", err);
+                UiError::new(&gettext(
+                    "Unable to open the settings. Please allow Warp to access the camera in the privacy settings of your system.",
+                ))
+                .handle();
+            }
+        }
+
+        /// Cancels a pending camera permission request, so no portal dialog is left behind
+        fn cancel_permission_request(&self) {
+            if let Some(cancellable) = self.portal_cancellable.take() {
+                log::debug!("Canceling the camera permission request");
+                cancellable.cancel();
+            }
+        }
+
+        #[template_callback]
+        fn on_cancel_clicked(&self) {
+            // Asking again when the camera is opened the next time, as it was never initialized
+            self.cancel_permission_request();
+            self.opened_automatically.set(false);
+            self.on_enter_code_clicked();
+        }
+
+        /// Goes back to the code entry if the camera was not opened by the user
+        fn fall_back_to_code_entry(&self, message: &str) -> bool {
+            if !self.opened_automatically.take() {
+                return false;
+            }
+
+            log::info!("Camera not available, falling back to the code entry");
+            if let Some(window) = self.obj().root().and_downcast::<WarpApplicationWindow>() {
+                window.toast_overlay().add_toast(adw::Toast::new(message));
+            }
+            self.on_enter_code_clicked();
+            true
+        }
+
+        #[template_callback]
+        fn on_enter_code_clicked(&self) {
+            let Some(window) = self.obj().root().and_downcast::<WarpApplicationWindow>() else {
+                return;
+            };
+
+            window.navigate_home();
+            if let Err(err) =
+                window.activate_action(crate::ui::window::Action::ReceiveFile.as_ref(), None)
+            {
+                log::error!("Error focusing the code entry: {}", err);
+            }
+        }
+
         fn handle_error(&self, error: &AppError) {
+            if error.is_user_canceled() {
+                log::debug!("Camera permission request canceled");
+                return;
+            }
+
             log::error!("Camera error: {}", error);
-            let description = if let AppError::Ashpd {
-                source: ashpd::Error::Portal(ashpd::PortalError::NotAllowed(..)),
-            } = error
-            {
-                gettextf(
-                    "Camera access denied. Open Settings and allow Warp to access the camera.",
-                    &[&error.gettext_error()],
+            let permission_denied = matches!(
+                error,
+                AppError::Ashpd {
+                    source: ashpd::Error::Portal(ashpd::PortalError::NotAllowed(..)),
+                }
+            );
+
+            let can_open_settings = Self::settings_commandline().is_some();
+            self.open_settings_button
+                .set_visible(permission_denied && can_open_settings);
+
+            let description = if permission_denied && can_open_settings {
+                gettext("Camera access denied. Open Settings and allow Warp to access the camera.")
+            } else if permission_denied {
+                gettext(
+                    "Camera access denied. Allow Warp to access the camera in the privacy settings of your system.",
                 )
             } else {
                 gettextf("Failed to start the camera: {}", &[&error.gettext_error()])
             };
 
+            if self.fall_back_to_code_entry(&description) {
+                return;
+            }
+
             self.error_page.set_title(&gettext("Error"));
             self.error_page.set_description(Some(&description));
             self.stack.set_visible_child_name("error");
@@ -329,6 +454,7 @@ impl Camera {
         }
 
         if INIT.is_completed() {
+            self.select_remembered_camera();
             return Ok(());
         }
 
@@ -356,6 +482,7 @@ impl Camera {
                 } else {
                     log::debug!("Device provider started");
                     INIT.call_once(|| ());
+                    self.select_remembered_camera();
                     Ok(())
                 }
             }
@@ -364,6 +491,38 @@ impl Camera {
         }
     }
 
+    /// Selects the camera that was picked last time. If it is not connected anymore, the default
+    /// back-facing camera stays selected.
+    fn select_remembered_camera(&self) {
+        let Some(window) = self.root().and_downcast::<WarpApplicationWindow>() else {
+            return;
+        };
+        let Some(name) = window.config().last_camera.clone() else {
+            return;
+        };
+
+        let selection = &self.imp().selection;
+        let position = (0..selection.n_items()).find(|&position| {
+            selection
+                .item(position)
+                .and_downcast::<aperture::Camera>()
+                .is_some_and(|camera| camera.display_name() == name.as_str())
+        });
+
+        match position {
+            Some(position) => {
+                log::debug!("Selecting the remembered camera '{}'", name);
+                selection.set_selected(position);
+            }
+            None => log::debug!("The remembered camera '{}' is not connected", name),
+        }
+    }
+
+    /// Whether the camera is opened without the user asking for it, see `fall_back_to_code_entry`
+    pub fn set_opened_automatically(&self, opened_automatically: bool) {
+        self.imp().opened_automatically.set(opened_automatically);
+    }
+
     pub fn stop(&self) {
         log::debug!("Stopping camera stream");
         if let Some(viewfinder) = self.imp().viewfinder.get() {
@@ -379,12 +538,17 @@ impl Camera {
                 imp.stack.set_visible_child_name("loading");
             }
             aperture::ViewfinderState::Error | aperture::ViewfinderState::NoCameras => {
+                if imp.fall_back_to_code_entry(&gettext("No Camera Found")) {
+                    return;
+                }
+
                 imp.stack.set_visible_child_name("error");
                 imp.error_page.set_title(&gettext("No Camera Found"));
                 imp.error_page
                     .set_description(Some(&gettext("Connect a camera to scan QR codes")));
             }
             aperture::ViewfinderState::Ready => {
+                imp.opened_automatically.set(false);
                 imp.stack.set_visible_child_name("stream");
             }
         }
@@ -0,0 +1,122 @@
+//! Transfer events for apps that embed Warp, sent over a Unix domain socket
+//!
+//! Every connected client receives each event as one JSON object per line. Clients that can't keep
+//! up or disconnected are dropped, so a stuck client never blocks the transfer.
+
+use serde::Serialize;
+use std::io::Write;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug)]
+pub struct EventSocket {
+    path: PathBuf,
+    clients: Arc<Mutex<Vec<UnixStream>>>,
+}
+
+impl EventSocket {
+    /// Listens on `path`. A socket left behind by an earlier run is replaced.
+    pub fn bind(path: &Path) -> std::io::Result<Self> {
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+
+        let accept_clients = clients.clone();
+        std::thread::Builder::new()
+            .name("event-socket".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream.and_then(|stream| {
+                        stream.set_nonblocking(true)?;
+                        Ok(stream)
+                    }) {
+                        Ok(stream) => {
+                            log::debug!("Event socket client connected");
+                            accept_clients.lock().unwrap().push(stream);
+                        }
+                        Err(err) => log::warn!("Error accepting event socket client: {err}"),
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            clients,
+        })
+    }
+
+    /// Sends an event to all connected clients
+    pub fn send(&self, event: &impl Serialize) {
+        let mut line = match serde_json::to_vec(event) {
+            Ok(line) => line,
+            Err(err) => {
+                log::error!("Error serializing event: {err}");
+                return;
+            }
+        };
+        line.push(b'\n');
+
+        self.clients.lock().unwrap().retain_mut(|client| {
+            let res = client.write_all(&line);
+            if let Err(err) = &res {
+                log::debug!("Dropping event socket client: {err}");
+            }
+            res.is_ok()
+        });
+    }
+}
+
+impl Drop for EventSocket {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            log::warn!(
+                "Error removing event socket '{}': {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_send() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.sock");
+        let socket = EventSocket::bind(&path).unwrap();
+
+        let client = UnixStream::connect(&path).unwrap();
+        // The client is registered by the accept thread
+        let start = Instant::now();
+        while socket.clients.lock().unwrap().is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        socket.send(&serde_json::json!({"event": "state", "state": "connected"}));
+        socket.send(&serde_json::json!({"event": "progress", "bytes": 5}));
+
+        let mut lines = BufReader::new(client).lines();
+        assert_eq!(
+            lines.next().unwrap().unwrap(),
+            r#"{"event":"state","state":"connected"}"#
+        );
+        assert_eq!(
+            lines.next().unwrap().unwrap(),
+            r#"{"bytes":5,"event":"progress"}"#
+        );
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+}
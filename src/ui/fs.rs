@@ -1,16 +1,75 @@
 use crate::error::UiError;
+use crate::gettext::gettextf;
 use crate::util::error::AppError;
+use crate::util::zip::{ZipOptions, ZipProgress};
 use crate::{gettext, globals};
-use futures::FutureExt;
+use futures::{FutureExt, StreamExt};
 use futures::{pin_mut, select};
-use std::borrow::Cow;
-use std::ffi::{OsStr, OsString};
+use gio::prelude::*;
 use std::future::Future;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
-pub fn default_download_dir() -> Result<PathBuf, AppError> {
-    if let Some(downloads) = glib::user_special_dir(glib::UserDirectory::Downloads) {
-        Ok(downloads)
+/// The only file in a folder, if the folder contains nothing else. Hidden files are only counted if
+/// they would be included in an archive. Subfolders are not looked into, so a folder with a single
+/// subfolder has no single file.
+pub fn single_file_in_folder(dir: &Path, include_hidden: bool) -> Option<PathBuf> {
+    let mut entries = std::fs::read_dir(dir).ok()?.filter(|entry| {
+        include_hidden
+            || entry.as_ref().map_or(true, |entry| {
+                !entry.file_name().to_string_lossy().starts_with('.')
+            })
+    });
+
+    let entry = entries.next()?.ok()?;
+    if entries.next().is_some() {
+        return None;
+    }
+
+    // Links are left to the archive, which knows the symlink policy
+    entry
+        .file_type()
+        .ok()
+        .filter(std::fs::FileType::is_file)
+        .map(|_| entry.path())
+}
+
+/// The downloads directory, and whether the home directory is used because there is none
+pub fn default_download_dir() -> Result<(PathBuf, bool), AppError> {
+    resolve_download_dir(
+        glib::user_special_dir(glib::UserDirectory::Downloads),
+        &glib::home_dir(),
+    )
+}
+
+/// A relative downloads directory is taken to be in the home directory. A missing one is created,
+/// and if that fails the home directory is used instead.
+fn resolve_download_dir(
+    downloads: Option<PathBuf>,
+    home: &Path,
+) -> Result<(PathBuf, bool), AppError> {
+    if let Some(downloads) = downloads {
+        let downloads = home.join(downloads);
+        if downloads.is_dir() {
+            return Ok((downloads, false));
+        }
+
+        match std::fs::create_dir_all(&downloads) {
+            Ok(()) => {
+                log::info!("Created downloads dir '{}'", downloads.display());
+                return Ok((downloads, false));
+            }
+            Err(err) => log::warn!(
+                "Error creating downloads dir '{}': {}",
+                downloads.display(),
+                err
+            ),
+        }
+    }
+
+    if home.is_dir() {
+        log::warn!("Using home dir '{}' for downloads", home.display());
+        Ok((home.to_path_buf(), true))
     } else {
         Err(UiError::new(&gettext(
             "Downloads dir missing. Please set XDG_DOWNLOAD_DIR",
@@ -19,19 +78,43 @@ pub fn default_download_dir() -> Result<PathBuf, AppError> {
     }
 }
 
-pub async fn compress_folder_cancelable<F: 'static + Fn(usize, usize) + Send>(
+pub async fn compress_folder_cancelable<F: 'static + Fn(ZipProgress) + Send>(
     path: &Path,
+    options: ZipOptions,
+    cancel_future: impl Future<Output = ()>,
+    progress_callback: F,
+) -> Result<tempfile::NamedTempFile, AppError> {
+    archive_cancelable(
+        compress_folder(path, options, progress_callback),
+        cancel_future,
+    )
+    .await
+}
+
+pub async fn compress_files_cancelable<F: 'static + Fn(ZipProgress) + Send>(
+    paths: Vec<PathBuf>,
+    options: ZipOptions,
     cancel_future: impl Future<Output = ()>,
     progress_callback: F,
 ) -> Result<tempfile::NamedTempFile, AppError> {
-    let tar_path_future = compress_folder(path, progress_callback);
-    let tar_path_future = tar_path_future.fuse();
+    archive_cancelable(
+        compress_files(paths, options, progress_callback),
+        cancel_future,
+    )
+    .await
+}
+
+async fn archive_cancelable(
+    archive_future: impl Future<Output = Result<tempfile::NamedTempFile, AppError>>,
+    cancel_future: impl Future<Output = ()>,
+) -> Result<tempfile::NamedTempFile, AppError> {
+    let archive_future = archive_future.fuse();
     let cancel_future = cancel_future.fuse();
 
-    pin_mut!(tar_path_future, cancel_future);
+    pin_mut!(archive_future, cancel_future);
 
     select! {
-        res = tar_path_future => {
+        res = archive_future => {
             if res.is_ok() {
                 log::debug!("Created archive");
             }
@@ -47,46 +130,243 @@ pub async fn compress_folder_cancelable<F: 'static + Fn(usize, usize) + Send>(
     }
 }
 
-pub async fn compress_folder<F: 'static + Fn(usize, usize) + Send>(
+fn archive_tempfile() -> Result<tempfile::NamedTempFile, AppError> {
+    let tmp_dir = &*globals::CACHE_DIR;
+    std::fs::create_dir_all(tmp_dir)?;
+
+    Ok(tempfile::Builder::new()
+        .prefix("warp_archive_")
+        .suffix(".zip")
+        .tempfile_in(tmp_dir)?)
+}
+
+pub async fn compress_folder<F: 'static + Fn(ZipProgress) + Send>(
     path: &Path,
+    options: ZipOptions,
     progress_callback: F,
 ) -> Result<tempfile::NamedTempFile, AppError> {
     let path = path.to_path_buf();
     assert!(path.is_dir(), "Wrong compress_folder invocation");
 
-    let tmp_dir = &*globals::CACHE_DIR;
-    std::fs::create_dir_all(tmp_dir)?;
+    let zip_file = archive_tempfile()?;
+    let async_zip_file = smol::fs::File::from(zip_file.reopen()?);
 
-    let zip_file = tempfile::Builder::new()
-        .prefix("warp_archive_")
-        .suffix(".zip")
-        .tempfile_in(tmp_dir)?;
+    log::debug!("Creating archive: {}", zip_file.path().display());
+    smol::spawn(async move {
+        crate::util::zip::zip_dir(&path, async_zip_file, options, progress_callback).await?;
+        Ok(zip_file)
+    })
+    .await
+}
 
+pub async fn compress_files<F: 'static + Fn(ZipProgress) + Send>(
+    paths: Vec<PathBuf>,
+    options: ZipOptions,
+    progress_callback: F,
+) -> Result<tempfile::NamedTempFile, AppError> {
+    let zip_file = archive_tempfile()?;
     let async_zip_file = smol::fs::File::from(zip_file.reopen()?);
 
-    log::debug!("Creating archive: {}", zip_file.path().display());
+    log::debug!(
+        "Creating archive of {} files: {}",
+        paths.len(),
+        zip_file.path().display()
+    );
     smol::spawn(async move {
-        crate::util::zip::zip_dir(&path, async_zip_file, progress_callback).await?;
+        crate::util::zip::zip_files(&paths, async_zip_file, options, progress_callback).await?;
         Ok(zip_file)
     })
     .await
 }
 
+/// Downloads a file via GVfs into a temporary directory. Redirects are followed by the http
+/// backend. Returns the directory, which deletes the file when dropped, and the file path.
+pub async fn download_url_cancelable(
+    url: &url::Url,
+    cancel_future: impl Future<Output = ()>,
+    progress_callback: impl Fn(i64, i64) + 'static,
+) -> Result<(tempfile::TempDir, PathBuf), AppError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(UiError::new(&gettext("Only http and https URLs can be downloaded")).into());
+    }
+
+    let tmp_dir = &*globals::CACHE_DIR;
+    std::fs::create_dir_all(tmp_dir)?;
+    let download_dir = tempfile::Builder::new()
+        .prefix("warp_download_")
+        .tempdir_in(tmp_dir)?;
+
+    let filename = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|segment| urlencoding::decode(segment).ok())
+        .map(|name| name.replace(['/', '\\'], "_"))
+        .filter(|name| !name.is_empty() && name != "." && name != "..")
+        .unwrap_or_else(|| "download".to_owned());
+    let path = download_dir.path().join(filename);
+
+    log::debug!("Downloading '{}' to '{}'", url, path.display());
+    let source = gio::File::for_uri(url.as_str());
+    let destination = gio::File::for_path(&path);
+    let (download_future, progress) = source.copy_future(
+        &destination,
+        gio::FileCopyFlags::OVERWRITE,
+        glib::Priority::DEFAULT,
+    );
+
+    let progress_future = progress.for_each(move |(current, total)| {
+        progress_callback(current, total);
+        futures::future::ready(())
+    });
+    let download_future = futures::future::join(download_future, progress_future)
+        .map(|(res, ())| res)
+        .fuse();
+    let cancel_future = cancel_future.fuse();
+
+    pin_mut!(download_future, cancel_future);
+
+    select! {
+        res = download_future => {
+            res.map_err(download_error)?;
+            log::debug!("Download finished");
+            Ok((download_dir, path))
+        },
+        () = cancel_future => {
+            log::debug!("Download canceled");
+            // Dropping the future cancels the copy operation
+            Err(AppError::Canceled)
+        }
+    }
+}
+
+fn download_error(err: glib::Error) -> AppError {
+    log::error!("Download error: {}", err);
+
+    let msg = match err.kind::<gio::IOErrorEnum>() {
+        Some(gio::IOErrorEnum::NotFound) => gettext("The file was not found on the server"),
+        Some(gio::IOErrorEnum::NotSupported) => gettext(
+            "Downloading files is not supported on this system. Please download the file manually.",
+        ),
+        Some(gio::IOErrorEnum::HostNotFound | gio::IOErrorEnum::HostUnreachable) => {
+            gettext("The server could not be reached. Please check your network connection.")
+        }
+        Some(gio::IOErrorEnum::PermissionDenied) => {
+            gettext("Access to the file was denied by the server")
+        }
+        Some(gio::IOErrorEnum::TimedOut) => gettext("The server took too long to respond"),
+        _ => gettextf("Error downloading the file: {}", &[&err.message()]),
+    };
+
+    UiError::new(&msg).into()
+}
+
+/// Checks that we can store files in `dir` by writing a small temporary file
+pub fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    let mut temp_file = tempfile::Builder::new()
+        .prefix(".warp_probe_")
+        .suffix(".warpdownload")
+        .tempfile_in(dir)?;
+
+    // A full file system usually only fails once data is written
+    temp_file.write_all(&[0])?;
+    temp_file.as_file().sync_all()
+}
+
+/// Where the document portal mounts the files it shares with sandboxed apps
+fn document_portal_dir() -> PathBuf {
+    glib::user_runtime_dir().join("doc")
+}
+
+/// Whether `path` was made accessible by the document portal, e.g. after choosing it in the file
+/// chooser, instead of being accessible directly
+pub fn is_portal_path(path: &Path) -> bool {
+    path.starts_with(document_portal_dir())
+}
+
+/// User facing explanation why files can't be stored in `dir`
+pub fn unwritable_dir_message(dir: &Path, err: &std::io::Error) -> String {
+    let dir = dir.display().to_string();
+    match err.kind() {
+        std::io::ErrorKind::ReadOnlyFilesystem => {
+            gettextf("The folder “{}” is on a read-only file system", &[&dir])
+        }
+        std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded => {
+            gettextf("There is no space left in the folder “{}”", &[&dir])
+        }
+        std::io::ErrorKind::PermissionDenied => gettextf(
+            "You don’t have permission to save files in the folder “{}”",
+            &[&dir],
+        ),
+        _ => gettextf(
+            "Unable to save files in the folder “{0}”: {1}",
+            &[&dir, &err],
+        ),
+    }
+}
+
+/// Longest file name most file systems allow, in bytes
+const MAX_FILENAME_LEN: usize = 255;
+/// Room for the random part and suffix of a temporary download, like `.a1b2c3.warpdownload`
+const TEMPFILE_NAME_RESERVE: usize = 32;
+/// Room for a number that makes a file name unique, like ` (12)`
+const UNIQUE_SUFFIX_RESERVE: usize = 8;
+/// Longer extensions are not kept when shortening a file name, as they are unlikely to be real
+const MAX_KEPT_EXTENSION_LEN: usize = 16;
+
+/// Cuts a string to at most `max_len` bytes without splitting a character
+fn truncate_at_char_boundary(str: &str, max_len: usize) -> &str {
+    if str.len() <= max_len {
+        return str;
+    }
+
+    let mut end = max_len;
+    while !str.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    &str[..end]
+}
+
+/// Shortens an offered file name, so the temporary download file can still be created next to
+/// it. The extension is kept. Returns `None` if the name is short enough.
+pub fn shorten_received_filename(name: &str) -> Option<String> {
+    let max_len = MAX_FILENAME_LEN - TEMPFILE_NAME_RESERVE;
+    if name.len() <= max_len {
+        return None;
+    }
+
+    let shortened = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && ext.len() <= MAX_KEPT_EXTENSION_LEN => format!(
+            "{}.{ext}",
+            truncate_at_char_boundary(stem, max_len - ext.len() - 1)
+        ),
+        _ => truncate_at_char_boundary(name, max_len).to_owned(),
+    };
+
+    Some(shortened)
+}
+
 pub fn safe_persist_tempfile(
     temp_file: tempfile::NamedTempFile,
     filename: &Path,
 ) -> std::io::Result<PathBuf> {
     let mut temp_path = temp_file.into_temp_path();
-    let orig_file_stem: String = filename
-        .file_stem()
-        .map(OsStr::to_string_lossy)
-        .map_or("Downloaded File".to_owned(), Cow::into_owned);
+    // Names without an extension like “Makefile” or “.bashrc” are kept as they are. Only a missing
+    // name is replaced by a generic one.
+    let (orig_file_stem, file_ext) = match filename.file_stem() {
+        Some(stem) => (
+            stem.to_string_lossy().into_owned(),
+            filename
+                .extension()
+                .map(|ext| ext.to_string_lossy().into_owned()),
+        ),
+        None => ("Downloaded File".to_owned(), Some("bin".to_owned())),
+    };
+    let ext_suffix = file_ext.map_or_else(String::new, |ext| format!(".{ext}"));
 
-    let file_ext: String = filename
-        .extension()
-        .unwrap_or(&OsString::from("bin"))
-        .to_string_lossy()
-        .into_owned();
+    // Leave room for the number that makes the name unique
+    let max_stem_len = MAX_FILENAME_LEN.saturating_sub(ext_suffix.len() + UNIQUE_SUFFIX_RESERVE);
+    let orig_file_stem = truncate_at_char_boundary(&orig_file_stem, max_stem_len).to_owned();
 
     let dir = temp_path
         .parent()
@@ -97,7 +377,7 @@ pub fn safe_persist_tempfile(
     let mut file_stem = orig_file_stem.clone();
 
     loop {
-        let path = dir.join(PathBuf::from(format!("{file_stem}.{file_ext}")));
+        let path = dir.join(PathBuf::from(format!("{file_stem}{ext_suffix}")));
 
         match temp_path.persist_noclobber(&path) {
             Ok(()) => {
@@ -118,11 +398,112 @@ pub fn safe_persist_tempfile(
     }
 }
 
+/// Moves the temporary file to `filename` next to it, replacing any file with that name
+pub fn overwrite_persist_tempfile(
+    temp_file: tempfile::NamedTempFile,
+    filename: &Path,
+) -> std::io::Result<PathBuf> {
+    let temp_path = temp_file.into_temp_path();
+    let path = temp_path
+        .parent()
+        .map_or(PathBuf::from("."), ToOwned::to_owned)
+        .join(filename);
+
+    if let Err(err) = temp_path.persist(&path) {
+        log::error!("Error creating file '{}': {}", path.display(), err);
+        return Err(err.error);
+    }
+
+    Ok(path)
+}
+
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    use super::{
+        MAX_FILENAME_LEN, is_portal_path, overwrite_persist_tempfile, probe_writable,
+        resolve_download_dir, safe_persist_tempfile, shorten_received_filename,
+        single_file_in_folder, unwritable_dir_message,
+    };
+
+    #[test]
+    fn test_resolve_download_dir() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+
+        // A missing downloads dir is created, a relative one is in the home dir
+        let (dir, fallback) = resolve_download_dir(Some(PathBuf::from("Downloads")), home).unwrap();
+        assert_eq!(dir, home.join("Downloads"));
+        assert!(dir.is_dir());
+        assert!(!fallback);
+
+        // Without a downloads dir, or one that can't be created, the home dir is used
+        assert_eq!(
+            resolve_download_dir(None, home).unwrap(),
+            (home.to_path_buf(), true)
+        );
+        std::fs::write(home.join("file"), b"").unwrap();
+        assert_eq!(
+            resolve_download_dir(Some(home.join("file").join("Downloads")), home).unwrap(),
+            (home.to_path_buf(), true)
+        );
 
-    use super::safe_persist_tempfile;
+        assert!(resolve_download_dir(None, &home.join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_single_file_in_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(single_file_in_folder(dir.path(), true), None);
+
+        let file = dir.path().join("file.txt");
+        std::fs::write(&file, "content").unwrap();
+        assert_eq!(single_file_in_folder(dir.path(), true), Some(file.clone()));
+
+        std::fs::write(dir.path().join(".hidden"), "content").unwrap();
+        assert_eq!(single_file_in_folder(dir.path(), true), None);
+        assert_eq!(single_file_in_folder(dir.path(), false), Some(file));
+
+        // Only one level is unwrapped
+        let outer = tempfile::tempdir().unwrap();
+        let inner = outer.path().join("inner");
+        std::fs::create_dir(&inner).unwrap();
+        std::fs::write(inner.join("file.txt"), "content").unwrap();
+        assert_eq!(single_file_in_folder(outer.path(), true), None);
+    }
+
+    #[test]
+    fn test_probe_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        probe_writable(dir.path()).unwrap();
+        // The probe file is removed again
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        assert!(probe_writable(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_is_portal_path() {
+        let runtime_dir = glib::user_runtime_dir();
+        assert!(is_portal_path(&runtime_dir.join("doc/1a2b3c4d/file.txt")));
+        assert!(!is_portal_path(&runtime_dir.join("documents/file.txt")));
+        assert!(!is_portal_path(Path::new("/home/user/Downloads/file.txt")));
+    }
+
+    #[test]
+    fn test_unwritable_dir_message() {
+        let dir = PathBuf::from("/downloads");
+        let read_only = std::io::Error::from(std::io::ErrorKind::ReadOnlyFilesystem);
+        let full = std::io::Error::from(std::io::ErrorKind::StorageFull);
+
+        assert_ne!(
+            unwritable_dir_message(&dir, &read_only),
+            unwritable_dir_message(&dir, &full)
+        );
+        assert!(unwritable_dir_message(&dir, &full).contains("/downloads"));
+    }
 
     #[test]
     fn test_safe_persist_tempfile() {
@@ -166,4 +547,98 @@ mod test {
             std::fs::remove_file(path).unwrap();
         }
     }
+
+    fn temp_file_with_content(dir: &std::path::Path, content: &str) -> tempfile::NamedTempFile {
+        let mut temp_file = tempfile::NamedTempFile::new_in(dir).unwrap();
+        temp_file.write_all(content.as_bytes()).unwrap();
+        temp_file
+    }
+
+    #[test]
+    fn test_safe_persist_tempfile_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("file.txt");
+        std::fs::write(&existing, "existing").unwrap();
+
+        let temp_file = temp_file_with_content(dir.path(), "new");
+        let path = safe_persist_tempfile(temp_file, &PathBuf::from("file.txt")).unwrap();
+
+        assert_eq!(path, dir.path().join("file (1).txt"));
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "existing");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_safe_persist_tempfile_without_extension() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for (name, renamed) in [
+            ("Makefile", "Makefile (1)"),
+            (".bashrc", ".bashrc (1)"),
+            ("archive.tar.gz", "archive.tar (1).gz"),
+            ("v1.2 notes.txt", "v1.2 notes (1).txt"),
+        ] {
+            let filename = PathBuf::from(name);
+            for expected in [name, renamed] {
+                let temp_file = temp_file_with_content(dir.path(), name);
+                let path = safe_persist_tempfile(temp_file, &filename).unwrap();
+                assert_eq!(path, dir.path().join(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_shorten_received_filename() {
+        assert_eq!(shorten_received_filename("file.txt"), None);
+
+        let name = format!("{}.txt", "a".repeat(300));
+        let shortened = shorten_received_filename(&name).unwrap();
+        assert!(shortened.len() < MAX_FILENAME_LEN);
+        assert!(shortened.starts_with("aaa"));
+        assert!(shortened.ends_with(".txt"));
+
+        // Characters are not cut in half
+        let name = format!("{}.txt", "ä".repeat(200));
+        let shortened = shorten_received_filename(&name).unwrap();
+        assert!(shortened.len() < MAX_FILENAME_LEN);
+        assert!(shortened.ends_with("ä.txt"));
+
+        // Without a plausible extension the whole name is cut
+        let name = format!("file.{}", "b".repeat(300));
+        let shortened = shorten_received_filename(&name).unwrap();
+        assert!(shortened.len() < MAX_FILENAME_LEN);
+        assert!(shortened.starts_with("file.bbb"));
+    }
+
+    #[test]
+    fn test_safe_persist_tempfile_long_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let filename = PathBuf::from(format!("{}.txt", "a".repeat(400)));
+
+        for _ in 0..2 {
+            let temp_file = temp_file_with_content(dir.path(), "content");
+            let path = safe_persist_tempfile(temp_file, &filename).unwrap();
+            let name = path.file_name().unwrap().to_str().unwrap();
+
+            assert!(name.len() <= MAX_FILENAME_LEN);
+            assert!(name.ends_with(".txt"));
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "content");
+        }
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_overwrite_persist_tempfile_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("file.txt");
+        std::fs::write(&existing, "existing").unwrap();
+
+        let temp_file = temp_file_with_content(dir.path(), "new");
+        let path = overwrite_persist_tempfile(temp_file, &PathBuf::from("file.txt")).unwrap();
+
+        assert_eq!(path, existing);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
 }
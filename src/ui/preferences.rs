@@ -3,14 +3,38 @@ use adw::subclass::prelude::*;
 
 use super::application::WarpApplication;
 
-const CODE_LENGTH_MIN: i32 = 2;
-const CODE_LENGTH_MAX: i32 = 8;
+use crate::globals::{CODE_LENGTH_MAX, CODE_LENGTH_MIN};
+
+/// SI megabyte, to match the units of `glib::format_size`
+const MEGABYTE: u64 = 1_000_000;
+
+/// Names from the freedesktop sound theme offered for events. In the combo rows they come after
+/// “Off” and before “Custom Sound”.
+const THEME_SOUNDS: [&str; 4] = ["bell", "message-new-instant", "complete", "dialog-warning"];
+const CUSTOM_SOUND_INDEX: u32 = THEME_SOUNDS.len() as u32 + 1;
+
+/// Position of a configured sound in the combo rows
+fn sound_index(sound: Option<&str>) -> u32 {
+    match sound {
+        None => 0,
+        Some(sound) => THEME_SOUNDS
+            .iter()
+            .position(|name| *name == sound)
+            .map_or(CUSTOM_SOUND_INDEX, |pos| pos as u32 + 1),
+    }
+}
 
 mod imp {
     use super::*;
-    use crate::gettext::gettextf;
+    use crate::config::{
+        ColorScheme, NotificationCategory, NotificationSettings, NotificationSounds,
+        OverwritePolicy, ProgressText, QrCodeSize, QrErrorCorrection, SymlinkPolicy,
+        is_default_rendezvous_url, is_default_transit_urls, parse_transit_relay_hints,
+    };
+    use crate::gettext::{gettext, gettextf, ngettextf};
     use crate::globals;
-    use glib::Properties;
+    use crate::util::validate_rendezvous_url;
+    use glib::{Properties, clone};
     use std::cell::{Cell, RefCell};
 
     #[derive(Properties, Debug, Default, gtk::CompositeTemplate)]
@@ -23,11 +47,22 @@ mod imp {
         #[template_child]
         pub rendezvous_server_url_entry_row: TemplateChild<adw::EntryRow>,
 
+        #[template_child]
+        pub rendezvous_server_url_default_label: TemplateChild<gtk::Label>,
+
         #[template_child]
         pub transit_server_url_entry_row: TemplateChild<adw::EntryRow>,
 
+        #[template_child]
+        pub transit_server_url_default_label: TemplateChild<gtk::Label>,
+
+        #[template_child]
+        pub transit_server_url_error_icon: TemplateChild<gtk::Image>,
+
         #[template_child]
         pub code_length_spin_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub open_camera_on_receive_row: TemplateChild<adw::SwitchRow>,
 
         #[property(get, set = Self::set_rendezvous_server_url)]
         pub rendezvous_server_url: RefCell<String>,
@@ -35,6 +70,75 @@ mod imp {
         pub transit_server_url: RefCell<String>,
         #[property(get, set, default = 4, minimum = CODE_LENGTH_MIN, maximum = CODE_LENGTH_MAX)]
         pub code_length: Cell<i32>,
+        #[property(get, set)]
+        pub spell_out_code: Cell<bool>,
+        #[property(get, set)]
+        pub highlight_code_number: Cell<bool>,
+        #[property(get, set)]
+        pub clear_clipboard_secs: Cell<u32>,
+        /// Index of the selected `QrErrorCorrection` in the combo row
+        #[property(get, set)]
+        pub qr_error_correction: Cell<u32>,
+        /// Index of the selected `QrCodeSize` in the combo row
+        #[property(get, set)]
+        pub qr_code_size: Cell<u32>,
+        #[property(get, set)]
+        pub allow_send_folders: Cell<bool>,
+        #[property(get, set)]
+        pub store_symlinks: Cell<bool>,
+        #[property(get, set)]
+        pub include_hidden_files: Cell<bool>,
+        #[property(get, set)]
+        pub send_single_file_directly: Cell<bool>,
+        #[property(get, set)]
+        pub confirm_relayed_transfers: Cell<bool>,
+        #[property(get, set)]
+        pub direct_connections_only: Cell<bool>,
+        #[property(get, set)]
+        pub debug_transit: Cell<bool>,
+        #[property(get, set)]
+        pub save_as_by_default: Cell<bool>,
+        #[property(get, set)]
+        pub open_camera_on_receive: Cell<bool>,
+        #[property(get, set)]
+        pub speed_in_bits: Cell<bool>,
+        /// Index of the selected `ProgressText` in the combo row
+        #[property(get, set)]
+        pub progress_text: Cell<u32>,
+        #[property(get, set)]
+        pub reduce_progress_updates: Cell<bool>,
+        /// Index of the selected `ColorScheme` in the combo row
+        #[property(get, set)]
+        pub color_scheme: Cell<u32>,
+        #[property(get, set)]
+        pub high_contrast: Cell<bool>,
+        #[property(get, set)]
+        pub confirm_cancel: Cell<bool>,
+        #[property(get, set)]
+        pub return_home_after_completion: Cell<bool>,
+        #[property(get, set)]
+        pub idle_quit_secs: Cell<u32>,
+        #[property(get, set)]
+        pub notify_ready_to_receive: Cell<bool>,
+        #[property(get, set)]
+        pub notify_complete: Cell<bool>,
+        #[property(get, set)]
+        pub notify_error: Cell<bool>,
+        /// Index of the selected sound in the combo rows, see `sound_index`
+        #[property(get, set = Self::set_sound_ready_to_receive)]
+        pub sound_ready_to_receive: Cell<u32>,
+        #[property(get, set = Self::set_sound_complete)]
+        pub sound_complete: Cell<u32>,
+        #[property(get, set = Self::set_sound_error)]
+        pub sound_error: Cell<u32>,
+        /// The files selected for the “Custom Sound” entries
+        pub custom_sounds: RefCell<NotificationSounds>,
+        /// Maximum size of received files in MB, 0 for no limit
+        #[property(get, set)]
+        pub max_receive_size_mb: Cell<u32>,
+        /// Index of the selected `OverwritePolicy` in the combo row
+        #[property(get, set)]
+        pub overwrite_policy: Cell<u32>,
     }
 
     #[glib::object_subclass]
@@ -70,7 +174,9 @@ mod imp {
             self.parent_constructed();
             let obj = self.obj();
 
-            let window = self.app().main_window();
+            let Some(window) = self.app().main_window() else {
+                return;
+            };
             obj.set_rendezvous_server_url(
                 window
                     .config()
@@ -86,17 +192,119 @@ mod imp {
                     .unwrap_or_default(),
             );
 
+            if window.config().env.rendezvous_server_url.is_some() {
+                self.rendezvous_server_url_entry_row.set_sensitive(false);
+                self.rendezvous_server_url_entry_row
+                    .set_tooltip_text(Some(&gettextf(
+                        "Set by the environment variable “{}”",
+                        &[&globals::ENV_RENDEZVOUS_URL],
+                    )));
+            }
+
+            if window.config().env.transit_server_url.is_some() {
+                self.transit_server_url_entry_row.set_sensitive(false);
+                self.transit_server_url_entry_row
+                    .set_tooltip_text(Some(&gettextf(
+                        "Set by the environment variable “{}”",
+                        &[&globals::ENV_TRANSIT_URL],
+                    )));
+            }
+
             self.code_length_spin_row
                 .set_adjustment(Some(&gtk::Adjustment::new(
                     4f64, 2f64, 8f64, 1f64, 0f64, 0f64,
                 )));
 
             obj.set_code_length(window.config().code_length_or_default() as i32);
+            obj.set_spell_out_code(window.config().spell_out_code_or_default());
+            obj.set_highlight_code_number(window.config().highlight_code_number_or_default());
+            obj.set_clear_clipboard_secs(window.config().clear_clipboard_secs.unwrap_or_default());
+            obj.set_qr_error_correction(match window.config().qr_error_correction_or_default() {
+                QrErrorCorrection::Low => 0,
+                QrErrorCorrection::Medium => 1,
+                QrErrorCorrection::Quartile => 2,
+                QrErrorCorrection::High => 3,
+            });
+            obj.set_qr_code_size(match window.config().qr_code_size_or_default() {
+                QrCodeSize::Small => 0,
+                QrCodeSize::Medium => 1,
+                QrCodeSize::Large => 2,
+            });
+            obj.set_allow_send_folders(window.config().allow_send_folders_or_default());
+            obj.set_store_symlinks(
+                window.config().symlink_policy_or_default() == SymlinkPolicy::Store,
+            );
+            obj.set_include_hidden_files(window.config().include_hidden_files_or_default());
+            obj.set_send_single_file_directly(
+                window.config().send_single_file_directly_or_default(),
+            );
+            obj.set_confirm_relayed_transfers(
+                window.config().confirm_relayed_transfers_or_default(),
+            );
+            obj.set_direct_connections_only(window.config().direct_connections_only_or_default());
+            obj.set_debug_transit(window.config().debug_transit_or_default());
+            obj.set_save_as_by_default(window.config().save_as_by_default_or_default());
+            obj.set_open_camera_on_receive(window.config().open_camera_on_receive_or_default());
+            #[cfg(feature = "qr_code_scanning")]
+            self.open_camera_on_receive_row.set_visible(true);
+            obj.set_speed_in_bits(window.config().speed_in_bits_or_default());
+            obj.set_progress_text(match window.config().progress_text_or_default() {
+                ProgressText::Both => 0,
+                ProgressText::TimeRemaining => 1,
+                ProgressText::Bytes => 2,
+            });
+            obj.set_reduce_progress_updates(window.config().reduce_progress_updates_or_default());
+            obj.set_color_scheme(match window.config().color_scheme_or_default() {
+                ColorScheme::System => 0,
+                ColorScheme::Light => 1,
+                ColorScheme::Dark => 2,
+            });
+            obj.set_high_contrast(window.config().high_contrast_or_default());
+            obj.set_confirm_cancel(window.config().confirm_cancel_or_default());
+            obj.set_return_home_after_completion(
+                window.config().return_home_after_completion_or_default(),
+            );
+            obj.set_idle_quit_secs(
+                window
+                    .config()
+                    .idle_quit_secs
+                    .map_or(0, |secs| secs.try_into().unwrap_or(u32::MAX)),
+            );
+            let notifications = window.config().notifications_or_default();
+            obj.set_notify_ready_to_receive(notifications.ready_to_receive);
+            obj.set_notify_complete(notifications.complete);
+            obj.set_notify_error(notifications.error);
+            let sounds = window.config().notification_sounds_or_default();
+            self.custom_sounds.replace(sounds.clone());
+            // Set without the setters, which would play a preview of each sound
+            for category in [
+                NotificationCategory::ReadyToReceive,
+                NotificationCategory::Complete,
+                NotificationCategory::Error,
+            ] {
+                self.sound_index_cell(category)
+                    .set(sound_index(sounds.sound(category)));
+            }
+            obj.notify_sound_ready_to_receive();
+            obj.notify_sound_complete();
+            obj.notify_sound_error();
+            obj.set_max_receive_size_mb(
+                window
+                    .config()
+                    .max_receive_size
+                    .map_or(0, |size| (size / MEGABYTE).try_into().unwrap_or(u32::MAX)),
+            );
+            obj.set_overwrite_policy(match window.config().overwrite_policy_or_default() {
+                OverwritePolicy::Rename => 0,
+                OverwritePolicy::Overwrite => 1,
+                OverwritePolicy::Ask => 2,
+            });
 
             self.preferences_group.set_description(Some(&gettextf(
                 "Changing the rendezvous server URL needs to be done on both sides of the \
 transfer. Only enter a server URL you can trust.\n\
 \n\
+Multiple transit servers can be entered separated by commas. \
 Leaving these entries empty will use the app defaults:\n\
 Rendezvous Server: “{0}”\n\
 Transit Server: “{1}”",
@@ -112,20 +320,108 @@ Transit Server: “{1}”",
 
     impl AdwDialogImpl for WarpPreferencesDialog {
         fn closed(&self) {
-            let window = self.app().main_window();
+            let Some(window) = self.app().main_window() else {
+                return;
+            };
 
+            // Entering the default URL is the same as leaving the entry empty
             let rendezvous_url = &*self.rendezvous_server_url.borrow();
             window.config().rendezvous_server_url =
-                (!rendezvous_url.is_empty()).then(|| rendezvous_url.clone());
+                (!is_default_rendezvous_url(rendezvous_url)).then(|| rendezvous_url.clone());
 
             let transit_url = &*self.transit_server_url.borrow();
             window.config().transit_server_url =
-                (!transit_url.is_empty()).then(|| transit_url.clone());
+                (!is_default_transit_urls(transit_url)).then(|| transit_url.clone());
 
             let code_length = self.code_length.get();
             window.config().code_length = Some(code_length as usize);
 
+            window.config().spell_out_code = Some(self.spell_out_code.get());
+            window.config().highlight_code_number = Some(self.highlight_code_number.get());
+            let clear_clipboard_secs = self.clear_clipboard_secs.get();
+            window.config().clear_clipboard_secs =
+                (clear_clipboard_secs > 0).then_some(clear_clipboard_secs);
+            window.config().qr_error_correction = Some(match self.qr_error_correction.get() {
+                0 => QrErrorCorrection::Low,
+                2 => QrErrorCorrection::Quartile,
+                3 => QrErrorCorrection::High,
+                _ => QrErrorCorrection::Medium,
+            });
+            window.config().qr_code_size = Some(match self.qr_code_size.get() {
+                0 => QrCodeSize::Small,
+                2 => QrCodeSize::Large,
+                _ => QrCodeSize::Medium,
+            });
+            window.config().allow_send_folders = Some(self.allow_send_folders.get());
+            window.config().symlink_policy = Some(if self.store_symlinks.get() {
+                SymlinkPolicy::Store
+            } else {
+                SymlinkPolicy::Follow
+            });
+            window.config().include_hidden_files = Some(self.include_hidden_files.get());
+            window.config().send_single_file_directly = Some(self.send_single_file_directly.get());
+            window.config().confirm_relayed_transfers = Some(self.confirm_relayed_transfers.get());
+            window.config().direct_connections_only = Some(self.direct_connections_only.get());
+            window.config().debug_transit = Some(self.debug_transit.get());
+            window.config().save_as_by_default = Some(self.save_as_by_default.get());
+            window.config().open_camera_on_receive = Some(self.open_camera_on_receive.get());
+            window.config().speed_in_bits = Some(self.speed_in_bits.get());
+            window.config().progress_text = Some(match self.progress_text.get() {
+                1 => ProgressText::TimeRemaining,
+                2 => ProgressText::Bytes,
+                _ => ProgressText::Both,
+            });
+            window.config().reduce_progress_updates = Some(self.reduce_progress_updates.get());
+            let color_scheme = match self.color_scheme.get() {
+                1 => ColorScheme::Light,
+                2 => ColorScheme::Dark,
+                _ => ColorScheme::System,
+            };
+            window.config().color_scheme = Some(color_scheme);
+            self.app().set_color_scheme(color_scheme);
+            window.config().high_contrast = Some(self.high_contrast.get());
+            window.config().confirm_cancel = Some(self.confirm_cancel.get());
+            window.config().return_home_after_completion =
+                Some(self.return_home_after_completion.get());
+            let idle_quit_secs = self.idle_quit_secs.get();
+            window.config().idle_quit_secs =
+                (idle_quit_secs > 0).then_some(u64::from(idle_quit_secs));
+            window.config().notifications = Some(NotificationSettings {
+                ready_to_receive: self.notify_ready_to_receive.get(),
+                complete: self.notify_complete.get(),
+                error: self.notify_error.get(),
+            });
+            let mut sounds = NotificationSounds::default();
+            for category in [
+                NotificationCategory::ReadyToReceive,
+                NotificationCategory::Complete,
+                NotificationCategory::Error,
+            ] {
+                let sound = match self.sound_index_cell(category).get() {
+                    0 => None,
+                    CUSTOM_SOUND_INDEX => self
+                        .custom_sounds
+                        .borrow()
+                        .sound(category)
+                        .map(str::to_owned),
+                    index => THEME_SOUNDS
+                        .get(index as usize - 1)
+                        .map(|name| (*name).to_owned()),
+                };
+                sounds.set_sound(category, sound);
+            }
+            window.config().notification_sounds = Some(sounds);
+            let max_receive_size_mb = self.max_receive_size_mb.get();
+            window.config().max_receive_size =
+                (max_receive_size_mb > 0).then(|| u64::from(max_receive_size_mb) * MEGABYTE);
+            window.config().overwrite_policy = Some(match self.overwrite_policy.get() {
+                1 => OverwritePolicy::Overwrite,
+                2 => OverwritePolicy::Ask,
+                _ => OverwritePolicy::Rename,
+            });
+
             window.save_config();
+            window.apply_config();
         }
     }
     impl PreferencesDialogImpl for WarpPreferencesDialog {}
@@ -135,10 +431,114 @@ Transit Server: “{1}”",
             WarpApplication::default()
         }
 
+        fn sound_index_cell(&self, category: NotificationCategory) -> &Cell<u32> {
+            match category {
+                NotificationCategory::ReadyToReceive => &self.sound_ready_to_receive,
+                NotificationCategory::Complete => &self.sound_complete,
+                NotificationCategory::Error => &self.sound_error,
+            }
+        }
+
+        fn set_sound_ready_to_receive(&self, index: u32) {
+            self.select_sound(NotificationCategory::ReadyToReceive, index);
+        }
+
+        fn set_sound_complete(&self, index: u32) {
+            self.select_sound(NotificationCategory::Complete, index);
+        }
+
+        fn set_sound_error(&self, index: u32) {
+            self.select_sound(NotificationCategory::Error, index);
+        }
+
+        /// Plays the selected sound as a preview. A custom sound is chosen with a file chooser
+        /// the first time, and the event stays silent if none is chosen.
+        fn select_sound(&self, category: NotificationCategory, index: u32) {
+            let previous = self.sound_index_cell(category).replace(index);
+            if index == previous {
+                return;
+            }
+
+            if index == CUSTOM_SOUND_INDEX {
+                let custom_sound = self
+                    .custom_sounds
+                    .borrow()
+                    .sound(category)
+                    .map(str::to_owned);
+                if let Some(sound) = custom_sound {
+                    self.app().play_event_sound(&sound);
+                } else {
+                    glib::spawn_future_local(clone!(
+                        #[weak(rename_to = obj)]
+                        self.obj(),
+                        async move {
+                            obj.imp().choose_custom_sound(category).await;
+                        }
+                    ));
+                }
+            } else if let Some(name) = index
+                .checked_sub(1)
+                .and_then(|index| THEME_SOUNDS.get(index as usize))
+            {
+                self.app().play_event_sound(name);
+            }
+        }
+
+        async fn choose_custom_sound(&self, category: NotificationCategory) {
+            let filter = gtk::FileFilter::new();
+            filter.set_name(Some(&gettext("Sounds")));
+            filter.add_mime_type("audio/*");
+
+            let dialog = gtk::FileDialog::builder()
+                .title(gettext("Choose Sound"))
+                .default_filter(&filter)
+                .modal(true)
+                .build();
+
+            let obj = self.obj();
+            let root = obj.root().and_downcast::<gtk::Window>();
+            let path = match dialog.open_future(root.as_ref()).await {
+                Ok(file) => file.path(),
+                Err(err) => {
+                    log::debug!("Sound file chooser error: {:?}", err);
+                    None
+                }
+            };
+
+            if let Some(path) = path {
+                let sound = path.display().to_string();
+                self.app().play_event_sound(&sound);
+                self.custom_sounds
+                    .borrow_mut()
+                    .set_sound(category, Some(sound));
+            } else {
+                // Nothing chosen, so the event stays silent
+                self.sound_index_cell(category).set(0);
+                obj.notify(match category {
+                    NotificationCategory::ReadyToReceive => "sound-ready-to-receive",
+                    NotificationCategory::Complete => "sound-complete",
+                    NotificationCategory::Error => "sound-error",
+                });
+            }
+        }
+
+        /// Shows whether an entered server URL is the app default
+        fn update_default_label(label: &gtk::Label, is_default: bool) {
+            if is_default {
+                // Translators: Next to a server URL in preferences, when it is empty or the default
+                label.set_label(&gettext("Using App Default"));
+            } else {
+                // Translators: Next to a server URL in preferences, when it differs from the default
+                label.set_label(&gettext("Custom"));
+            }
+        }
+
         fn set_rendezvous_server_url(&self, url: String) {
-            let is_valid_url = url::Url::parse(&url).is_ok();
+            let is_valid_url = validate_rendezvous_url(&url).is_some();
+            let is_default = is_default_rendezvous_url(&url);
+            Self::update_default_label(&self.rendezvous_server_url_default_label, is_default);
 
-            if is_valid_url && !url.is_empty() {
+            if is_valid_url && !is_default {
                 self.rendezvous_server_url_entry_row
                     .add_css_class("success");
             } else {
@@ -156,26 +556,37 @@ Transit Server: “{1}”",
             }
         }
 
-        fn set_transit_server_url(&self, url: String) {
-            let url_res = url::Url::parse(&url);
-            let is_valid_hint = if let Ok(url) = url_res {
-                wormhole::transit::RelayHint::from_urls(None, [url]).is_ok()
-            } else {
-                false
-            };
-
-            if is_valid_hint && !url.is_empty() {
+        fn set_transit_server_url(&self, urls: String) {
+            let hints = parse_transit_relay_hints(&urls);
+            let invalid_urls = hints
+                .iter()
+                .filter(|(_, hint)| hint.is_err())
+                .map(|(url, _)| format!("“{url}”"))
+                .collect::<Vec<_>>();
+            let is_default = is_default_transit_urls(&urls);
+            Self::update_default_label(&self.transit_server_url_default_label, is_default);
+
+            if invalid_urls.is_empty() && !is_default {
                 self.transit_server_url_entry_row.add_css_class("success");
             } else {
                 self.transit_server_url_entry_row
                     .remove_css_class("success");
             }
 
-            if url.is_empty() || is_valid_hint {
+            if invalid_urls.is_empty() {
                 self.transit_server_url_entry_row.remove_css_class("error");
-                self.transit_server_url.replace(url);
+                self.transit_server_url_error_icon.set_visible(false);
+                self.transit_server_url.replace(urls);
             } else {
                 self.transit_server_url_entry_row.add_css_class("error");
+                self.transit_server_url_error_icon
+                    .set_tooltip_text(Some(&ngettextf(
+                        "Invalid transit server URL: {}",
+                        "Invalid transit server URLs: {}",
+                        invalid_urls.len() as u32,
+                        &[&invalid_urls.join(", ")],
+                    )));
+                self.transit_server_url_error_icon.set_visible(true);
                 self.transit_server_url.replace(String::new());
             }
         }
@@ -1,4 +1,6 @@
-use crate::gettext::duration;
+use crate::config::ProgressText;
+use crate::gettext::{duration, pgettextf};
+use crate::util::format_decimal;
 use simple_moving_average::{SMA, SingleSumSMA};
 use std::fmt::{Debug, Formatter};
 use std::ops::Add;
@@ -9,12 +11,25 @@ const SAMPLE_DURATION_MS: usize = 50;
 const SAMPLE_COUNT: usize = MOVING_AVG_MS / SAMPLE_DURATION_MS;
 const SAMPLES_PER_SECOND: usize = 1000 / SAMPLE_DURATION_MS;
 
+/// How often the UI is refreshed when power usage should be reduced
+pub const LOW_POWER_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
 pub struct FileTransferProgress {
     start_time: Instant,
     next_feed_offset: usize,
     avg: SingleSumSMA<usize, usize, SAMPLE_COUNT>,
     done_bytes: usize,
     total_bytes: usize,
+    min_update_interval: Duration,
+    last_update: Option<Instant>,
+}
+
+/// The latest known progress, ready to be displayed
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgressSnapshot {
+    pub fraction: f64,
+    /// Transferred and total size, time remaining and speed, once enough samples were taken
+    pub text: Option<String>,
 }
 
 impl Debug for FileTransferProgress {
@@ -36,9 +51,16 @@ impl FileTransferProgress {
             avg: SingleSumSMA::new(),
             done_bytes: 0,
             total_bytes,
+            min_update_interval: Duration::ZERO,
+            last_update: None,
         }
     }
 
+    /// Limits how often `set_progress` reports that the UI should be updated
+    pub fn set_min_update_interval(&mut self, interval: Duration) {
+        self.min_update_interval = interval;
+    }
+
     fn should_add_sample(&self) -> bool {
         // We only care about one record every 50ms
         let next_feed = self
@@ -57,12 +79,25 @@ impl FileTransferProgress {
             self.avg.add_sample(offset);
             self.next_feed_offset += SAMPLE_DURATION_MS;
 
-            true
+            self.should_update_ui()
         } else {
             false
         }
     }
 
+    fn should_update_ui(&mut self) -> bool {
+        let now = Instant::now();
+        if self
+            .last_update
+            .is_some_and(|last_update| now.duration_since(last_update) < self.min_update_interval)
+        {
+            false
+        } else {
+            self.last_update = Some(now);
+            true
+        }
+    }
+
     fn bytes_per_sample_size(&self) -> Option<usize> {
         (self.avg.get_num_samples() >= SAMPLES_PER_SECOND).then(|| self.avg.get_average())
     }
@@ -100,13 +135,144 @@ impl FileTransferProgress {
         })
     }
 
-    pub fn pretty_time_remaining(&self) -> Option<String> {
+    fn time_remaining_duration(&self) -> Option<chrono::Duration> {
         self.get_time_remaining()
             .and_then(|duration| chrono::Duration::from_std(duration).ok())
+    }
+
+    pub fn pretty_time_remaining(&self) -> Option<String> {
+        self.time_remaining_duration()
             .map(|d| duration::left(self.done_bytes(), self.total_bytes(), &d))
     }
 
+    pub fn pretty_transferred(&self) -> String {
+        duration::transferred(self.done_bytes(), self.total_bytes())
+    }
+
+    pub fn pretty_speed(&self, bits: bool) -> Option<String> {
+        self.bytes_s().map(|bytes_s| format_speed(bytes_s, bits))
+    }
+
+    /// Time since the transfer started
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
     pub fn progress_fraction(&self) -> f64 {
         self.done_bytes() as f64 / self.total_bytes() as f64
     }
+
+    /// The current progress, to show it without waiting for the next sample
+    pub fn snapshot(&self, speed_in_bits: bool, progress_text: ProgressText) -> ProgressSnapshot {
+        let time_remaining = match progress_text {
+            ProgressText::Both => self.pretty_time_remaining(),
+            ProgressText::TimeRemaining => self
+                .time_remaining_duration()
+                .map(|d| duration::time_remaining(&d)),
+            ProgressText::Bytes => {
+                return ProgressSnapshot {
+                    fraction: self.progress_fraction(),
+                    text: Some(self.pretty_transferred()),
+                };
+            }
+        };
+
+        let text = time_remaining.map(|time_remaining| {
+            if let Some(speed) = self.pretty_speed(speed_in_bits) {
+                pgettextf(
+                    "Combine time remaining {0} and transfer speed {1}",
+                    // Translators: {0} = 11.3MB / 20.7MB — 3 seconds left, {1} = 10.5 MB/s
+                    "{0} ({1})",
+                    &[&time_remaining, &speed],
+                )
+            } else {
+                time_remaining
+            }
+        });
+
+        ProgressSnapshot {
+            fraction: self.progress_fraction(),
+            text,
+        }
+    }
+}
+
+/// Formats a transfer rate either in bytes/s or in bits/s with SI prefixes
+pub fn format_speed(bytes_s: usize, bits: bool) -> String {
+    if bits {
+        const UNITS: [&str; 5] = ["bit/s", "kbit/s", "Mbit/s", "Gbit/s", "Tbit/s"];
+
+        let mut value = bytes_s as f64 * 8.0;
+        let mut unit = 0;
+        while value >= 1000.0 && unit < UNITS.len() - 1 {
+            value /= 1000.0;
+            unit += 1;
+        }
+
+        if unit == 0 {
+            format!("{} {}", value as usize, UNITS[unit])
+        } else {
+            format!("{} {}", format_decimal(value, 1), UNITS[unit])
+        }
+    } else {
+        pgettextf(
+            "Transfer speed",
+            // Translators: {0} = file size transferred per second, Example: 10.5 MB/s
+            "{0}/s",
+            &[&glib::format_size(bytes_s as u64)],
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_speed_bits() {
+        assert_eq!(format_speed(0, true), "0 bit/s");
+        assert_eq!(format_speed(100, true), "800 bit/s");
+        assert_eq!(format_speed(1_250, true), "10.0 kbit/s");
+        assert_eq!(format_speed(1_000_000, true), "8.0 Mbit/s");
+        assert_eq!(format_speed(12_500_000, true), "100.0 Mbit/s");
+        assert_eq!(format_speed(125_000_000, true), "1.0 Gbit/s");
+        assert_eq!(format_speed(5_000_000_000_000, true), "40.0 Tbit/s");
+        assert_eq!(format_speed(500_000_000_000_000, true), "4000.0 Tbit/s");
+    }
+
+    #[test]
+    fn test_format_speed_bytes() {
+        let speed = format_speed(12_500_000, false);
+        assert!(speed.starts_with("12.5"));
+        assert!(speed.ends_with("MB/s"));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut progress = FileTransferProgress::begin(100);
+        progress.set_progress(50);
+
+        let snapshot = progress.snapshot(false, ProgressText::Both);
+        assert!((snapshot.fraction - 0.5).abs() < f64::EPSILON);
+        // Not enough samples for an estimate yet
+        assert_eq!(snapshot.text, None);
+        assert_eq!(
+            progress.snapshot(false, ProgressText::TimeRemaining).text,
+            None
+        );
+
+        // The size is shown right away
+        let snapshot = progress.snapshot(false, ProgressText::Bytes);
+        assert_eq!(snapshot.text, Some(progress.pretty_transferred()));
+    }
+
+    #[test]
+    fn test_min_update_interval() {
+        let mut progress = FileTransferProgress::begin(100);
+        assert!(progress.should_update_ui());
+        assert!(progress.should_update_ui());
+
+        progress.set_min_update_interval(Duration::from_secs(3600));
+        assert!(!progress.should_update_ui());
+    }
 }
@@ -0,0 +1,220 @@
+//! Receiving a file, from the offer of the sender to saving it under its final name
+//!
+//! None of this needs the UI. The action view asks for confirmation and shows the progress around
+//! these steps, the self test and the interoperability tests run them directly.
+
+use crate::gettext::gettextf;
+use crate::ui::fs;
+use crate::util::error::{AppError, UiError};
+use crate::util::relay_gate::RelayGate;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+/// Name for offers without a file name, which shouldn't happen realistically
+const UNKNOWN_FILE_NAME: &str = "Unknown Filename.bin";
+
+/// Whether a file of `size` bytes may be received with the maximum size from preferences
+fn size_allowed(size: u64, max_size: Option<u64>) -> bool {
+    max_size.is_none_or(|max| size <= max)
+}
+
+/// The name an offered file is saved as, and whether it had to be shortened. Only the last
+/// component is used, otherwise the other side could write to different folders.
+fn offered_file_name(name: &str) -> (String, bool) {
+    let name = Path::new(name)
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let name = if name.is_empty() {
+        UNKNOWN_FILE_NAME.to_owned()
+    } else {
+        name
+    };
+
+    match fs::shorten_received_filename(&name) {
+        Some(shortened) => {
+            log::info!("Shortened the offered file name '{name}' to '{shortened}'");
+            (shortened, true)
+        }
+        None => (name, false),
+    }
+}
+
+/// A file offered by the sender
+pub struct Offer {
+    request: wormhole::transfer::ReceiveRequestV1,
+    /// The offered name, made safe to save the file with
+    pub file_name: String,
+    /// Whether the offered name was too long for the file system and had to be shortened
+    pub shortened: bool,
+}
+
+impl Offer {
+    pub fn size(&self) -> u64 {
+        self.request.file_size()
+    }
+
+    /// Whether the file is small enough to be received
+    pub fn size_allowed(&self, max_size: Option<u64>) -> bool {
+        size_allowed(self.size(), max_size)
+    }
+
+    /// Declines the offer. Errors are only logged, as the transfer is over either way.
+    pub async fn reject(self) {
+        if let Err(err) = self.request.reject().await {
+            log::error!("An error occurred when rejecting a transfer: {}", err);
+        }
+    }
+}
+
+/// Waits for the offer of the sender. Returns `None` if the transfer was canceled meanwhile.
+pub async fn request(
+    wormhole: wormhole::Wormhole,
+    relay_hints: Vec<wormhole::transit::RelayHint>,
+    abilities: wormhole::transit::Abilities,
+    cancel: impl Future<Output = ()>,
+) -> Result<Option<Offer>, AppError> {
+    let request =
+        wormhole::transfer::request_file(wormhole, relay_hints, abilities, cancel).await?;
+
+    Ok(request.map(|request| {
+        let (file_name, shortened) = offered_file_name(&request.file_name());
+        Offer {
+            request,
+            file_name,
+            shortened,
+        }
+    }))
+}
+
+/// The file a received file is written to until it is complete
+pub struct Target {
+    temp_file: tempfile::NamedTempFile,
+    /// Name the temporary file gets once complete
+    file_name: PathBuf,
+}
+
+impl Target {
+    /// A temporary file next to `path`, which gets the name of `path` once complete. If that
+    /// name is taken, a number is added unless `overwrite` is passed to [`Self::persist`].
+    pub fn temporary(path: &Path) -> Result<Self, AppError> {
+        let file_name = PathBuf::from(path.file_name().ok_or_else(|| {
+            UiError::new(&gettextf("Invalid path selected: {}", &[&path.display()]))
+        })?);
+
+        let mut prefix = file_name.as_os_str().to_os_string();
+        prefix.push(".");
+        let temp_file = tempfile::Builder::new()
+            .prefix(&prefix)
+            .suffix(&".warpdownload")
+            .tempfile_in(path.parent().unwrap_or(Path::new("./")))?;
+
+        Ok(Self {
+            temp_file,
+            file_name,
+        })
+    }
+
+    /// The file being written
+    pub fn path(&self) -> &Path {
+        self.temp_file.path()
+    }
+
+    /// The file to pass to [`accept`]
+    pub fn open(&self) -> Result<smol::fs::File, AppError> {
+        Ok(smol::fs::File::from(self.temp_file.reopen()?))
+    }
+
+    /// Keeps the complete file and returns where it was saved
+    pub fn persist(self, overwrite: bool) -> Result<PathBuf, AppError> {
+        let path = if overwrite {
+            fs::overwrite_persist_tempfile(self.temp_file, &self.file_name)?
+        } else {
+            fs::safe_persist_tempfile(self.temp_file, &self.file_name)?
+        };
+
+        Ok(path)
+    }
+}
+
+/// Accepts the offer and writes the file once `gate` lets the data pass. The file is closed
+/// afterwards, as Windows requires this to rename it.
+pub async fn accept(
+    offer: Offer,
+    file: smol::fs::File,
+    gate: RelayGate,
+    transit_handler: impl Fn(wormhole::transit::TransitInfo),
+    progress_handler: impl Fn(u64, u64) + 'static,
+    cancel: impl Future<Output = ()>,
+) -> Result<(), AppError> {
+    let mut file = gate.wrap(file);
+    Box::pin(
+        offer
+            .request
+            .accept(transit_handler, progress_handler, &mut file, cancel),
+    )
+    .await?;
+    file.into_inner().sync_all().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Target, UNKNOWN_FILE_NAME, offered_file_name, size_allowed};
+
+    #[test]
+    fn test_size_allowed() {
+        assert!(size_allowed(u64::MAX, None));
+        assert!(size_allowed(0, Some(1_000_000)));
+        assert!(size_allowed(1_000_000, Some(1_000_000)));
+        assert!(!size_allowed(1_000_001, Some(1_000_000)));
+    }
+
+    #[test]
+    fn test_offered_file_name() {
+        assert_eq!(
+            offered_file_name("file.txt"),
+            ("file.txt".to_owned(), false)
+        );
+        assert_eq!(
+            offered_file_name("../../.bashrc"),
+            (".bashrc".to_owned(), false)
+        );
+        assert_eq!(offered_file_name(""), (UNKNOWN_FILE_NAME.to_owned(), false));
+
+        let (name, shortened) = offered_file_name(&format!("{}.txt", "a".repeat(300)));
+        assert!(shortened);
+        assert!(name.ends_with(".txt"));
+    }
+
+    #[test]
+    fn test_target_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        std::fs::write(&path, "existing").unwrap();
+
+        // A taken name gets a number, the temporary file is gone
+        let target = Target::temporary(&path).unwrap();
+        std::fs::write(target.path(), "received").unwrap();
+        let saved = target.persist(false).unwrap();
+        assert_eq!(saved, dir.path().join("file (1).txt"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "existing");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let target = Target::temporary(&path).unwrap();
+        std::fs::write(target.path(), "replaced").unwrap();
+        assert_eq!(target.persist(true).unwrap(), path);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "replaced");
+
+        // An unfinished transfer leaves nothing behind and keeps the existing file
+        let target = Target::temporary(&path).unwrap();
+        std::fs::write(target.path(), "unfinished").unwrap();
+        drop(target);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "replaced");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let other_path = dir.path().join("other.txt");
+        drop(Target::temporary(&other_path).unwrap());
+        assert!(!other_path.exists());
+    }
+}
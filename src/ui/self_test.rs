@@ -0,0 +1,368 @@
+//! Diagnostic for devel builds that sends a generated folder to ourselves
+//!
+//! It runs the whole pipeline of archiving, connecting to the rendezvous server, establishing the
+//! transit connection and transferring the file, with two wormhole endpoints in this process.
+//! The messages are not translated, as this is only available in devel builds.
+//!
+//! The sending and receiving halves don't need the UI, so the tests also use them to check
+//! interoperability with the reference `wormhole` command line client. Receiving goes through the
+//! same steps as in the app, from the offered file name to saving the file.
+
+use crate::config::SymlinkPolicy;
+use crate::globals;
+use crate::ui::fs;
+use crate::ui::receive;
+use crate::ui::window::WarpApplicationWindow;
+use crate::util::error::{AppError, UiError};
+use crate::util::future::spawn_async;
+use crate::util::relay_gate::RelayGate;
+use crate::util::zip::ZipOptions;
+use adw::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wormhole::transit::{Abilities, ConnectionType, RelayHint};
+
+/// Number and size of the generated files, large enough to see some progress
+const TEST_FILE_COUNT: usize = 3;
+const TEST_FILE_SIZE: usize = 256 * 1024;
+
+pub struct SelfTestReport {
+    pub conn_type: Option<ConnectionType>,
+    pub size: u64,
+    pub duration: Duration,
+}
+
+impl SelfTestReport {
+    fn message(&self) -> String {
+        let conn_type = match &self.conn_type {
+            Some(ConnectionType::Direct) => "direct".to_owned(),
+            Some(ConnectionType::Relay { name: Some(name) }) => format!("relay “{name}”"),
+            Some(ConnectionType::Relay { name: None }) => "relay".to_owned(),
+            Some(_) | None => "unknown".to_owned(),
+        };
+
+        format!(
+            "Transferred {} in {:.1} s.\nConnection: {}",
+            glib::format_size(self.size),
+            self.duration.as_secs_f64(),
+            conn_type
+        )
+    }
+}
+
+/// Sends a small generated folder to ourselves. The temporary files are deleted afterwards.
+pub async fn run(
+    app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    relay_hints: Vec<RelayHint>,
+    code_length: usize,
+) -> Result<SelfTestReport, AppError> {
+    std::fs::create_dir_all(&*globals::CACHE_DIR)?;
+    // Deletes all files of the test when dropped
+    let temp_dir = tempfile::Builder::new()
+        .prefix("warp_self_test_")
+        .tempdir_in(&*globals::CACHE_DIR)?;
+
+    let folder = temp_dir.path().join("warp-self-test");
+    std::fs::create_dir(&folder)?;
+    for i in 0..TEST_FILE_COUNT {
+        let content: Vec<u8> = (0..TEST_FILE_SIZE)
+            .map(|byte| ((byte * (i + 1)) % 251) as u8)
+            .collect();
+        std::fs::write(folder.join(format!("file-{i}.bin")), content)?;
+    }
+
+    let archive = fs::compress_folder(
+        &folder,
+        ZipOptions {
+            symlink_policy: SymlinkPolicy::default(),
+            include_hidden: true,
+        },
+        |_| {},
+    )
+    .await?;
+    let received_dir = temp_dir.path().join("received");
+    std::fs::create_dir(&received_dir)?;
+
+    let start = Instant::now();
+    let (conn_type, received_path) = spawn_async(transfer(
+        app_cfg,
+        relay_hints,
+        code_length,
+        archive.path().to_path_buf(),
+        received_dir,
+    ))
+    .await?;
+    let duration = start.elapsed();
+
+    if received_path.file_name() != archive.path().file_name() {
+        return Err(UiError::new("The received file was saved under a different name").into());
+    }
+    if std::fs::read(archive.path())? != std::fs::read(&received_path)? {
+        return Err(UiError::new("The received file differs from the sent file").into());
+    }
+    let size = std::fs::metadata(&received_path)?.len();
+
+    Ok(SelfTestReport {
+        conn_type,
+        size,
+        duration,
+    })
+}
+
+async fn transfer(
+    app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    relay_hints: Vec<RelayHint>,
+    code_length: usize,
+    path: PathBuf,
+    received_dir: PathBuf,
+) -> Result<(Option<ConnectionType>, PathBuf), AppError> {
+    let sender_mailbox = wormhole::MailboxConnection::create(app_cfg.clone(), code_length).await?;
+    let code = sender_mailbox.code().to_owned();
+    log::info!("Self test: Sending with code '{}'", code);
+
+    let (_, received) = futures::try_join!(
+        send_path(sender_mailbox, relay_hints.clone(), &path),
+        receive_to_dir(app_cfg, relay_hints.clone(), code, &received_dir, None),
+    )?;
+
+    Ok(received)
+}
+
+/// Sends the file at `path` to the peer that connects to `mailbox`
+async fn send_path(
+    mailbox: wormhole::MailboxConnection<wormhole::transfer::AppVersion>,
+    relay_hints: Vec<RelayHint>,
+    path: &Path,
+) -> Result<(), AppError> {
+    let sender = wormhole::Wormhole::connect(mailbox).await?;
+    let mut file = smol::fs::File::open(path).await?;
+    let size = file.metadata().await?.len();
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    wormhole::transfer::send_file(
+        sender,
+        relay_hints,
+        &mut file,
+        file_name,
+        size,
+        Abilities::ALL,
+        |_| {},
+        |_, _| {},
+        futures::future::pending(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Receives the file offered with `code` into `dir` like the app does, with the same file names,
+/// size limit and temporary files. Returns how we were connected to the peer and where the file
+/// was saved.
+async fn receive_to_dir(
+    app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    relay_hints: Vec<RelayHint>,
+    code: wormhole::Code,
+    dir: &Path,
+    max_size: Option<u64>,
+) -> Result<(Option<ConnectionType>, PathBuf), AppError> {
+    let mailbox = wormhole::MailboxConnection::connect(app_cfg, code, false).await?;
+    let receiver = wormhole::Wormhole::connect(mailbox).await?;
+
+    let offer = receive::request(
+        receiver,
+        relay_hints,
+        Abilities::ALL,
+        futures::future::pending(),
+    )
+    .await?
+    .ok_or(AppError::Canceled)?;
+
+    if !offer.size_allowed(max_size) {
+        offer.reject().await;
+        return Err(UiError::new("The offered file exceeds the maximum file size").into());
+    }
+
+    let size = offer.size();
+    let target = receive::Target::temporary(&dir.join(&offer.file_name))?;
+    let conn_type = Arc::new(Mutex::new(None));
+    let received = Arc::new(AtomicU64::new(0));
+
+    let transit_conn_type = conn_type.clone();
+    let progress_received = received.clone();
+    receive::accept(
+        offer,
+        target.open()?,
+        RelayGate::default(),
+        move |info: wormhole::transit::TransitInfo| {
+            *transit_conn_type.lock().unwrap() = Some(info.conn_type);
+        },
+        move |sent, _total| progress_received.store(sent, Ordering::Relaxed),
+        futures::future::pending(),
+    )
+    .await?;
+
+    if received.load(Ordering::Relaxed) != size {
+        return Err(UiError::new("The progress did not reach the file size").into());
+    }
+
+    let path = target.persist(false)?;
+    let conn_type = conn_type.lock().unwrap().take();
+    Ok((conn_type, path))
+}
+
+/// Runs the self test and shows the result in a dialog
+pub async fn run_and_report(window: &WarpApplicationWindow) {
+    if !window.confirm_new_transfer().await {
+        return;
+    }
+
+    let config = window.config();
+    let app_cfg = config.app_cfg();
+    let code_length = config.code_length_or_default();
+    let relay_hints = config.transit_relay_hints();
+    drop(config);
+
+    let Ok(relay_hints) = relay_hints else {
+        UiError::new("Invalid transit URL in preferences").handle();
+        return;
+    };
+
+    log::info!("Running self test");
+    let (heading, body) = match run(app_cfg, relay_hints, code_length).await {
+        Ok(report) => ("Self Test Succeeded", report.message()),
+        Err(err) => {
+            log::error!("Self test failed: {}", err);
+            ("Self Test Failed", err.to_string())
+        }
+    };
+
+    let dialog = adw::AlertDialog::new(Some(heading), Some(&body));
+    dialog.add_response("close", "_Close");
+    dialog.present(Some(window));
+}
+
+/// Interoperability with the reference `wormhole` client. These tests use the public servers, so
+/// they are only run on request: `cargo test -- --ignored interop`
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process::{Command, Stdio};
+
+    const CLI: &str = "wormhole";
+
+    #[allow(clippy::print_stderr)]
+    fn cli_available() -> bool {
+        let available = Command::new(CLI)
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+
+        if !available {
+            eprintln!("Skipping, the `{CLI}` command line client is not installed");
+        }
+        available
+    }
+
+    fn app_cfg() -> wormhole::AppConfig<wormhole::transfer::AppVersion> {
+        wormhole::AppConfig {
+            id: wormhole::AppID::new(globals::WORMHOLE_DEFAULT_APPID_STR),
+            rendezvous_url: format!("{}/v1", globals::WORMHOLE_DEFAULT_RENDEZVOUS_SERVER_STR)
+                .into(),
+            app_version: wormhole::transfer::AppVersion::default(),
+        }
+    }
+
+    fn test_content() -> Vec<u8> {
+        (0..TEST_FILE_SIZE).map(|byte| (byte % 251) as u8).collect()
+    }
+
+    #[test]
+    #[ignore = "needs the wormhole client and network access"]
+    fn test_interop_receive_from_cli() {
+        if !cli_available() {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("interop.bin");
+        let received_dir = dir.path().join("received");
+        std::fs::write(&path, test_content()).unwrap();
+        std::fs::create_dir(&received_dir).unwrap();
+
+        // The client lets us choose the code, so we know it without parsing its output
+        let code = format!("{}-warp-interop", std::process::id() % 1000 + 1);
+        let mut child = Command::new(CLI)
+            .args(["send", "--hide-progress", "--code", &code])
+            .arg(&path)
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let res = smol::block_on(receive_to_dir(
+            app_cfg(),
+            globals::WORMHOLE_DEFAULT_TRANSIT_RELAY_HINTS.clone(),
+            code.parse().unwrap(),
+            &received_dir,
+            Some(TEST_FILE_SIZE as u64),
+        ));
+        let status = child.wait().unwrap();
+
+        let (_, received_path) = res.unwrap();
+        assert!(status.success());
+        // Saved under the offered name, without leftover temporary files
+        assert_eq!(received_path, received_dir.join("interop.bin"));
+        assert_eq!(std::fs::read_dir(&received_dir).unwrap().count(), 1);
+        assert_eq!(std::fs::read(&received_path).unwrap(), test_content());
+    }
+
+    #[test]
+    #[ignore = "needs the wormhole client and network access"]
+    fn test_interop_send_to_cli() {
+        if !cli_available() {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("interop.bin");
+        let received_path = dir.path().join("received.bin");
+        std::fs::write(&path, test_content()).unwrap();
+
+        smol::block_on(async {
+            let mailbox = wormhole::MailboxConnection::create(app_cfg(), 2)
+                .await
+                .unwrap();
+            let mut child = Command::new(CLI)
+                .args([
+                    "receive",
+                    "--hide-progress",
+                    "--accept-file",
+                    "--output-file",
+                ])
+                .arg(&received_path)
+                .arg(mailbox.code().to_string())
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap();
+
+            let res = send_path(
+                mailbox,
+                globals::WORMHOLE_DEFAULT_TRANSIT_RELAY_HINTS.clone(),
+                &path,
+            )
+            .await;
+            let status = child.wait().unwrap();
+
+            res.unwrap();
+            assert!(status.success());
+        });
+
+        assert_eq!(std::fs::read(&received_path).unwrap(), test_content());
+    }
+}
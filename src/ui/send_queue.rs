@@ -0,0 +1,156 @@
+//! Rows to review the files and folders selected for sending before the transfer starts
+
+use crate::gettext::*;
+use adw::prelude::*;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Total size of a file, or of the files in a folder. Symbolic links and entries that can't be
+/// read are not counted.
+pub fn path_size(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+
+    if metadata.is_dir() {
+        let Ok(entries) = std::fs::read_dir(path) else {
+            return 0;
+        };
+
+        entries
+            .flatten()
+            .map(|entry| path_size(&entry.path()))
+            .sum()
+    } else if metadata.is_file() {
+        metadata.len()
+    } else {
+        0
+    }
+}
+
+/// Moves the item at `from` to the position `to`, shifting the items in between
+pub fn move_item<T>(items: &mut Vec<T>, from: usize, to: usize) {
+    if from >= items.len() || to >= items.len() {
+        return;
+    }
+
+    let item = items.remove(from);
+    items.insert(to, item);
+}
+
+/// A button next to a queued path, with its tooltip as the only label
+fn row_button(icon_name: &str, tooltip: &str) -> gtk::Button {
+    gtk::Button::builder()
+        .icon_name(icon_name)
+        .tooltip_text(tooltip)
+        .valign(gtk::Align::Center)
+        .css_classes(["flat"])
+        .build()
+}
+
+/// A row for the queued path at `index` out of `count`. Rows can be dragged onto another row to
+/// take its place, or moved by one position with the buttons, which also works with the keyboard.
+pub fn row(
+    path: &Path,
+    size: u64,
+    index: usize,
+    count: usize,
+    on_remove: impl Fn(usize) + 'static,
+    on_move: impl Fn(usize, usize) + 'static,
+) -> adw::ActionRow {
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+
+    let row = adw::ActionRow::builder()
+        .title(name)
+        .subtitle(glib::format_size(size))
+        .use_markup(false)
+        .build();
+    row.add_prefix(&gtk::Image::from_icon_name("list-drag-handle-symbolic"));
+
+    let on_move = Rc::new(on_move);
+
+    let up_button = row_button(
+        "go-up-symbolic",
+        // Translators: Tooltip of the button next to each file before sending
+        &gettext("Move Up"),
+    );
+    up_button.set_sensitive(index > 0);
+    up_button.connect_clicked(glib::clone!(
+        #[strong]
+        on_move,
+        move |_| on_move(index, index - 1)
+    ));
+    row.add_suffix(&up_button);
+
+    let down_button = row_button(
+        "go-down-symbolic",
+        // Translators: Tooltip of the button next to each file before sending
+        &gettext("Move Down"),
+    );
+    down_button.set_sensitive(index + 1 < count);
+    down_button.connect_clicked(glib::clone!(
+        #[strong]
+        on_move,
+        move |_| on_move(index, index + 1)
+    ));
+    row.add_suffix(&down_button);
+
+    let remove_button = row_button(
+        "user-trash-symbolic",
+        // Translators: Tooltip of the button next to each file before sending
+        &gettext("Remove"),
+    );
+    remove_button.connect_clicked(move |_| on_remove(index));
+    row.add_suffix(&remove_button);
+
+    let index = u32::try_from(index).unwrap_or(u32::MAX);
+    let drag_source = gtk::DragSource::builder()
+        .actions(gdk::DragAction::MOVE)
+        .content(&gdk::ContentProvider::for_value(&index.to_value()))
+        .build();
+    row.add_controller(drag_source);
+
+    let drop_target = gtk::DropTarget::new(u32::static_type(), gdk::DragAction::MOVE);
+    drop_target.connect_drop(move |_, value, _, _| {
+        let Ok(from) = value.get::<u32>() else {
+            return false;
+        };
+
+        on_move(from as usize, index as usize);
+        true
+    });
+    row.add_controller(drop_target);
+
+    row
+}
+
+#[cfg(test)]
+mod test {
+    use super::{move_item, path_size};
+
+    #[test]
+    fn test_move_item() {
+        let mut items = vec!['a', 'b', 'c', 'd'];
+        move_item(&mut items, 0, 2);
+        assert_eq!(items, ['b', 'c', 'a', 'd']);
+        move_item(&mut items, 3, 0);
+        assert_eq!(items, ['d', 'b', 'c', 'a']);
+        move_item(&mut items, 1, 4);
+        assert_eq!(items, ['d', 'b', 'c', 'a']);
+    }
+
+    #[test]
+    fn test_path_size() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a"), [0; 10]).unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("b"), [0; 5]).unwrap();
+
+        assert_eq!(path_size(&dir.path().join("a")), 10);
+        assert_eq!(path_size(dir.path()), 15);
+        assert_eq!(path_size(&dir.path().join("missing")), 0);
+    }
+}
@@ -0,0 +1,55 @@
+//! Sounds for transfer events, from a file or the freedesktop sound theme
+
+use gtk::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// File types of the sound theme, in order of preference
+const THEME_SOUND_EXTENSIONS: [&str; 3] = ["oga", "ogg", "wav"];
+
+/// Finds the file of a sound, given as a path or a sound theme name like `complete`
+pub fn resolve(sound: &str) -> Option<PathBuf> {
+    let path = Path::new(sound);
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    std::iter::once(glib::user_data_dir())
+        .chain(glib::system_data_dirs())
+        .flat_map(|dir| {
+            let theme_dir = dir.join("sounds").join("freedesktop").join("stereo");
+            THEME_SOUND_EXTENSIONS.map(|extension| theme_dir.join(format!("{sound}.{extension}")))
+        })
+        .find(|path| path.is_file())
+}
+
+/// Starts playing a sound. It stops when the returned stream is dropped.
+pub fn play(sound: &str) -> Option<gtk::MediaFile> {
+    let Some(path) = resolve(sound) else {
+        log::warn!("Sound '{}' not found", sound);
+        return None;
+    };
+
+    log::debug!("Playing sound '{}'", path.display());
+    let media = gtk::MediaFile::for_filename(&path);
+    media.play();
+    Some(media)
+}
+
+#[cfg(test)]
+mod test {
+    use super::resolve;
+
+    #[test]
+    fn test_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("done.oga");
+        std::fs::write(&path, b"").unwrap();
+
+        assert_eq!(resolve(path.to_str().unwrap()), Some(path.clone()));
+        assert_eq!(
+            resolve(dir.path().join("missing.oga").to_str().unwrap()),
+            None
+        );
+        assert_eq!(resolve("warp-no-such-sound"), None);
+    }
+}
@@ -1,11 +1,14 @@
+use crate::gettext::*;
 use crate::globals;
 use crate::ui::application::WarpApplication;
+use crate::util::release_notes_to_text;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 
 mod imp {
     use super::*;
     use gtk::CompositeTemplate;
+    use std::cell::Cell;
 
     #[derive(Debug, Default, CompositeTemplate)]
     #[template(file = "welcome_dialog.ui")]
@@ -15,11 +18,19 @@ mod imp {
         #[template_child]
         pub(super) page_detail: TemplateChild<adw::NavigationPage>,
         #[template_child]
+        pub(super) page_whats_new: TemplateChild<adw::NavigationPage>,
+        #[template_child]
+        pub(super) whats_new_status_page: TemplateChild<adw::StatusPage>,
+        #[template_child]
+        pub(super) release_notes_label: TemplateChild<gtk::Label>,
+        #[template_child]
         status_page: TemplateChild<adw::StatusPage>,
         #[template_child]
         next_button: TemplateChild<gtk::Button>,
         #[template_child]
         get_started_button: TemplateChild<gtk::Button>,
+
+        pub(super) whats_new: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -47,7 +58,19 @@ mod imp {
     }
 
     impl WidgetImpl for WelcomeDialog {}
-    impl AdwDialogImpl for WelcomeDialog {}
+    impl AdwDialogImpl for WelcomeDialog {
+        fn closed(&self) {
+            self.parent_closed();
+
+            if !self.whats_new.get() {
+                return;
+            }
+
+            if let Some(window) = WarpApplication::default().main_window() {
+                window.set_current_version_seen();
+            }
+        }
+    }
 }
 
 glib::wrapper! {
@@ -62,6 +85,30 @@ impl WelcomeDialog {
         glib::Object::new()
     }
 
+    /// Shows the changes of the current version instead of the introduction
+    pub fn new_whats_new() -> Self {
+        let obj = Self::new();
+        let imp = obj.imp();
+        imp.whats_new.set(true);
+
+        imp.whats_new_status_page
+            .set_icon_name(Some(globals::APP_ID));
+        imp.whats_new_status_page.set_description(Some(&gettextf(
+            "Warp was updated to version {}",
+            &[&globals::VERSION],
+        )));
+
+        let about =
+            adw::AboutDialog::from_appdata("app/drey/Warp/metainfo.xml", Some(globals::VERSION));
+        let release_notes = release_notes_to_text(&about.release_notes());
+        imp.release_notes_label
+            .set_visible(!release_notes.is_empty());
+        imp.release_notes_label.set_label(&release_notes);
+
+        imp.navigation_view.replace(&[imp.page_whats_new.get()]);
+        obj
+    }
+
     fn app(&self) -> WarpApplication {
         WarpApplication::default()
     }
@@ -71,9 +118,16 @@ impl WelcomeDialog {
         self.imp().navigation_view.push(&*self.imp().page_detail);
     }
 
+    #[template_callback]
+    pub fn continue_button_clicked(&self) {
+        self.close();
+    }
+
     #[template_callback]
     pub fn get_started_button_clicked(&self) {
-        self.app().main_window().set_welcome_window_shown(true);
+        if let Some(window) = self.app().main_window() {
+            window.set_welcome_window_shown(true);
+        }
         self.close();
     }
 }
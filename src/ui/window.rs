@@ -10,25 +10,77 @@ use crate::ui::action_view::ActionView;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::clone;
-use std::cell::RefMut;
+use std::cell::{Cell, RefMut};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 use crate::ui::application::WarpApplication;
 use crate::util::error::AppError;
 use crate::util::{
     TransferDirection, WormholeTransferURI, error::UiError, extract_transmit_code,
-    extract_transmit_uri, future::main_async_local_infallible,
+    extract_transmit_uri, future::main_async_local_infallible, is_plausible_transmit_code,
+    sanitize_note, show_dir, validate_download_url,
 };
 
 use super::licenses::AboutDialogLicenseExt;
 
+/// How long a code we generated is still not inserted from the clipboard after its transfer ended
+const GENERATED_CODE_EXPIRY: Duration = Duration::from_secs(60);
+
+/// Codes we created for our own transfers, which are not inserted from the clipboard on the receive
+/// page. They expire shortly after the transfer ended, so a later transfer that happens to use the
+/// same code is still detected.
+#[derive(Debug, Default)]
+pub struct GeneratedCodes {
+    /// When the transfer of each code ended, or `None` while it is running
+    codes: HashMap<String, Option<Instant>>,
+}
+
+impl GeneratedCodes {
+    fn insert(&mut self, code: String) {
+        self.codes.insert(code, None);
+    }
+
+    /// Starts the expiry of `code`, as the transfer it was created for is over
+    fn end_transfer(&mut self, code: &str, now: Instant) {
+        if let Some(ended) = self.codes.get_mut(code) {
+            ended.get_or_insert(now);
+        }
+    }
+
+    /// Whether `code` is ours and not expired. Expired codes are removed.
+    fn contains(&mut self, code: &str, now: Instant) -> bool {
+        self.codes.retain(|_, ended| {
+            ended.is_none_or(|ended| now.saturating_duration_since(ended) < GENERATED_CODE_EXPIRY)
+        });
+        self.codes.contains_key(code)
+    }
+}
+
+/// Types of the “Documents” filter of the file dialog
+const DOCUMENT_MIME_TYPES: &[&str] = &[
+    "application/pdf",
+    "text/plain",
+    "application/vnd.oasis.opendocument.text",
+    "application/vnd.oasis.opendocument.spreadsheet",
+    "application/vnd.oasis.opendocument.presentation",
+    "application/msword",
+    "application/vnd.ms-excel",
+    "application/vnd.ms-powerpoint",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+];
+
 mod imp {
     use super::*;
-    use crate::config::PersistentConfig;
+    use crate::config::{EnvOverrides, PersistentConfig};
     use crate::ui::welcome_dialog::WelcomeDialog;
     use crate::util::{error::UiError, future::main_async_local_infallible};
     use std::cell::{Cell, RefCell};
-    use std::collections::HashSet;
 
     #[derive(Default, gtk::CompositeTemplate)]
     #[template(file = "window.ui")]
@@ -49,8 +101,16 @@ mod imp {
         #[template_child]
         pub headerbar: TemplateChild<adw::HeaderBar>,
         #[template_child]
+        pub do_not_disturb_image: TemplateChild<gtk::Image>,
+        #[template_child]
         pub navigation_view: TemplateChild<adw::NavigationView>,
         #[template_child]
+        pub send_note_entry: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub send_receivers_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub send_url_entry: TemplateChild<adw::EntryRow>,
+        #[template_child]
         pub send_select_file_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub send_select_folder_button: TemplateChild<gtk::Button>,
@@ -59,8 +119,14 @@ mod imp {
         #[template_child]
         pub code_entry: TemplateChild<adw::EntryRow>,
         #[template_child]
+        pub code_format_hint_label: TemplateChild<gtk::Label>,
+        #[template_child]
         pub scan_qr_code_button: TemplateChild<gtk::Button>,
         #[template_child]
+        pub recently_received_clamp: TemplateChild<adw::Clamp>,
+        #[template_child]
+        pub recently_received_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
         pub action_view: TemplateChild<ActionView>,
         #[template_child]
         pub file_dialog: TemplateChild<gtk::FileDialog>,
@@ -73,9 +139,13 @@ mod imp {
 
         pub action_view_showing: Cell<bool>,
         pub config: RefCell<PersistentConfig>,
-        pub generated_transmit_codes: RefCell<HashSet<String>>,
+        pub generated_transmit_codes: RefCell<super::GeneratedCodes>,
+        pub recently_received: RefCell<Option<PathBuf>>,
         pub inserted_code_toast_showing: Cell<bool>,
         pub close_in_progress: Cell<bool>,
+        /// Time of the last input or transfer state change, and the timer that checks it
+        pub last_activity: Cell<Option<Instant>>,
+        pub idle_source_id: RefCell<Option<glib::SourceId>>,
     }
 
     #[glib::object_subclass]
@@ -124,14 +194,14 @@ mod imp {
                         };
 
                         if !obj.imp().action_view_showing.get() {
-                            obj.open_code_from_uri(uri);
+                            obj.open_uri_auto(uri);
                         }
                     }
                 ));
             }
 
             self.config
-                .replace(PersistentConfig::from_file().unwrap_or_else(
+                .replace(PersistentConfig::from_file_or_back_up().unwrap_or_else(
                 clone!(#[strong(rename_to = obj)] self.obj(), move |err| {
                     obj.connect_visible_notify(move |window| {
                         if window.is_visible() {
@@ -145,12 +215,18 @@ mod imp {
                     });
 
                     let mut config = PersistentConfig::default();
+                    config.env = EnvOverrides::from_env();
                     // Prevent the welcome window from obscuring the error message
                     config.config.welcome_window_shown = true;
                     config
                 }),
             ));
 
+            obj.apply_config();
+            obj.setup_file_dialog_filters();
+            obj.action_set_enabled(Action::CopyCode.as_ref(), false);
+            obj.action_set_enabled(Action::CopyLink.as_ref(), false);
+
             let drop_type = gio::File::static_type();
             let drag_action = gdk::DragAction::COPY;
             let drop_target = gtk::DropTarget::new(drop_type, drag_action);
@@ -171,6 +247,21 @@ mod imp {
                 }
             ));
             self.send_box.add_controller(drop_target);
+
+            // Any input postpones closing the window after inactivity
+            let activity_controller = gtk::EventControllerLegacy::new();
+            activity_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+            activity_controller.connect_event(clone!(
+                #[weak(rename_to = obj)]
+                self.obj(),
+                #[upgrade_or]
+                glib::Propagation::Proceed,
+                move |_, _| {
+                    obj.note_activity();
+                    glib::Propagation::Proceed
+                }
+            ));
+            self.obj().add_controller(activity_controller);
         }
     }
 
@@ -181,9 +272,18 @@ mod imp {
 
             widget.load_window_size();
 
+            let last_seen_version = self.config.borrow().last_seen_version.clone();
             if !self.config.borrow().welcome_window_shown {
                 let welcome_window = WelcomeDialog::new();
                 welcome_window.present(Some(&*widget));
+            } else if last_seen_version.as_deref() != Some(globals::VERSION) {
+                log::info!(
+                    "Updated from version {} to {}",
+                    last_seen_version.as_deref().unwrap_or("unknown"),
+                    globals::VERSION
+                );
+                let whats_new_window = WelcomeDialog::new_whats_new();
+                whats_new_window.present(Some(&*widget));
             }
         }
     }
@@ -224,42 +324,6 @@ mod imp {
 
     impl ApplicationWindowImpl for WarpApplicationWindow {}
     impl AdwApplicationWindowImpl for WarpApplicationWindow {}
-
-    impl WarpApplicationWindow {
-        pub fn open_help(&self, page: Option<&str>) {
-            /* `help:` URIs are a Linux specific thing and won't work on Windows. There, we'll just open the path to the
-             * respective HTML files and hope that it launches a browser …
-             */
-            let help_uri = if cfg!(target_os = "linux") {
-                format!("help:warp/{}", page.unwrap_or_default())
-            } else {
-                let file = page.unwrap_or("index");
-                let mut uri = globals::WINDOWS_BASE_PATH.clone();
-                /* Hardcode the "C" language for now, so no translated help files *sigh*
-                 *
-                 * The problem is that gettext is a mess and does not provide us with a good way
-                 * to query the currenty used language. In theory it can do that, but the values
-                 * it returned on Windows did not work as they should.
-                 */
-                uri.push(format!("share\\help\\C\\warp\\{file}.html"));
-                /* People with non-UTF-8 paths will at least get a good error message */
-                let mut uri = uri.to_string_lossy().into_owned();
-                uri.insert_str(0, "file:///");
-                uri
-            };
-
-            log::debug!("Opening '{}' to show help", help_uri);
-            let context = gtk::prelude::WidgetExt::display(&*self.obj()).app_launch_context();
-
-            glib::MainContext::default().spawn_local(async move {
-                if let Err(err) =
-                    gio::AppInfo::launch_default_for_uri_future(&help_uri, Some(&context)).await
-                {
-                    log::error!("Error launching help: {err:?}");
-                }
-            });
-        }
-    }
 }
 
 glib::wrapper! {
@@ -272,10 +336,35 @@ glib::wrapper! {
 #[gtk::template_callbacks]
 impl WarpApplicationWindow {
     pub fn new(app: &WarpApplication) -> Self {
-        glib::Object::builder()
+        let window: Self = glib::Object::builder()
             .property("application", app)
             .property("icon-name", globals::APP_ID)
-            .build()
+            .build();
+
+        // Do not disturb applies to all windows, so every window shows that it's active
+        if let Some(action) = app.lookup_action("do-not-disturb") {
+            action.connect_notify_local(
+                Some("state"),
+                clone!(
+                    #[weak]
+                    window,
+                    #[weak]
+                    app,
+                    move |_, _| {
+                        window
+                            .imp()
+                            .do_not_disturb_image
+                            .set_visible(app.do_not_disturb());
+                    }
+                ),
+            );
+        }
+        window
+            .imp()
+            .do_not_disturb_image
+            .set_visible(app.do_not_disturb());
+
+        window
     }
 
     pub fn config(&self) -> RefMut<'_, PersistentConfig> {
@@ -285,14 +374,329 @@ impl WarpApplicationWindow {
     pub fn save_config(&self) {
         if let Err(err) = self.imp().config.borrow_mut().save() {
             UiError::new(&gettextf("Error saving configuration file: {}", &[&err])).handle();
+            return;
         }
+
+        // Other windows would otherwise overwrite the changes with their outdated copy
+        for window in self.app().iter().flat_map(WarpApplication::app_windows) {
+            if window != *self {
+                window.reload_config();
+            }
+        }
+    }
+
+    /// Loads the configuration file again, after another window saved it
+    fn reload_config(&self) {
+        match PersistentConfig::from_file() {
+            Ok(config) => {
+                self.imp().config.replace(config);
+                self.apply_config();
+            }
+            Err(err) => log::warn!("Error reloading config file: {err}"),
+        }
+    }
+
+    /// Updates the parts of the UI that depend on preferences
+    pub fn apply_config(&self) {
+        let allow_send_folders = self.config().allow_send_folders_or_default();
+        self.imp()
+            .send_select_folder_button
+            .set_visible(allow_send_folders);
+        self.action_set_enabled(Action::OpenFolder.as_ref(), allow_send_folders);
+
+        // The rules for this class are in style.css
+        if self.config().high_contrast_or_default() {
+            self.add_css_class("high-contrast");
+        } else {
+            self.remove_css_class("high-contrast");
+        }
+
+        self.restart_idle_timer();
+    }
+
+    /// Marks the window as in use, which postpones closing it after inactivity
+    pub fn note_activity(&self) {
+        self.imp().last_activity.set(Some(Instant::now()));
+    }
+
+    /// Starts the timer that closes the window after the time without activity set in preferences
+    fn restart_idle_timer(&self) {
+        if let Some(source_id) = self.imp().idle_source_id.take() {
+            source_id.remove();
+        }
+
+        let Some(idle_secs) = self.config().idle_quit_secs else {
+            return;
+        };
+
+        self.note_activity();
+        self.schedule_idle_check(Duration::from_secs(idle_secs));
+    }
+
+    fn schedule_idle_check(&self, delay: Duration) {
+        let source_id = glib::timeout_add_local_once(
+            delay,
+            clone!(
+                #[weak(rename_to = obj)]
+                self,
+                move || {
+                    // The source is gone once it ran
+                    obj.imp().idle_source_id.take();
+                    obj.check_idle();
+                }
+            ),
+        );
+        self.imp().idle_source_id.replace(Some(source_id));
+    }
+
+    fn check_idle(&self) {
+        let Some(idle_secs) = self.config().idle_quit_secs else {
+            return;
+        };
+
+        let idle_time = Duration::from_secs(idle_secs);
+        let idle = self
+            .imp()
+            .last_activity
+            .get()
+            .map_or(idle_time, |last_activity| last_activity.elapsed());
+
+        if idle < idle_time {
+            self.schedule_idle_check(idle_time - idle);
+        } else if self.transfer_in_progress() {
+            // Never quit during a transfer, however long it takes
+            self.note_activity();
+            self.schedule_idle_check(idle_time);
+        } else {
+            log::info!("Closing the window after {idle_secs} seconds without activity");
+            self.close();
+        }
+    }
+
+    /// Whether progress updates should be reduced to save power, either because the user asked for
+    /// it or because power saver mode is enabled
+    pub fn low_power_mode(&self) -> bool {
+        self.config().reduce_progress_updates_or_default()
+            || gio::PowerProfileMonitor::get_default().is_power_saver_enabled()
     }
 
     pub fn set_welcome_window_shown(&self, shown: bool) {
-        self.imp().config.borrow_mut().welcome_window_shown = shown;
+        let mut config = self.imp().config.borrow_mut();
+        config.welcome_window_shown = shown;
+        // A new install doesn't need to be told what changed
+        config.last_seen_version = Some(globals::VERSION.to_owned());
+        drop(config);
+
+        self.save_config();
+    }
+
+    pub fn set_current_version_seen(&self) {
+        self.imp().config.borrow_mut().last_seen_version = Some(globals::VERSION.to_owned());
         self.save_config();
     }
 
+    /// Offers filters for common kinds of files to send, with all files selected by default
+    fn setup_file_dialog_filters(&self) {
+        let all_filter = gtk::FileFilter::new();
+        all_filter.set_name(Some(&gettext("All Files")));
+        all_filter.add_pattern("*");
+
+        let images_filter = gtk::FileFilter::new();
+        images_filter.set_name(Some(&gettext("Images")));
+        images_filter.add_mime_type("image/*");
+
+        let documents_filter = gtk::FileFilter::new();
+        documents_filter.set_name(Some(&gettext("Documents")));
+        for mime_type in DOCUMENT_MIME_TYPES {
+            documents_filter.add_mime_type(mime_type);
+        }
+
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+        filters.extend_from_slice(&[all_filter.clone(), images_filter, documents_filter]);
+
+        let file_dialog = &self.imp().file_dialog;
+        file_dialog.set_filters(Some(&filters));
+        file_dialog.set_default_filter(Some(&all_filter));
+    }
+
+    /// Prepares the file dialog for selecting what to send. It starts in the folder of the last
+    /// selection, unless it was removed since.
+    fn prepare_send_file_dialog(&self, title: &str) -> gtk::FileDialog {
+        let file_dialog = self.imp().file_dialog.get();
+        file_dialog.set_title(title);
+
+        let last_send_dir = self
+            .config()
+            .last_send_dir
+            .clone()
+            .filter(|dir| dir.is_dir());
+        if let Some(dir) = last_send_dir {
+            file_dialog.set_initial_folder(Some(&gio::File::for_path(dir)));
+        }
+
+        file_dialog
+    }
+
+    /// Remembers the folder of a selection for sending
+    fn remember_send_dir(&self, path: &Path) {
+        self.config().last_send_dir = path.parent().map(Path::to_path_buf);
+    }
+
+    fn settings_file_dialog(title: &str) -> gtk::FileDialog {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some(&gettext("JSON Files")));
+        filter.add_mime_type("application/json");
+
+        gtk::FileDialog::builder()
+            .title(title)
+            .initial_name("warp-settings.json")
+            .default_filter(&filter)
+            .modal(true)
+            .build()
+    }
+
+    /// Saves the preferences to a file, to set up Warp the same way somewhere else
+    pub async fn export_settings(&self) {
+        let include_window_size = gtk::CheckButton::with_label(&gettext("Include Window Size"));
+        let dialog = adw::AlertDialog::builder()
+            .heading(gettext("Export Settings?"))
+            .body(gettext(
+                "The settings can be imported by Warp on another computer. The window size is only exported if requested, as it depends on the screen.",
+            ))
+            .extra_child(&include_window_size)
+            .close_response("cancel")
+            .default_response("export")
+            .build();
+        dialog.add_response("cancel", &gettext("_Cancel"));
+        dialog.add_response("export", &gettext("_Export"));
+        dialog.set_response_appearance("export", adw::ResponseAppearance::Suggested);
+
+        if dialog.choose_future(self).await != "export" {
+            return;
+        }
+
+        let file_dialog = Self::settings_file_dialog(&gettext("Export Settings"));
+        let path = match file_dialog.save_future(Some(self)).await {
+            Ok(file) => file.path(),
+            Err(err) => {
+                log::debug!("Settings file chooser error: {:?}", err);
+                return;
+            }
+        };
+
+        let Some(path) = path else {
+            log::error!("Selected settings file has no path");
+            return;
+        };
+
+        let result = self
+            .config()
+            .export_settings(include_window_size.is_active())
+            .and_then(|json| std::fs::write(&path, json).map_err(AppError::from));
+
+        match result {
+            Ok(()) => {
+                let toast = adw::Toast::new(&gettext("Exported Settings"));
+                toast.set_timeout(3);
+                toast.set_priority(adw::ToastPriority::Normal);
+                self.toast_overlay().add_toast(toast);
+            }
+            Err(err) => err.handle(),
+        }
+    }
+
+    /// Applies preferences from a file that was exported before
+    pub async fn import_settings(&self) {
+        let file_dialog = Self::settings_file_dialog(&gettext("Import Settings"));
+        let path = match file_dialog.open_future(Some(self)).await {
+            Ok(file) => file.path(),
+            Err(err) => {
+                log::debug!("Settings file chooser error: {:?}", err);
+                return;
+            }
+        };
+
+        let Some(path) = path else {
+            log::error!("Selected settings file has no path");
+            return;
+        };
+
+        let result = std::fs::read_to_string(&path)
+            .map_err(AppError::from)
+            .and_then(|json| self.config().import_settings(&json));
+        let (config, changed) = match result {
+            Ok(result) => result,
+            Err(err) => {
+                err.handle();
+                return;
+            }
+        };
+
+        if changed.is_empty() {
+            let toast = adw::Toast::new(&gettext("All Settings Already Match"));
+            toast.set_timeout(3);
+            toast.set_priority(adw::ToastPriority::Normal);
+            self.toast_overlay().add_toast(toast);
+            return;
+        }
+
+        self.imp().config.borrow_mut().config = config;
+        self.save_config();
+        self.apply_config();
+
+        let summary = changed
+            .iter()
+            .map(|field| format!("• {}", Self::setting_title(field)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let dialog = adw::AlertDialog::builder()
+            .heading(gettext("Imported Settings"))
+            .body(gettextf(
+                "The following settings were changed:\n{}",
+                &[&summary],
+            ))
+            .build();
+        dialog.add_response("close", &gettext("_Close"));
+        dialog.present(Some(self));
+    }
+
+    /// User facing name of a config field, like in preferences
+    fn setting_title(field: &str) -> String {
+        match field {
+            "window" => gettext("Window Size"),
+            "rendezvous_server_url" => gettext("Rendezvous Server URL"),
+            "transit_server_url" => gettext("Transit Server URLs"),
+            "code_length" => gettext("Code Word Count"),
+            "spell_out_code" => gettext("Spell Out Codes"),
+            "highlight_code_number" => gettext("Highlight Channel Number"),
+            "clear_clipboard_secs" => gettext("Clear Copied Codes After (Seconds)"),
+            "qr_error_correction" => gettext("QR Code Error Correction"),
+            "qr_code_size" => gettext("QR Code Size"),
+            "allow_send_folders" => gettext("Allow Sending Folders"),
+            "symlink_policy" => gettext("Keep Symbolic Links"),
+            "include_hidden_files" => gettext("Include Hidden Files"),
+            "send_single_file_directly" => gettext("Send Single Files Directly"),
+            "confirm_relayed_transfers" => gettext("Confirm Relayed Transfers"),
+            "direct_connections_only" => gettext("Direct Connections Only"),
+            "debug_transit" => gettext("Log Connection Details"),
+            "save_as_by_default" => gettext("Choose Location by Default"),
+            "open_camera_on_receive" => gettext("Open Camera Automatically"),
+            "speed_in_bits" => gettext("Show Speed in Bits"),
+            "progress_text" => gettext("Progress Text"),
+            "reduce_progress_updates" => gettext("Reduce Progress Updates"),
+            "color_scheme" => gettext("Style"),
+            "high_contrast" => gettext("High Contrast"),
+            "confirm_cancel" => gettext("Confirm Before Canceling Transfers"),
+            "return_home_after_completion" => gettext("Return to Home After Completion"),
+            "idle_quit_secs" => gettext("Quit After Inactivity (Seconds)"),
+            "notifications" => gettext("Notifications"),
+            "notification_sounds" => gettext("Sounds"),
+            "max_receive_size" => gettext("Maximum File Size (MB)"),
+            "overwrite_policy" => gettext("Existing Files"),
+            _ => field.to_owned(),
+        }
+    }
+
     pub fn show_about_dialog(&self) {
         let dialog =
             adw::AboutDialog::from_appdata("app/drey/Warp/metainfo.xml", Some(globals::VERSION));
@@ -347,10 +751,11 @@ impl WarpApplicationWindow {
 
             if page == *self.imp().page_root {
                 imp.code_entry.set_text("");
-
-                if let Some(app) = self.app() {
-                    app.uninhibit_transfer();
-                }
+                imp.send_note_entry.set_text("");
+                imp.send_receivers_row.set_value(1.0);
+                imp.send_url_entry.set_text("");
+                // Hides the recently received file if it was moved or deleted in the meantime
+                self.recently_received();
 
                 #[cfg(feature = "qr_code_scanning")]
                 self.imp().page_camera.stop();
@@ -369,23 +774,28 @@ impl WarpApplicationWindow {
 
     #[template_callback]
     async fn select_file(&self) {
-        self.imp()
-            .file_dialog
-            .set_title(&gettext("Select File to Send"));
-        self.select_file_result(self.imp().file_dialog.open_future(Some(self)).await);
+        let file_dialog = self.prepare_send_file_dialog(&gettext("Select File to Send"));
+        self.select_file_result(file_dialog.open_future(Some(self)).await);
     }
 
     #[template_callback]
     async fn select_folder(&self) {
-        self.imp()
-            .file_dialog
-            .set_title(&gettext("Select Folder to Send"));
-        self.select_file_result(
-            self.imp()
-                .file_dialog
-                .select_folder_future(Some(self))
-                .await,
-        );
+        if !self.config().allow_send_folders_or_default() {
+            return;
+        }
+
+        let file_dialog = self.prepare_send_file_dialog(&gettext("Select Folder to Send"));
+        self.select_file_result(file_dialog.select_folder_future(Some(self)).await);
+    }
+
+    /// The note entered on the send page, sanitized for display
+    pub fn send_note(&self) -> Option<String> {
+        sanitize_note(&self.imp().send_note_entry.text())
+    }
+
+    /// How many people the next file is sent to, one after another
+    pub fn send_receivers(&self) -> u32 {
+        self.imp().send_receivers_row.value() as u32
     }
 
     pub fn send_file(&self, file: &gio::File) {
@@ -399,9 +809,54 @@ impl WarpApplicationWindow {
         }
     }
 
+    pub fn send_files(&self, files: &[gio::File]) {
+        let paths = files
+            .iter()
+            .filter_map(|file| {
+                let path = file.path();
+                if path.is_none() {
+                    log::error!("Can't send file '{}': path is None", file.uri());
+                }
+                path
+            })
+            .collect::<Vec<_>>();
+
+        if paths.is_empty() {
+            return;
+        }
+
+        log::debug!("Selected {} files", paths.len());
+        self.imp()
+            .action_view
+            .send_files(paths, self.config().app_cfg());
+    }
+
+    #[template_callback]
+    fn send_url_entry_activated(&self) {
+        if self.action_view_showing() {
+            return;
+        }
+
+        let text = self.imp().send_url_entry.text();
+        if let Some(url) = validate_download_url(&text) {
+            self.imp()
+                .action_view
+                .send_url(url, self.config().app_cfg());
+        } else {
+            UiError::new(&gettextf(
+                "“{}” is not a valid web address. Only http and https links can be sent.",
+                &[&text],
+            ))
+            .handle();
+        }
+    }
+
     fn select_file_result(&self, result: Result<gio::File, glib::Error>) {
         match result {
             Ok(file) => {
+                if let Some(path) = file.path() {
+                    self.remember_send_dir(&path);
+                }
                 self.send_file(&file);
             }
             Err(err) => {
@@ -416,11 +871,66 @@ impl WarpApplicationWindow {
         self.imp().navigation_view.push(&self.imp().page_camera);
     }
 
+    /// Opens the camera when switching to the receive tab, if this was turned on in preferences
+    #[template_callback]
+    fn stack_visible_child_name_notify(&self) {
+        #[cfg(feature = "qr_code_scanning")]
+        {
+            let imp = self.imp();
+            let on_root =
+                imp.navigation_view.visible_page().as_ref() == Some(imp.page_root.upcast_ref());
+
+            if on_root
+                && imp.stack.visible_child_name().as_deref() == Some("receive")
+                && self.config().open_camera_on_receive_or_default()
+            {
+                log::debug!("Opening the camera for receiving");
+                imp.page_camera.set_opened_automatically(true);
+                imp.navigation_view.push(&imp.page_camera);
+            }
+        }
+    }
+
     #[template_callback]
     fn code_entry_changed(&self) {
-        self.imp()
-            .receive_button
-            .set_sensitive(!self.imp().code_entry.text().is_empty());
+        let imp = self.imp();
+        let text = imp.code_entry.text();
+        let is_plausible = is_plausible_transmit_code(&text);
+
+        if text.is_empty() {
+            imp.code_entry.remove_css_class("success");
+            imp.code_entry.remove_css_class("error");
+        } else if is_plausible {
+            imp.code_entry.add_css_class("success");
+            imp.code_entry.remove_css_class("error");
+        } else {
+            imp.code_entry.remove_css_class("success");
+            imp.code_entry.add_css_class("error");
+        }
+
+        imp.code_format_hint_label
+            .set_visible(!text.is_empty() && !is_plausible);
+        imp.receive_button.set_sensitive(is_plausible);
+    }
+
+    #[template_callback]
+    fn receive_new_code_button_clicked(&self) {
+        if self.action_view_showing() {
+            return;
+        }
+
+        self.action_view()
+            .receive_file_new_code(self.config().app_cfg());
+    }
+
+    #[template_callback]
+    fn code_entry_activated(&self) {
+        // Enter behaves like the receive button, so it must not bypass the plausibility check
+        if self.imp().receive_button.is_sensitive() {
+            self.receive_button_clicked();
+        } else {
+            self.imp().code_entry.error_bell();
+        }
     }
 
     #[template_callback]
@@ -430,8 +940,13 @@ impl WarpApplicationWindow {
         }
 
         let text = self.imp().code_entry.text();
-        let uri = extract_transmit_uri(&text).and_then(|s| WormholeTransferURI::from_str(&s).ok());
-        let code = if globals::TRANSMIT_CODE_MATCH_REGEX.is_match(&text) {
+        self.receive_from_text(&text);
+    }
+
+    /// Starts receiving with a code or a `wormhole-transfer:` link, as entered by the user
+    pub fn receive_from_text(&self, text: &str) {
+        let uri = extract_transmit_uri(text).and_then(|s| WormholeTransferURI::from_str(&s).ok());
+        let code = if globals::TRANSMIT_CODE_MATCH_REGEX.is_match(text) {
             match text.parse() {
                 Ok(code) => code,
                 Err(err) => {
@@ -440,9 +955,9 @@ impl WarpApplicationWindow {
                 }
             }
         } else if let Some(uri) = uri {
-            self.open_code_from_uri(uri);
+            self.open_uri_auto(uri);
             return;
-        } else if let Some(code) = extract_transmit_code(&text) {
+        } else if let Some(code) = extract_transmit_code(text) {
             code
         } else {
             UiError::new(&gettextf(
@@ -470,9 +985,56 @@ impl WarpApplicationWindow {
         self.action_view_showing() && self.action_view().transfer_in_progress()
     }
 
+    /// The direction of the transfer that keeps a new one from starting in this window, if any
+    pub fn running_transfer(&self) -> Option<TransferDirection> {
+        self.transfer_in_progress()
+            .then(|| self.action_view().transfer_direction())
+    }
+
+    /// Checks that a new transfer can be started. If one is already running, this asks whether it
+    /// should be canceled for the new one.
+    pub async fn confirm_new_transfer(&self) -> bool {
+        let Some(direction) = self.running_transfer() else {
+            return true;
+        };
+
+        let body = match direction {
+            TransferDirection::Send => gettext(
+                "A file is being sent in this window. Cancel it to start a new transfer, or use a new window to run both at the same time.",
+            ),
+            TransferDirection::Receive => gettext(
+                "A file is being received in this window. Cancel it to start a new transfer, or use a new window to run both at the same time.",
+            ),
+        };
+
+        let dialog = adw::AlertDialog::builder()
+            .heading(gettext("Transfer in Progress"))
+            .body(body)
+            .close_response("keep")
+            .default_response("keep")
+            .build();
+
+        dialog.add_response("keep", &gettext("_Keep Transfer"));
+        dialog.add_response("cancel", &gettext("_Cancel Transfer"));
+        dialog.set_response_appearance("cancel", adw::ResponseAppearance::Destructive);
+
+        if dialog.choose_future(self).await != "cancel" {
+            return false;
+        }
+
+        // The transfer may have ended while the dialog was open
+        if self.transfer_in_progress() {
+            self.action_view().cancel().await;
+        }
+        true
+    }
+
     pub fn show_action_view(&self) {
         let imp = self.imp();
 
+        // A new transfer is starting
+        self.set_recently_received(None);
+
         let visible_page = imp.navigation_view.visible_page();
 
         if visible_page.as_ref() == Some(imp.page_root.upcast_ref()) {
@@ -490,6 +1052,26 @@ impl WarpApplicationWindow {
         }
     }
 
+    /// Closes the window once a running transfer is canceled, asking first like the close button.
+    /// Returns `false` if the user wants to keep the transfer running.
+    pub async fn close_gracefully(&self) -> bool {
+        let action_view = self.action_view();
+        if self.action_view_showing() && action_view.transfer_in_progress() {
+            if !action_view.cancel_request().await {
+                return false;
+            }
+
+            action_view.cancel().await;
+        } else {
+            action_view.wait_for_cancellation().await;
+        }
+
+        // The transfer is over, so closing doesn't ask again
+        self.imp().close_in_progress.set(true);
+        self.close();
+        true
+    }
+
     pub fn navigate_home(&self) {
         log::debug!("Navigating back to main screen");
         self.imp()
@@ -504,6 +1086,15 @@ impl WarpApplicationWindow {
             .insert(code.to_string());
     }
 
+    /// The transfer of `code` is over, so it expires soon. Codes of other running transfers in
+    /// this window are kept.
+    pub fn end_generated_code(&self, code: &wormhole::Code) {
+        self.imp()
+            .generated_transmit_codes
+            .borrow_mut()
+            .end_transfer(&code.to_string(), Instant::now());
+    }
+
     #[template_callback]
     pub fn add_code_from_clipboard(&self) {
         let Some(stack_name) = self.imp().stack.visible_child_name() else {
@@ -531,8 +1122,8 @@ impl WarpApplicationWindow {
                         if imp.code_entry.text() != extracted_text
                             && !imp
                                 .generated_transmit_codes
-                                .borrow()
-                                .contains(code.as_ref())
+                                .borrow_mut()
+                                .contains(code.as_ref(), Instant::now())
                         {
                             let imp = obj.imp();
                             imp.code_entry.set_text(&extracted_text);
@@ -563,16 +1154,226 @@ impl WarpApplicationWindow {
         dialog
     }
 
-    pub fn open_code_from_uri(&self, uri: WormholeTransferURI) {
-        let app_cfg = uri.to_app_cfg();
-        if uri.direction == TransferDirection::Receive {
-            self.imp().stack.set_visible_child_name("receive");
-            self.action_view().receive_file(uri.code, app_cfg);
+    pub fn set_recently_received(&self, path: Option<PathBuf>) {
+        let imp = self.imp();
+
+        let file_name = path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        imp.recently_received_row.set_subtitle(&file_name);
+        imp.recently_received_clamp.set_visible(path.is_some());
+        imp.recently_received.replace(path);
+    }
+
+    /// The last file received in this session, if it still exists
+    fn recently_received(&self) -> Option<PathBuf> {
+        let path = self.imp().recently_received.borrow().clone()?;
+        if path.exists() {
+            Some(path)
         } else {
-            let err = UiError::new(&gettext(
-                "Sending files with a preconfigured code is not yet supported",
-            ));
-            AppError::from(err).show_error_dialog(self);
+            log::debug!(
+                "Recently received file '{}' is gone, forgetting it",
+                path.display()
+            );
+            self.set_recently_received(None);
+            None
+        }
+    }
+
+    #[template_callback]
+    async fn recently_received_open_clicked(&self) {
+        if let Some(path) = self.recently_received() {
+            self.open_file(&path).await;
+        }
+    }
+
+    #[template_callback]
+    async fn recently_received_open_dir_clicked(&self) {
+        if let Some(path) = self.recently_received() {
+            self.open_dir(&path).await;
+        }
+    }
+
+    /// The uri of a local file, also for paths that aren't valid in the filename encoding
+    fn file_uri(path: &Path) -> String {
+        match glib::filename_to_uri(path, None) {
+            Ok(uri) => uri.to_string(),
+            Err(err) => {
+                // GIO escapes any path, like one that isn't valid in the filename encoding
+                log::warn!(
+                    "Can't convert '{}' to a uri: {}. Using the GFile uri instead",
+                    path.display(),
+                    err
+                );
+                gio::File::for_path(path).uri().to_string()
+            }
+        }
+    }
+
+    /// Opens a file with the default application, offering to show it in its folder if that fails
+    pub async fn open_file(&self, path: &Path) {
+        let uri = Self::file_uri(path);
+
+        log::debug!("Opening file with uri '{}'", uri);
+        let none: Option<&gio::AppLaunchContext> = None;
+        if let Err(err) = gio::AppInfo::launch_default_for_uri(&uri, none) {
+            log::error!("Error opening file: {}", err);
+
+            let answer = self
+                .no_registered_application_error_dialog(err.message())
+                .choose_future(self)
+                .await;
+
+            if answer == "show-in-folder" {
+                self.open_dir(path).await;
+            }
+        }
+    }
+
+    /// Lets the user choose one of the apps that can open the file, instead of the default one
+    pub async fn open_file_with(&self, path: &Path) {
+        let file = gio::File::for_path(path);
+        let content_type = match file
+            .query_info_future(
+                gio::FILE_ATTRIBUTE_STANDARD_CONTENT_TYPE,
+                gio::FileQueryInfoFlags::NONE,
+                glib::Priority::default(),
+            )
+            .await
+        {
+            Ok(info) => info.content_type(),
+            Err(err) => {
+                log::warn!("Error querying the content type: {}", err);
+                None
+            }
+        };
+
+        let apps = content_type
+            .as_deref()
+            .map(gio::AppInfo::all_for_type)
+            .unwrap_or_default();
+        if apps.is_empty() {
+            log::info!("No apps for content type {:?}", content_type);
+            let answer = self
+                .no_registered_application_error_dialog(&gettext(
+                    "No installed app can open this type of file",
+                ))
+                .choose_future(self)
+                .await;
+
+            if answer == "show-in-folder" {
+                self.open_dir(path).await;
+            }
+            return;
+        }
+
+        let list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+        for app in &apps {
+            let row = adw::ActionRow::builder()
+                .title(app.display_name())
+                .use_markup(false)
+                .activatable(true)
+                .build();
+            if let Some(icon) = app.icon() {
+                row.add_prefix(&gtk::Image::from_gicon(&icon));
+            }
+            list.append(&row);
+        }
+
+        // Translators: Dialog title, to choose the app that opens a received file
+        let dialog = adw::AlertDialog::new(Some(&gettext("Open With")), None);
+        dialog.set_extra_child(Some(&list));
+        dialog.add_response("cancel", &gettext("_Cancel"));
+
+        let chosen = Rc::new(Cell::new(None));
+        list.connect_row_activated(clone!(
+            #[weak]
+            dialog,
+            #[strong]
+            chosen,
+            move |_, row| {
+                chosen.set(usize::try_from(row.index()).ok());
+                dialog.close();
+            }
+        ));
+        dialog.choose_future(self).await;
+
+        let Some(app) = chosen.get().and_then(|index| apps.get(index)) else {
+            return;
+        };
+
+        let uri = Self::file_uri(path);
+        log::debug!("Opening '{}' with {}", uri, app.display_name());
+        let context = self.display().app_launch_context();
+        if let Err(err) = app.launch_uris(&[&uri], Some(&context)) {
+            log::error!("Error opening file: {}", err);
+            let answer = self
+                .no_registered_application_error_dialog(err.message())
+                .choose_future(self)
+                .await;
+
+            if answer == "show-in-folder" {
+                self.open_dir(path).await;
+            }
+        }
+    }
+
+    pub async fn open_dir(&self, path: &Path) {
+        if let Err(err) = show_dir(path).await {
+            log::error!("Error showing directory: {}", err);
+            err.handle();
+        }
+    }
+
+    /// Starts the transfer a link was created for. A link from a sender starts receiving, a link
+    /// from a receiver asks for the file to send.
+    pub fn open_uri_auto(&self, uri: WormholeTransferURI) {
+        self.imp()
+            .stack
+            .set_visible_child_name(stack_page_for_uri(&uri));
+
+        let app_cfg = uri.to_app_cfg();
+        match uri.direction {
+            TransferDirection::Receive => {
+                self.action_view().receive_file(uri.code, app_cfg);
+            }
+            TransferDirection::Send => {
+                glib::spawn_future_local(clone!(
+                    #[weak(rename_to = obj)]
+                    self,
+                    async move {
+                        obj.select_file_for_code(uri.code, app_cfg).await;
+                    }
+                ));
+            }
+        }
+    }
+
+    /// Asks for a file to send with a code the receiver created
+    async fn select_file_for_code(
+        &self,
+        code: wormhole::Code,
+        app_cfg: wormhole::AppConfig<wormhole::transfer::AppVersion>,
+    ) {
+        let file_dialog = self.prepare_send_file_dialog(&gettext("Select File to Send"));
+
+        match file_dialog.open_future(Some(self)).await {
+            Ok(file) => {
+                if let Some(path) = file.path() {
+                    self.remember_send_dir(&path);
+                    self.action_view().send_file_with_code(path, code, app_cfg);
+                } else {
+                    log::error!("Can't send file: path is None");
+                }
+            }
+            Err(err) => {
+                log::debug!("File chooser error: {:?}", err);
+            }
         }
     }
 
@@ -580,3 +1381,65 @@ impl WarpApplicationWindow {
         self.application().and_downcast()
     }
 }
+
+/// The page of the main stack that fits the transfer a link was created for
+fn stack_page_for_uri(uri: &WormholeTransferURI) -> &'static str {
+    match uri.direction {
+        TransferDirection::Receive => "receive",
+        TransferDirection::Send => "send",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stack_page_for_uri() {
+        // Created by a sender, so the one opening it receives
+        let uri: WormholeTransferURI = "wormhole-transfer:4-hurricane-equipment".parse().unwrap();
+        assert_eq!(uri.direction, TransferDirection::Receive);
+        assert_eq!(stack_page_for_uri(&uri), "receive");
+
+        // Created by a receiver, so the one opening it sends
+        let uri: WormholeTransferURI = "wormhole-transfer:4-hurricane-equipment?role=leader"
+            .parse()
+            .unwrap();
+        assert_eq!(uri.direction, TransferDirection::Send);
+        assert_eq!(stack_page_for_uri(&uri), "send");
+    }
+
+    #[test]
+    fn test_generated_codes_expire_after_transfer() {
+        let mut codes = GeneratedCodes::default();
+        let start = Instant::now();
+        codes.insert("4-hurricane-equipment".to_owned());
+
+        // Still running, no matter how long it takes
+        assert!(codes.contains("4-hurricane-equipment", start + GENERATED_CODE_EXPIRY * 10));
+        assert!(!codes.contains("5-other-code", start));
+
+        codes.end_transfer("4-hurricane-equipment", start);
+        assert!(codes.contains("4-hurricane-equipment", start + Duration::from_secs(1)));
+        assert!(!codes.contains("4-hurricane-equipment", start + GENERATED_CODE_EXPIRY));
+        assert!(codes.codes.is_empty());
+
+        // The same code used again later is ours again until that transfer ends
+        codes.insert("4-hurricane-equipment".to_owned());
+        assert!(codes.contains("4-hurricane-equipment", start + GENERATED_CODE_EXPIRY * 2));
+    }
+
+    #[test]
+    fn test_generated_codes_expire_separately() {
+        let mut codes = GeneratedCodes::default();
+        let start = Instant::now();
+        codes.insert("4-hurricane-equipment".to_owned());
+        codes.insert("7-guitarist-revenge".to_owned());
+
+        // Ending one transfer keeps the code of the other one running
+        codes.end_transfer("4-hurricane-equipment", start);
+        codes.end_transfer("5-unknown-code", start);
+        assert!(!codes.contains("4-hurricane-equipment", start + GENERATED_CODE_EXPIRY));
+        assert!(codes.contains("7-guitarist-revenge", start + GENERATED_CODE_EXPIRY * 10));
+    }
+}
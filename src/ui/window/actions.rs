@@ -6,7 +6,8 @@ use gtk::subclass::widget::WidgetClassExt;
 use strum::IntoEnumIterator as _;
 
 use crate::{
-    ui::preferences::WarpPreferencesDialog,
+    globals,
+    ui::{preferences::WarpPreferencesDialog, self_test},
     util::{future::main_async_local, show_dir},
 };
 
@@ -14,12 +15,21 @@ use crate::{
 #[strum(prefix = "win.", serialize_all = "kebab-case")]
 pub enum Action {
     ShowHelpOverlay,
+    NewWindow,
     Preferences,
+    ExportSettings,
+    ImportSettings,
     About,
     OpenFile,
     OpenFolder,
     ReceiveFile,
     ShowFile,
+    /// Only enabled while the code of a send is shown
+    CopyCode,
+    CopyLink,
+    CopyCodeAndLink,
+    /// Only available in devel builds
+    SelfTest,
 }
 
 impl Action {
@@ -35,6 +45,18 @@ impl Action {
                         action.as_ref(),
                     );
                 }
+                Action::NewWindow => {
+                    class.install_action(action.as_ref(), None, move |win, _, _| {
+                        if let Some(app) = win.app() {
+                            app.new_window();
+                        }
+                    });
+                    class.add_binding_action(
+                        gdk::Key::N,
+                        gdk::ModifierType::CONTROL_MASK,
+                        action.as_ref(),
+                    );
+                }
                 Action::Preferences => {
                     class.install_action(action.as_ref(), None, move |win, _, _| {
                         WarpPreferencesDialog::new().present(Some(win));
@@ -45,22 +67,26 @@ impl Action {
                         action.as_ref(),
                     );
                 }
+                Action::ExportSettings => {
+                    class.install_action_async(action.as_ref(), None, |win, _, _| async move {
+                        win.export_settings().await;
+                    });
+                }
+                Action::ImportSettings => {
+                    class.install_action_async(action.as_ref(), None, |win, _, _| async move {
+                        win.import_settings().await;
+                    });
+                }
                 Action::About => {
                     class.install_action(action.as_ref(), None, move |win, _, _| {
                         win.show_about_dialog();
                     });
                 }
                 Action::OpenFile => {
-                    class.install_action(action.as_ref(), None, move |win, _, _| {
-                        if !win.transfer_in_progress() {
+                    class.install_action_async(action.as_ref(), None, |win, _, _| async move {
+                        if win.confirm_new_transfer().await {
                             win.imp().stack.set_visible_child_name("send");
-                            glib::MainContext::default().spawn_local(glib::clone!(
-                                #[strong]
-                                win,
-                                async move {
-                                    win.select_file().await;
-                                }
-                            ));
+                            win.select_file().await;
                         }
                     });
                     class.add_binding_action(
@@ -70,16 +96,10 @@ impl Action {
                     );
                 }
                 Action::OpenFolder => {
-                    class.install_action(action.as_ref(), None, move |win, _, _| {
-                        if !win.transfer_in_progress() {
+                    class.install_action_async(action.as_ref(), None, |win, _, _| async move {
+                        if win.confirm_new_transfer().await {
                             win.imp().stack.set_visible_child_name("send");
-                            glib::MainContext::default().spawn_local(glib::clone!(
-                                #[strong]
-                                win,
-                                async move {
-                                    win.select_folder().await;
-                                }
-                            ));
+                            win.select_folder().await;
                         }
                     });
                     class.add_binding_action(
@@ -89,8 +109,8 @@ impl Action {
                     );
                 }
                 Action::ReceiveFile => {
-                    class.install_action(action.as_ref(), None, move |win, _, _| {
-                        if !win.transfer_in_progress() {
+                    class.install_action_async(action.as_ref(), None, |win, _, _| async move {
+                        if win.confirm_new_transfer().await {
                             win.imp().stack.set_visible_child_name("receive");
                             win.imp().code_entry.grab_focus();
                         }
@@ -118,6 +138,56 @@ impl Action {
                         },
                     );
                 }
+                Action::CopyCode => {
+                    class.install_action(action.as_ref(), None, move |win, _, _| {
+                        let action_view = win.action_view();
+                        if win.action_view_showing() && action_view.can_copy_code() {
+                            action_view.copy_code();
+                        }
+                    });
+                    class.add_binding_action(
+                        gdk::Key::C,
+                        gdk::ModifierType::CONTROL_MASK,
+                        action.as_ref(),
+                    );
+                }
+                Action::CopyLink => {
+                    class.install_action(action.as_ref(), None, move |win, _, _| {
+                        let action_view = win.action_view();
+                        if win.action_view_showing() && action_view.can_copy_code() {
+                            action_view.copy_link();
+                        }
+                    });
+                    class.add_binding_action(
+                        gdk::Key::C,
+                        gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
+                        action.as_ref(),
+                    );
+                }
+                Action::CopyCodeAndLink => {
+                    class.install_action(action.as_ref(), None, move |win, _, _| {
+                        let action_view = win.action_view();
+                        if win.action_view_showing() && action_view.can_copy_code() {
+                            action_view.copy_code_and_link();
+                        }
+                    });
+                    class.add_binding_action(
+                        gdk::Key::C,
+                        gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::ALT_MASK,
+                        action.as_ref(),
+                    );
+                }
+                Action::SelfTest if globals::DEBUG_BUILD => {
+                    class.install_action_async(action.as_ref(), None, |win, _, _| async move {
+                        self_test::run_and_report(&win).await;
+                    });
+                    class.add_binding_action(
+                        gdk::Key::T,
+                        gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
+                        action.as_ref(),
+                    );
+                }
+                Action::SelfTest => {}
             }
         }
     }
@@ -130,6 +200,11 @@ mod test {
     #[test]
     fn strum_serialisations() {
         assert_eq!(Action::ShowHelpOverlay.as_ref(), "win.show-help-overlay");
+        assert_eq!(Action::NewWindow.as_ref(), "win.new-window");
         assert_eq!(Action::ShowFile.as_ref(), "win.show-file");
+        assert_eq!(Action::CopyCode.as_ref(), "win.copy-code");
+        assert_eq!(Action::CopyLink.as_ref(), "win.copy-link");
+        assert_eq!(Action::CopyCodeAndLink.as_ref(), "win.copy-code-and-link");
+        assert_eq!(Action::SelfTest.as_ref(), "win.self-test");
     }
 }
@@ -1,15 +1,17 @@
+use crate::config::{QrCodeSize, QrErrorCorrection};
 use crate::error::{AppError, UiError};
 use crate::gettext::gettextf;
 use crate::gettext::*;
 use crate::globals;
 use crate::globals::{TRANSMIT_CODE_FIND_REGEX, TRANSMIT_URI_FIND_REGEX};
 use gio::prelude::*;
-use std::fmt::Display;
+use std::fmt::{Display, Write};
 use std::str::FromStr;
 use wormhole::Code;
 
 pub mod error;
 pub mod future;
+pub mod relay_gate;
 pub mod zip;
 
 pub async fn show_dir(path: &std::path::Path) -> Result<(), AppError> {
@@ -50,12 +52,12 @@ async fn show_dir_dbus(path: &std::path::Path) -> Result<(), AppError> {
         // directories is the file browser itself.
         // If we'd use "OpenDirectory" instead, it would open the parent and pre-select the dir, not show the dir itself
         gtk::FileLauncher::new(Some(&file))
-            .launch_future(Some(&window))
+            .launch_future(window.as_ref())
             .await
     } else {
         // If this is a file we want to be displaying, open the containing folder in file browser, and pre-select the file
         gtk::FileLauncher::new(Some(&file))
-            .open_containing_folder_future(Some(&window))
+            .open_containing_folder_future(window.as_ref())
             .await
     }
     .map_err(|_| UiError::new(&err_msg))?;
@@ -69,10 +71,186 @@ pub fn extract_transmit_uri(str: &str) -> Option<String> {
         .map(|m| m.as_str().to_owned())
 }
 
+/// Finds a code in pasted text, like “Code: 7-guitarist-revenge.” Spaces around the dashes and
+/// punctuation after the code are left out.
 pub fn extract_transmit_code(str: &str) -> Option<Code> {
+    let normalized = globals::TRANSMIT_CODE_DASH_SPACING_REGEX.replace_all(str, "-");
     TRANSMIT_CODE_FIND_REGEX
-        .find(str)
-        .and_then(|m| m.as_str().parse().ok())
+        .find(&normalized)
+        .map(|m| {
+            m.as_str()
+                .trim_end_matches(|char: char| !char.is_alphanumeric())
+        })
+        .and_then(|code| code.parse().ok())
+}
+
+/// Words of the NATO spelling alphabet, in the order of the letters
+const SPELLING_ALPHABET: [&str; 26] = [
+    "Alfa", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India", "Juliett",
+    "Kilo", "Lima", "Mike", "November", "Oscar", "Papa", "Quebec", "Romeo", "Sierra", "Tango",
+    "Uniform", "Victor", "Whiskey", "X-ray", "Yankee", "Zulu",
+];
+
+/// Words for digits, as they are spoken with the spelling alphabet
+const SPELLING_DIGITS: [&str; 10] = [
+    "Zero", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine",
+];
+
+/// Spells a code word with the NATO spelling alphabet, to read it aloud without mistakes.
+/// Characters without a spelling word are kept as they are.
+pub fn spell_word(word: &str) -> String {
+    word.chars()
+        .map(|char| {
+            let lower = char.to_ascii_lowercase();
+            if lower.is_ascii_lowercase() {
+                SPELLING_ALPHABET[usize::from(lower as u8 - b'a')].to_owned()
+            } else if let Some(digit) = char.to_digit(10) {
+                SPELLING_DIGITS[digit as usize].to_owned()
+            } else {
+                char.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reads a code or link for receiving, like it is piped in by another tool. Returns the first
+/// line that isn't empty.
+pub fn read_transmit_code(reader: impl std::io::BufRead) -> std::io::Result<Option<String>> {
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() {
+            return Ok(Some(line.to_owned()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Whether the text looks like something the receive flow will accept: either a code in the
+/// `number-word-word` format or a `wormhole-transfer:` link.
+pub fn is_plausible_transmit_code(str: &str) -> bool {
+    globals::TRANSMIT_CODE_MATCH_REGEX.is_match(str)
+        || extract_transmit_code(str).is_some()
+        || extract_transmit_uri(str).is_some_and(|uri| WormholeTransferURI::from_str(&uri).is_ok())
+}
+
+/// Parses a rendezvous server URL, which has to be a websocket URL with a host
+pub fn validate_rendezvous_url(url: &str) -> Option<url::Url> {
+    url::Url::parse(url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "ws" | "wss") && url.has_host())
+}
+
+/// Parses a transit relay URL, which has to be a `tcp` or `tls` URL with a host and a port
+pub fn validate_transit_url(url: &str) -> Option<url::Url> {
+    url::Url::parse(url).ok().filter(|url| {
+        matches!(url.scheme(), "tcp" | "tls") && url.has_host() && url.port().is_some()
+    })
+}
+
+/// Parses a URL of a file to download and send, which has to be a http or https URL with a host
+pub fn validate_download_url(url: &str) -> Option<url::Url> {
+    url::Url::parse(url.trim())
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+}
+
+/// Converts AppStream release notes markup into plain text with bullet points
+pub fn release_notes_to_text(markup: &str) -> String {
+    let mut text = String::new();
+    let mut rest = markup;
+
+    while let Some(start) = rest.find('<') {
+        push_collapsed_whitespace(&mut text, &rest[..start]);
+
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+
+        match &rest[start + 1..start + end] {
+            "li" => text.push_str("• "),
+            "/p" | "/ul" | "/ol" => {
+                text.truncate(text.trim_end().len());
+                text.push_str("\n\n");
+            }
+            "/li" => {
+                text.truncate(text.trim_end().len());
+                text.push('\n');
+            }
+            _ => {}
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+    push_collapsed_whitespace(&mut text, rest);
+
+    text.trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn push_collapsed_whitespace(text: &mut String, fragment: &str) {
+    let words = fragment.split_whitespace().collect::<Vec<_>>();
+    if words.is_empty() {
+        return;
+    }
+
+    if fragment.starts_with(char::is_whitespace)
+        && !text.is_empty()
+        && !text.ends_with(char::is_whitespace)
+    {
+        text.push(' ');
+    }
+
+    text.push_str(&words.join(" "));
+
+    if fragment.ends_with(char::is_whitespace) {
+        text.push(' ');
+    }
+}
+
+/// The decimal separator of the current locale, as used by `glib::format_size`
+fn decimal_separator() -> char {
+    glib::format_size(1500)
+        .chars()
+        .find(|c| !c.is_ascii_digit())
+        .unwrap_or('.')
+}
+
+/// Formats a number with the decimal separator of the current locale, to match the sizes
+/// formatted by glib
+pub fn format_decimal(value: f64, decimals: usize) -> String {
+    let formatted = format!("{value:.decimals$}");
+    match decimal_separator() {
+        '.' => formatted,
+        separator => formatted.replace('.', &separator.to_string()),
+    }
+}
+
+/// Collapses whitespace and control characters into single spaces and limits the length of a
+/// user entered note. Returns `None` if nothing is left.
+pub fn sanitize_note(note: &str) -> Option<String> {
+    let note = note
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if note.is_empty() {
+        None
+    } else if note.chars().count() > globals::NOTE_MAX_CHARS {
+        let mut note: String = note.chars().take(globals::NOTE_MAX_CHARS - 1).collect();
+        note.truncate(note.trim_end().len());
+        note.push('…');
+        Some(note)
+    } else {
+        Some(note)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -102,7 +280,7 @@ impl From<wormhole::ParseCodeError> for WormholeURIParseError {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct WormholeTransferURI {
     pub code: wormhole::Code,
     pub version: usize,
@@ -110,6 +288,43 @@ pub struct WormholeTransferURI {
     pub direction: TransferDirection,
 }
 
+fn qr_ec_level(error_correction: QrErrorCorrection) -> qrcode::EcLevel {
+    match error_correction {
+        QrErrorCorrection::Low => qrcode::EcLevel::L,
+        QrErrorCorrection::Medium => qrcode::EcLevel::M,
+        QrErrorCorrection::Quartile => qrcode::EcLevel::Q,
+        QrErrorCorrection::High => qrcode::EcLevel::H,
+    }
+}
+
+/// Encodes `data` with the requested error correction. Higher levels hold less data, so for long
+/// data the level is lowered until it fits.
+fn qr_code_with_fallback(data: &str, error_correction: QrErrorCorrection) -> qrcode::QrCode {
+    let levels = [
+        QrErrorCorrection::High,
+        QrErrorCorrection::Quartile,
+        QrErrorCorrection::Medium,
+        QrErrorCorrection::Low,
+    ];
+
+    for level in levels
+        .into_iter()
+        .skip_while(|level| *level != error_correction)
+    {
+        match qrcode::QrCode::with_error_correction_level(data, qr_ec_level(level)) {
+            Ok(qr) => return qr,
+            Err(err) => log::warn!(
+                "QR code does not fit with error correction {:?}: {}",
+                level,
+                err
+            ),
+        }
+    }
+
+    // The lowest level fits about 3 KB, far more than a transfer link
+    qrcode::QrCode::new(data).unwrap()
+}
+
 impl WormholeTransferURI {
     pub fn new(
         code: wormhole::Code,
@@ -184,23 +399,98 @@ impl WormholeTransferURI {
     }
 
     pub fn to_paintable_qr(&self) -> gdk::Paintable {
+        self.to_paintable_qr_with_opts(QrErrorCorrection::default(), QrCodeSize::default())
+    }
+
+    pub fn to_paintable_qr_with_opts(
+        &self,
+        error_correction: QrErrorCorrection,
+        size: QrCodeSize,
+    ) -> gdk::Paintable {
         let uri = if cfg!(feature = "demo") {
             "https://apps.gnome.org/Warp".to_owned()
         } else {
             self.create_uri()
         };
 
-        let qr = qrcode::QrCode::new(uri).unwrap();
+        let qr = qr_code_with_fallback(&uri, error_correction);
+        let dimensions = size.render_dimensions();
 
         let svg = qr
             .render::<qrcode::render::svg::Color>()
-            .min_dimensions(800, 800)
+            .min_dimensions(dimensions, dimensions)
             .light_color(qrcode::render::svg::Color("#0000"))
             .build();
         gdk::Texture::from_bytes(&svg.as_bytes().into())
             .unwrap()
             .upcast()
     }
+
+    /// Creates a printable SVG poster containing the title, QR code, code words and instruction lines
+    pub fn to_qr_poster_svg(
+        &self,
+        title: &str,
+        instructions: &[String],
+        error_correction: QrErrorCorrection,
+    ) -> String {
+        const WIDTH: usize = 800;
+        const HEIGHT: usize = 1131;
+        const QR_SIZE: usize = 560;
+        const QR_TOP: usize = 190;
+        // The QR code specification requires 4 light modules around the code
+        const QUIET_ZONE: usize = 4;
+
+        let qr = qr_code_with_fallback(&self.create_uri(), error_correction);
+        let modules = qr.width();
+
+        let mut qr_path = String::new();
+        for (i, color) in qr.to_colors().iter().enumerate() {
+            if *color == qrcode::Color::Dark {
+                let _ignored = write!(
+                    qr_path,
+                    "M{} {}h1v1h-1z",
+                    i % modules + QUIET_ZONE,
+                    i / modules + QUIET_ZONE
+                );
+            }
+        }
+
+        let scale = QR_SIZE as f64 / (modules + 2 * QUIET_ZONE) as f64;
+        let center = WIDTH / 2;
+
+        let mut svg = format!(
+            r##"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}">
+<rect x="0" y="0" width="{WIDTH}" height="{HEIGHT}" fill="#ffffff"/>
+<text x="{center}" y="120" font-family="sans-serif" font-size="44" font-weight="bold" text-anchor="middle" fill="#000000">{title}</text>
+<g transform="translate({qr_left} {QR_TOP}) scale({scale})" shape-rendering="crispEdges"><path fill="#000000" d="{qr_path}"/></g>
+<text x="{center}" y="{code_top}" font-family="monospace" font-size="40" font-weight="bold" text-anchor="middle" fill="#000000">{code}</text>
+"##,
+            title = escape_xml(title),
+            qr_left = (WIDTH - QR_SIZE) / 2,
+            code_top = QR_TOP + QR_SIZE + 70,
+            code = escape_xml(self.code.as_ref()),
+        );
+
+        for (i, line) in instructions.iter().enumerate() {
+            let _ignored = writeln!(
+                svg,
+                r##"<text x="{center}" y="{}" font-family="sans-serif" font-size="26" text-anchor="middle" fill="#333333">{}</text>"##,
+                QR_TOP + QR_SIZE + 150 + i * 40,
+                escape_xml(line)
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn escape_xml(str: &str) -> String {
+    str.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
 impl TryFrom<url::Url> for WormholeTransferURI {
@@ -295,8 +585,14 @@ impl FromStr for WormholeTransferURI {
 
 #[cfg(test)]
 mod test {
+    use crate::config::QrErrorCorrection;
     use crate::globals;
-    use crate::util::{TransferDirection, WormholeTransferURI};
+    use crate::util::{
+        TransferDirection, WormholeTransferURI, decimal_separator, extract_transmit_code,
+        format_decimal, is_plausible_transmit_code, qr_code_with_fallback, read_transmit_code,
+        release_notes_to_text, sanitize_note, spell_word, validate_download_url,
+        validate_rendezvous_url, validate_transit_url,
+    };
 
     #[test]
     fn test_create_uri() {
@@ -340,4 +636,166 @@ mod test {
         assert_eq!(params3.version, parsed_params3.version);
         assert_eq!(params3.direction, parsed_params3.direction);
     }
+
+    #[test]
+    fn test_qr_poster_svg() {
+        let uri = WormholeTransferURI::new(
+            "4-hurricane-equipment".parse().unwrap(),
+            globals::WORMHOLE_DEFAULT_RENDEZVOUS_SERVER.clone(),
+            TransferDirection::Receive,
+        );
+
+        let svg = uri.to_qr_poster_svg(
+            "Title <&>",
+            &["Line \"1\"".to_owned()],
+            QrErrorCorrection::High,
+        );
+        assert!(svg.contains(">4-hurricane-equipment</text>"));
+        assert!(svg.contains(">Title &lt;&amp;&gt;</text>"));
+        assert!(svg.contains(">Line &quot;1&quot;</text>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_is_plausible_transmit_code() {
+        assert!(is_plausible_transmit_code("4-hurricane-equipment"));
+        assert!(is_plausible_transmit_code(
+            "wormhole-transfer:4-hurricane-equipment"
+        ));
+        assert!(!is_plausible_transmit_code(""));
+        assert!(!is_plausible_transmit_code("hurricane-equipment"));
+        assert!(!is_plausible_transmit_code("4 hurricane equipment"));
+        assert!(is_plausible_transmit_code(" Code: 4-hurricane-equipment "));
+    }
+
+    #[test]
+    fn test_extract_transmit_code() {
+        let extract = |text| extract_transmit_code(text).map(|code| code.as_ref().to_owned());
+        let code = Some("7-guitarist-revenge".to_owned());
+
+        assert_eq!(extract("7-guitarist-revenge"), code);
+        assert_eq!(extract("wormhole code 7-guitarist-revenge"), code);
+        assert_eq!(extract("  7-guitarist-revenge \t"), code);
+        assert_eq!(extract("Code: “7-guitarist-revenge”."), code);
+        assert_eq!(extract("Here you go:\n7-guitarist-revenge\nThanks!"), code);
+        assert_eq!(extract("7 - guitarist - revenge"), code);
+        assert_eq!(extract("v2-guitarist-revenge"), None);
+        assert_eq!(extract("guitarist revenge"), None);
+
+        for text in ["  7-guitarist-revenge ", "Code:\n7 -guitarist- revenge!"] {
+            let code = extract(text).unwrap();
+            assert!(globals::TRANSMIT_CODE_MATCH_REGEX.is_match(&code));
+        }
+    }
+
+    #[test]
+    fn test_format_decimal() {
+        // Defers to the locale instead of always using a point
+        let separator = decimal_separator();
+        assert!(glib::format_size(1500).starts_with(&format!("1{separator}5")));
+        assert_eq!(format_decimal(4.21, 1), format!("4{separator}2"));
+        assert_eq!(format_decimal(-0.5, 2), format!("-0{separator}50"));
+        assert_eq!(format_decimal(12.0, 0), "12");
+    }
+
+    #[test]
+    fn test_sanitize_note() {
+        assert_eq!(sanitize_note(""), None);
+        assert_eq!(sanitize_note(" \n\t "), None);
+        assert_eq!(
+            sanitize_note("  Invoice PDF\nfor\u{7}March "),
+            Some("Invoice PDF for March".to_owned())
+        );
+
+        let long = "a".repeat(globals::NOTE_MAX_CHARS + 10);
+        let note = sanitize_note(&long).unwrap();
+        assert_eq!(note.chars().count(), globals::NOTE_MAX_CHARS);
+        assert!(note.ends_with('…'));
+    }
+
+    #[test]
+    fn test_validate_rendezvous_url() {
+        assert!(validate_rendezvous_url("ws://relay.magic-wormhole.io:4000").is_some());
+        assert!(validate_rendezvous_url("wss://relay.example.org/v1").is_some());
+        assert!(validate_rendezvous_url("http://relay.example.org").is_none());
+        assert!(validate_rendezvous_url("tcp://relay.example.org:4000").is_none());
+        assert!(validate_rendezvous_url("ws:relay").is_none());
+        assert!(validate_rendezvous_url("relay.example.org").is_none());
+        assert!(validate_rendezvous_url("").is_none());
+    }
+
+    #[test]
+    fn test_validate_transit_url() {
+        assert!(validate_transit_url("tcp://transit.magic-wormhole.io:4001").is_some());
+        assert!(validate_transit_url("tls://transit.example.org:443").is_some());
+        assert!(validate_transit_url("tcp://transit.example.org").is_none());
+        assert!(validate_transit_url("ws://transit.example.org:4001").is_none());
+        assert!(validate_transit_url("http://transit.example.org:80").is_none());
+        assert!(validate_transit_url("").is_none());
+    }
+
+    #[test]
+    fn test_release_notes_to_text() {
+        assert_eq!(release_notes_to_text(""), "");
+        assert_eq!(
+            release_notes_to_text("<p>Fixes an issue with the transmit code</p>"),
+            "Fixes an issue with the transmit code"
+        );
+        assert_eq!(
+            release_notes_to_text(
+                "<p>Changes from 0.9.0:</p>\n<ul>\n  <li>Allow sending files via “Open with”</li>\n  \
+                 <li>Fish &amp; <em>chips</em></li>\n</ul>"
+            ),
+            "Changes from 0.9.0:\n\n• Allow sending files via “Open with”\n• Fish & chips"
+        );
+    }
+
+    #[test]
+    fn test_validate_download_url() {
+        assert!(validate_download_url("https://example.org/file.pdf").is_some());
+        assert!(validate_download_url(" http://example.org/file.pdf ").is_some());
+        assert!(validate_download_url("ftp://example.org/file.pdf").is_none());
+        assert!(validate_download_url("file:///etc/passwd").is_none());
+        assert!(validate_download_url("example.org/file.pdf").is_none());
+    }
+
+    #[test]
+    fn test_read_transmit_code() {
+        let input = b"\n  \n 5-guitarist-revenge \nignored\n";
+        assert_eq!(
+            read_transmit_code(&input[..]).unwrap().as_deref(),
+            Some("5-guitarist-revenge")
+        );
+
+        let input = b"wormhole-transfer:5-guitarist-revenge";
+        assert_eq!(
+            read_transmit_code(&input[..]).unwrap().as_deref(),
+            Some("wormhole-transfer:5-guitarist-revenge")
+        );
+
+        assert_eq!(read_transmit_code(&b" \n\n"[..]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_spell_word() {
+        assert_eq!(
+            spell_word("guitarist"),
+            "Golf Uniform India Tango Alfa Romeo India Sierra Tango"
+        );
+        assert_eq!(spell_word("Xray"), "X-ray Romeo Alfa Yankee");
+        assert_eq!(spell_word("a1"), "Alfa One");
+        assert_eq!(spell_word("ü"), "ü");
+        assert_eq!(spell_word(""), "");
+    }
+
+    #[test]
+    fn test_qr_code_with_fallback() {
+        let qr = qr_code_with_fallback("wormhole-transfer:4-hurricane", QrErrorCorrection::High);
+        assert_eq!(qr.error_correction_level(), qrcode::EcLevel::H);
+
+        // Too long for the highest level, which holds about 1.2 KB
+        let data = "a".repeat(2000);
+        let qr = qr_code_with_fallback(&data, QrErrorCorrection::High);
+        assert_eq!(qr.error_correction_level(), qrcode::EcLevel::L);
+    }
 }
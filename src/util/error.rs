@@ -1,7 +1,7 @@
+use crate::config::Config;
 use crate::gettext::gettextf;
 use crate::gettext::*;
 use crate::globals;
-use crate::ui::application::WarpApplication;
 use crate::ui::window::WarpApplicationWindow;
 use adw::prelude::*;
 use async_zip::error::ZipError;
@@ -12,7 +12,7 @@ use wormhole::ParsePasswordError;
 use wormhole::WormholeError;
 use wormhole::rendezvous::RendezvousError;
 use wormhole::transfer::TransferError;
-use wormhole::transit::RelayHintParseError;
+use wormhole::transit::{RelayHintParseError, TransitConnectError};
 
 #[derive(thiserror::Error, Debug)]
 pub struct UiError {
@@ -125,6 +125,11 @@ impl Display for AppError {
     }
 }
 
+/// wormhole-rs always sends these fixed messages when cancelling or rejecting, so we can't transmit
+/// a reason of our own. Other clients may send anything, which is shown as is.
+const PEER_CANCELED_MESSAGE: &str = "Task has been cancelled";
+const PEER_REJECTED_MESSAGE: &str = "transfer rejected";
+
 // Don't show more than one error dialog at the same time, it will get very annoying
 static ERROR_DIALOG_ALREADY_SHOWING: AtomicBool = AtomicBool::new(false);
 
@@ -151,6 +156,46 @@ impl AppError {
         }
     }
 
+    fn peer_error_message(&self) -> Option<&str> {
+        match self {
+            AppError::Transfer {
+                source: TransferError::PeerError(msg),
+            } => Some(msg),
+            _ => None,
+        }
+    }
+
+    /// Establishing or using the connection to the peer or relay failed
+    pub fn is_transit_error(&self) -> bool {
+        matches!(
+            self,
+            AppError::Transfer {
+                source: TransferError::Transit(_) | TransferError::TransitConnect(_),
+            }
+        )
+    }
+
+    /// No connection to the peer could be established at all, not even via a transit relay when
+    /// relays are allowed. Firewalls that block the port of the relay are a common cause.
+    pub fn is_transit_blocked_error(&self) -> bool {
+        matches!(
+            self,
+            AppError::Transfer {
+                source: TransferError::TransitConnect(TransitConnectError::Handshake),
+            }
+        )
+    }
+
+    /// The other side canceled the transfer
+    pub fn is_peer_canceled(&self) -> bool {
+        self.peer_error_message() == Some(PEER_CANCELED_MESSAGE)
+    }
+
+    /// The other side declined the offered file, which may well have been by accident
+    pub fn is_peer_rejected(&self) -> bool {
+        self.peer_error_message() == Some(PEER_REJECTED_MESSAGE)
+    }
+
     pub fn handle(self) {
         if self.is_user_canceled() {
             // Don't do anything here, the user canceled the operation
@@ -188,7 +233,7 @@ impl AppError {
 
     pub fn show_error_dialog(&self, window: &WarpApplicationWindow) {
         let msg1 = &gettext("An error occurred");
-        let msg2 = self.gettext_error();
+        let msg2 = self.gettext_error_for(&window.config());
 
         let res = ERROR_DIALOG_ALREADY_SHOWING.compare_exchange(
             false,
@@ -212,7 +257,16 @@ impl AppError {
         }
     }
 
-    fn gettext_error_wormhole(wormhole_error: &WormholeError) -> String {
+    /// Whether connecting to the rendezvous server failed because of the network, so trying again
+    /// might help. Messages from the server itself and failed key exchanges are final.
+    pub fn is_transient_wormhole_error(wormhole_error: &WormholeError) -> bool {
+        match wormhole_error {
+            WormholeError::ServerError(err) => !matches!(err, RendezvousError::Server(_)),
+            _ => false,
+        }
+    }
+
+    fn gettext_error_wormhole(wormhole_error: &WormholeError, config: &Config) -> String {
         match wormhole_error {
             WormholeError::ProtocolJson(_) | WormholeError::Protocol(_) => {
                 gettext("Corrupt or unexpected message received")
@@ -233,12 +287,7 @@ impl AppError {
                             &[msg],
                         )
                     }
-                } else if WarpApplication::default()
-                    .main_window()
-                    .config()
-                    .rendezvous_server_url
-                    .is_some()
-                {
+                } else if config.has_custom_rendezvous_server_url() {
                     gettext(
                         "Error connecting to the rendezvous server.\nYou have entered a custom rendezvous server URL in preferences. Please verify the URL is correct and the server is working.",
                     )
@@ -295,6 +344,28 @@ impl AppError {
         }
     }
 
+    fn gettext_error_glib(error: &glib::Error) -> String {
+        if let Some(kind) = error.kind::<gio::IOErrorEnum>() {
+            match kind {
+                gio::IOErrorEnum::NotFound => gettext("File / Directory not found"),
+                gio::IOErrorEnum::PermissionDenied => gettext("Permission denied"),
+                gio::IOErrorEnum::NetworkUnreachable | gio::IOErrorEnum::HostUnreachable => {
+                    gettext("The network is unreachable")
+                }
+                gio::IOErrorEnum::Cancelled => gettext("Canceled"),
+                _ => error.message().to_owned(),
+            }
+        } else if let Some(kind) = error.kind::<glib::FileError>() {
+            match kind {
+                glib::FileError::Noent => gettext("File / Directory not found"),
+                glib::FileError::Acces | glib::FileError::Perm => gettext("Permission denied"),
+                _ => error.message().to_owned(),
+            }
+        } else {
+            error.message().to_owned()
+        }
+    }
+
     #[cfg(target_os = "linux")]
     fn gettext_error_ashpd(error: &ashpd::Error) -> String {
         match error {
@@ -314,7 +385,15 @@ impl AppError {
         }
     }
 
+    /// The message for the user, assuming the default preferences. Errors of a transfer should use
+    /// [`Self::gettext_error_for`] with the config of its window instead.
     pub fn gettext_error(&self) -> String {
+        self.gettext_error_for(&Config::default())
+    }
+
+    /// The message for the user, pointing out the preferences in `config` that may have caused the
+    /// error. Pass the config of the window the error happened in.
+    pub fn gettext_error_for(&self, config: &Config) -> String {
         match self {
             AppError::Canceled => "canceled".to_owned(),
             AppError::Io { source } => Self::gettext_error_io(source),
@@ -333,24 +412,45 @@ impl AppError {
                     &[sent_size, &file_size],
                 ),
                 TransferError::PeerError(msg) => {
-                    if msg == "Task has been cancelled" {
+                    if msg == PEER_CANCELED_MESSAGE {
                         gettext("The other side has cancelled the transfer")
-                    } else if msg == "transfer rejected" {
+                    } else if msg == PEER_REJECTED_MESSAGE {
                         gettext("The other side has rejected the transfer")
                     } else {
                         gettextf("Something went wrong on the other side: {}", &[msg])
                     }
                 }
                 TransferError::UnsupportedOffer
-                | TransferError::ProtocolJson(_)
+                | TransferError::ProtocolUnexpectedMessage(_, _) => gettext(
+                    "The other side is using an incompatible or newer version of the transfer protocol.",
+                ),
+                TransferError::ProtocolJson(_)
                 | TransferError::ProtocolMsgpack(_)
-                | TransferError::Protocol(_)
-                | TransferError::ProtocolUnexpectedMessage(_, _) => {
-                    gettext("Corrupt or unexpected message received")
-                }
+                | TransferError::Protocol(_) => gettext("Corrupt or unexpected message received"),
                 TransferError::Wormhole(source) => {
                     log::error!("Wormhole error: {:?}", source);
-                    Self::gettext_error_wormhole(source)
+                    Self::gettext_error_wormhole(source, config)
+                }
+                TransferError::TransitConnect(_) if config.direct_connections_only_or_default() => {
+                    gettext(
+                        "No direct connection to the other side could be established. Relay servers are not used, because only direct connections are allowed in preferences.",
+                    )
+                }
+                TransferError::TransitConnect(TransitConnectError::Handshake) => {
+                    let ports = config.transit_relay_ports();
+                    let ports_str = ports
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    ngettextf(
+                        // Translators: Argument is a list of port numbers, like 4001
+                        "No file transfer connection could be established, not even via the transit relay. Firewalls in company or school networks often block the port {} it uses. Please try another network, or enter a transit server with an allowed port in preferences.",
+                        "No file transfer connection could be established, not even via the transit relays. Firewalls in company or school networks often block the ports {} they use. Please try another network, or enter a transit server with an allowed port in preferences.",
+                        ports.len() as u32,
+                        &[&ports_str],
+                    )
                 }
                 TransferError::TransitConnect(_) => {
                     gettext("Error while establishing file transfer connection")
@@ -359,7 +459,7 @@ impl AppError {
                 TransferError::IO(source) => Self::gettext_error_io(source),
                 _ => gettext("An unknown error occurred"),
             },
-            AppError::Wormhole { source } => Self::gettext_error_wormhole(source),
+            AppError::Wormhole { source } => Self::gettext_error_wormhole(source, config),
             AppError::WormholeRelayHintParseError { source } => source.to_string(),
             AppError::WormholeParseCodeError { source } => {
                 Self::gettext_error_wormhole_parse_code(source)
@@ -376,7 +476,7 @@ impl AppError {
             AppError::Panic { .. } => gettext(
                 "An unexpected error occurred. Please report an issue with the error message.",
             ),
-            AppError::Glib { source } => source.to_string(),
+            AppError::Glib { source } => Self::gettext_error_glib(source),
             #[cfg(target_os = "linux")]
             AppError::Ashpd { source } => Self::gettext_error_ashpd(source),
         }
@@ -422,3 +522,91 @@ pub fn error_for_panic() -> AppError {
 
     AppError::Panic { msg }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{AppError, Config};
+    use wormhole::transfer::TransferError;
+    use wormhole::transit::TransitConnectError;
+
+    fn peer_error(msg: &str) -> AppError {
+        AppError::Transfer {
+            source: TransferError::PeerError(msg.to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_transit_blocked() {
+        let transit_error = |source| AppError::Transfer {
+            source: TransferError::TransitConnect(source),
+        };
+
+        assert!(transit_error(TransitConnectError::Handshake).is_transit_blocked_error());
+        assert!(transit_error(TransitConnectError::Handshake).is_transit_error());
+        assert!(
+            !transit_error(TransitConnectError::IO(
+                std::io::ErrorKind::BrokenPipe.into()
+            ))
+            .is_transit_blocked_error()
+        );
+        assert!(!peer_error("transfer rejected").is_transit_blocked_error());
+
+        // The message lists the ports of the relays in use, unless relays are disabled
+        let mut config = Config::default();
+        let message = transit_error(TransitConnectError::Handshake).gettext_error_for(&config);
+        assert!(message.contains("4001"));
+        config.direct_connections_only = Some(true);
+        let message = transit_error(TransitConnectError::Handshake).gettext_error_for(&config);
+        assert!(!message.contains("4001"));
+    }
+
+    #[test]
+    fn test_peer_rejected() {
+        assert!(peer_error("transfer rejected").is_peer_rejected());
+        assert!(!peer_error("transfer rejected").is_peer_canceled());
+        assert!(peer_error("Task has been cancelled").is_peer_canceled());
+        assert!(!peer_error("Task has been cancelled").is_peer_rejected());
+        assert!(!peer_error("out of space").is_peer_rejected());
+        assert!(!AppError::Canceled.is_peer_rejected());
+    }
+
+    fn glib_error_text(error: glib::Error) -> String {
+        AppError::from(error).gettext_error()
+    }
+
+    #[test]
+    fn test_gettext_error_glib() {
+        assert_eq!(
+            glib_error_text(glib::Error::new(gio::IOErrorEnum::NotFound, "g_file_open")),
+            "File / Directory not found"
+        );
+        assert_eq!(
+            glib_error_text(glib::Error::new(glib::FileError::Acces, "open failed")),
+            "Permission denied"
+        );
+        assert_eq!(
+            glib_error_text(glib::Error::new(
+                gio::IOErrorEnum::NetworkUnreachable,
+                "connect failed"
+            )),
+            "The network is unreachable"
+        );
+        assert_eq!(
+            glib_error_text(glib::Error::new(gio::IOErrorEnum::Cancelled, "cancelled")),
+            "Canceled"
+        );
+
+        // Unknown errors keep their message
+        assert_eq!(
+            glib_error_text(glib::Error::new(gio::IOErrorEnum::Busy, "Device is busy")),
+            "Device is busy"
+        );
+        assert_eq!(
+            glib_error_text(glib::Error::new(
+                glib::KeyFileError::NotFound,
+                "No such key"
+            )),
+            "No such key"
+        );
+    }
+}
@@ -1,5 +1,4 @@
 use crate::error;
-use crate::ui::application::WarpApplication;
 use crate::util::error::AppError;
 use futures::{FutureExt, pin_mut, select};
 use std::future::Future;
@@ -18,30 +17,20 @@ where
         .map_err(Into::into)
 }
 
-pub fn invoke_main_with_app<F>(func: F)
+/// Invoke a function on the main thread with the object behind a thread safe weak reference
+///
+/// The function is not called when the object was destroyed in the meantime.
+pub fn invoke_main_with<O, F>(object: &glib::SendWeakRef<O>, func: F)
 where
-    F: FnOnce(WarpApplication) + 'static + Send,
+    O: glib::prelude::ObjectType,
+    F: FnOnce(O) + 'static + Send,
 {
-    glib::MainContext::default().invoke(|| func(WarpApplication::default()));
-}
-
-pub async fn block_on_main_with_app_async<R>(
-    func: impl FnOnce(WarpApplication) -> R + 'static + Send,
-) -> R
-where
-    R: 'static + Send,
-{
-    let (sender, receiver) = async_channel::bounded(1);
+    let object = object.clone();
     glib::MainContext::default().invoke(move || {
-        sender
-            .send_blocking(func(WarpApplication::default()))
-            .unwrap();
+        if let Some(object) = object.upgrade() {
+            func(object);
+        }
     });
-
-    receiver
-        .recv()
-        .await
-        .expect("async_channel is supposed to work")
 }
 
 /// Run a future from main thread with error handling
@@ -0,0 +1,140 @@
+//! Holding back the file data of a transfer until the user confirmed its relayed connection
+
+use futures::channel::oneshot;
+use futures::{AsyncRead, AsyncWrite};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, ready};
+
+#[derive(Debug, Default)]
+enum State {
+    /// Nothing to confirm, the data passes
+    #[default]
+    Open,
+    /// Waiting for the answer of the user
+    Asking(oneshot::Receiver<bool>),
+    /// The user refused the relayed connection
+    Refused,
+}
+
+/// Whether the file data of a transfer may pass
+///
+/// The transit callback is called once the connection is established, before any file data is
+/// sent or received. If it asks for confirmation, reading or writing the file waits for the
+/// answer, which holds the whole transfer without blocking the thread it runs on. A refused
+/// connection fails the transfer before any file data went through the relay.
+#[derive(Clone, Debug, Default)]
+pub struct RelayGate(Arc<Mutex<State>>);
+
+impl RelayGate {
+    /// Holds the file data until the returned sender gets an answer. Dropping the sender refuses
+    /// the connection.
+    pub fn ask(&self) -> oneshot::Sender<bool> {
+        let (sender, receiver) = oneshot::channel();
+        *self.0.lock().unwrap() = State::Asking(receiver);
+        sender
+    }
+
+    /// The file of a transfer, passing its data only once the connection was confirmed
+    pub fn wrap<F>(self, file: F) -> GatedFile<F> {
+        GatedFile { file, gate: self }
+    }
+
+    fn poll_open(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut state = self.0.lock().unwrap();
+        if let State::Asking(receiver) = &mut *state {
+            *state = match ready!(Pin::new(receiver).poll(cx)) {
+                Ok(true) => State::Open,
+                Ok(false) | Err(_) => State::Refused,
+            };
+        }
+
+        match *state {
+            State::Refused => {
+                Poll::Ready(Err(io::Error::other("The relayed connection was refused")))
+            }
+            State::Open | State::Asking(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+/// A file whose data only passes the [`RelayGate`] it was wrapped with
+#[derive(Debug)]
+pub struct GatedFile<F> {
+    file: F,
+    gate: RelayGate,
+}
+
+impl<F> GatedFile<F> {
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+}
+
+impl<F: AsyncRead + Unpin> AsyncRead for GatedFile<F> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.gate.poll_open(cx))?;
+        Pin::new(&mut self.file).poll_read(cx, buf)
+    }
+}
+
+impl<F: AsyncWrite + Unpin> AsyncWrite for GatedFile<F> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.gate.poll_open(cx))?;
+        Pin::new(&mut self.file).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.file).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.file).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RelayGate;
+    use futures::AsyncReadExt;
+    use futures::io::Cursor;
+    use std::pin::pin;
+
+    #[test]
+    fn test_relay_gate() {
+        let gate = RelayGate::default();
+        let mut file = gate.clone().wrap(Cursor::new(b"content".to_vec()));
+        let mut buf = [0; 2];
+
+        smol::block_on(async {
+            // Without a relayed connection, nothing is held back
+            file.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"co");
+
+            let answer = gate.ask();
+            {
+                let mut read = pin!(file.read_exact(&mut buf));
+                assert!(futures::poll!(read.as_mut()).is_pending());
+                answer.send(true).unwrap();
+                read.await.unwrap();
+            }
+            assert_eq!(&buf, b"nt");
+
+            // Nothing is read after refusing, neither after the window closed without an answer
+            gate.ask().send(false).unwrap();
+            assert!(file.read(&mut buf).await.is_err());
+            drop(gate.ask());
+            assert!(file.read(&mut buf).await.is_err());
+        });
+    }
+}
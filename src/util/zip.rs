@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
 use async_zip::{Compression, ZipEntryBuilder, base::write::ZipFileWriter};
@@ -5,19 +6,77 @@ use futures::{AsyncReadExt, AsyncWrite, AsyncWriteExt, TryStreamExt};
 use smol::fs::File;
 
 use super::error::{AppError, UiError};
+use crate::config::SymlinkPolicy;
+
+/// Files larger than this are copied into the archive in chunks instead of being mapped into memory
+const STREAM_THRESHOLD: u64 = 1024 * 1024 * 1024;
+
+/// Size of the chunks that are read from a file that is streamed into the archive
+const STREAM_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// File mode of a symbolic link (`S_IFLNK | 0o777`), marking a stored link in the archive
+const SYMLINK_MODE: u16 = 0o120_777;
+
+/// A file system entry found when walking a folder for an archive
+#[derive(Debug, PartialEq, Eq)]
+enum WalkEntry {
+    /// A regular file, or a link to one when following links
+    File(PathBuf),
+    /// A symbolic link that is stored as a link
+    Symlink(PathBuf),
+    /// Anything that can't be added, like sockets, FIFOs, device files or broken links
+    Skipped(PathBuf),
+    /// A file or folder whose name starts with a dot, when hidden files are excluded
+    Hidden(PathBuf),
+}
+
+/// Progress of creating an archive, passed to the callback whenever it changes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ZipProgress {
+    /// Number of files added so far
+    pub files: usize,
+    /// Size of the files added so far
+    pub size: u64,
+    /// Estimated size of the archive, zero until all folders were walked
+    pub estimated_total: u64,
+    /// Number of entries that can't be added, see [`WalkEntry::Skipped`]
+    pub skipped: usize,
+    /// Number of hidden entries that were excluded
+    pub hidden: usize,
+}
+
+/// Which entries of folders are added to an archive
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ZipOptions {
+    pub symlink_policy: SymlinkPolicy,
+    /// Whether files and folders starting with a dot are added
+    pub include_hidden: bool,
+}
+
+impl WalkEntry {
+    fn path(&self) -> &Path {
+        match self {
+            Self::File(path) | Self::Symlink(path) | Self::Skipped(path) | Self::Hidden(path) => {
+                path
+            }
+        }
+    }
+}
 
 // See https://github.com/Majored/rs-async-zip/blob/main/examples/cli_compress.rs
-async fn handle_directory<W: AsyncWrite + Unpin + Send + 'static, F: Fn(usize, usize)>(
+async fn handle_directory<W: AsyncWrite + Unpin + Send + 'static, F: Fn(ZipProgress)>(
     input_path: &Path,
     mut writer: ZipFileWriter<W>,
+    options: ZipOptions,
     callback: F,
 ) -> Result<(), AppError> {
-    let mut num_files = 0;
-    let mut size = 0;
+    let mut progress = ZipProgress::default();
 
-    callback(0, 0);
+    callback(progress);
 
-    let entries = walk_dir(input_path.into()).await?;
+    let entries = walk_dir(input_path.into(), options).await?;
+    progress.estimated_total = estimated_size(&entries).await;
+    callback(progress);
     let input_dir_str = input_path
         .as_os_str()
         .to_str()
@@ -26,8 +85,22 @@ async fn handle_directory<W: AsyncWrite + Unpin + Send + 'static, F: Fn(usize, u
             input_path.display()
         )))?;
 
-    for entry_path_buf in entries {
-        let entry_path = entry_path_buf.as_path();
+    for entry in entries {
+        match entry {
+            WalkEntry::Skipped(_) => {
+                progress.skipped += 1;
+                callback(progress);
+                continue;
+            }
+            WalkEntry::Hidden(_) => {
+                progress.hidden += 1;
+                callback(progress);
+                continue;
+            }
+            WalkEntry::File(_) | WalkEntry::Symlink(_) => {}
+        }
+
+        let entry_path = entry.path();
         let entry_str = entry_path
             .as_os_str()
             .to_str()
@@ -44,13 +117,13 @@ async fn handle_directory<W: AsyncWrite + Unpin + Send + 'static, F: Fn(usize, u
         }
 
         let entry_str = &entry_str[input_dir_str.len() + 1..];
-        let res = write_entry(entry_str, entry_path, writer).await?;
-        size += res.0;
+        let res = write_walk_entry(entry_str, &entry, writer).await?;
+        progress.size += res.0;
         writer = res.1;
 
-        num_files += 1;
+        progress.files += 1;
 
-        callback(num_files, size);
+        callback(progress);
     }
 
     let mut inner = writer.close().await?;
@@ -60,13 +133,25 @@ async fn handle_directory<W: AsyncWrite + Unpin + Send + 'static, F: Fn(usize, u
     Ok(())
 }
 
+/// Adds a file to the archive. Returns the file size.
+///
+/// Entries over 4 GiB get Zip64 records, which `write_entry_whole` adds on its own once a size
+/// doesn't fit into the 32 bit fields of the classic format. Streamed entries always have them.
 async fn write_entry<W: AsyncWrite + Unpin + Send + 'static>(
     filename: &str,
     input_path: &Path,
     mut writer: ZipFileWriter<W>,
-) -> Result<(usize, ZipFileWriter<W>), AppError> {
+) -> Result<(u64, ZipFileWriter<W>), AppError> {
     let mut input_file = File::open(input_path).await?;
-    let input_file_size = input_file.metadata().await?.len() as usize;
+    let input_file_size = input_file.metadata().await?.len();
+
+    // Very large files would take up a lot of memory when mapped, and can't be mapped at all on
+    // 32 bit systems
+    if input_file_size > STREAM_THRESHOLD || usize::try_from(input_file_size).is_err() {
+        log::trace!("Adding file {filename} to zip file in chunks");
+        write_entry_stream(filename, input_file, &mut writer).await?;
+        return Ok((input_file_size, writer));
+    }
 
     // We need to do memory mapped I/O for big files. This means extra work, so we only do it for files > 10 MiB
     if input_file_size > 10 * 1024 * 1024 {
@@ -90,7 +175,7 @@ async fn write_entry<W: AsyncWrite + Unpin + Send + 'static>(
 
         Ok((input_file_size, result?))
     } else {
-        let mut buffer = Vec::with_capacity(input_file_size);
+        let mut buffer = Vec::with_capacity(input_file_size as usize);
         input_file.read_to_end(&mut buffer).await?;
 
         let builder = ZipEntryBuilder::new(filename.into(), Compression::Stored);
@@ -99,30 +184,148 @@ async fn write_entry<W: AsyncWrite + Unpin + Send + 'static>(
     }
 }
 
-async fn walk_dir(dir: PathBuf) -> Result<Vec<PathBuf>, AppError> {
+/// Copies a file into the archive in chunks, so only one chunk is in memory at a time. The size
+/// and checksum are written after the data.
+async fn write_entry_stream<W: AsyncWrite + Unpin>(
+    filename: &str,
+    input_file: File,
+    writer: &mut ZipFileWriter<W>,
+) -> Result<(), AppError> {
+    let builder = ZipEntryBuilder::new(filename.into(), Compression::Stored);
+    let mut entry_writer = writer.write_entry_stream(builder).await?;
+
+    let reader = futures::io::BufReader::with_capacity(STREAM_CHUNK_SIZE, input_file);
+    futures::io::copy_buf(reader, &mut entry_writer).await?;
+    entry_writer.close().await?;
+
+    Ok(())
+}
+
+/// Adds a file or stored symbolic link to the archive
+async fn write_walk_entry<W: AsyncWrite + Unpin + Send + 'static>(
+    filename: &str,
+    entry: &WalkEntry,
+    mut writer: ZipFileWriter<W>,
+) -> Result<(u64, ZipFileWriter<W>), AppError> {
+    match entry {
+        WalkEntry::File(path) => write_entry(filename, path, writer).await,
+        WalkEntry::Symlink(path) => {
+            // Like the zip command line tool, the link target is stored as the entry content
+            let target = smol::fs::read_link(path).await?;
+            let target = target.as_os_str().as_encoded_bytes();
+            let builder = ZipEntryBuilder::new(filename.into(), Compression::Stored)
+                .unix_permissions(SYMLINK_MODE);
+            writer.write_entry_whole(builder, target).await?;
+            Ok((target.len() as u64, writer))
+        }
+        WalkEntry::Skipped(path) | WalkEntry::Hidden(path) => {
+            unreachable!("Excluded entry '{}' can't be written", path.display())
+        }
+    }
+}
+
+/// Estimates the size of the archive from the sizes of the files and links that will be added,
+/// without the archive headers. Entries are stored uncompressed, so the archive will be about as
+/// large. If entries get compressed, this is an upper bound.
+async fn estimated_size(entries: impl IntoIterator<Item = &WalkEntry>) -> u64 {
+    let mut total = 0;
+    for entry in entries {
+        total += match entry {
+            WalkEntry::File(path) => smol::fs::metadata(path)
+                .await
+                .map_or(0, |metadata| metadata.len()),
+            WalkEntry::Symlink(path) => smol::fs::read_link(path)
+                .await
+                .map_or(0, |target| target.as_os_str().len() as u64),
+            WalkEntry::Skipped(_) | WalkEntry::Hidden(_) => 0,
+        };
+    }
+
+    total
+}
+
+/// Lists all files in a folder and its subfolders
+///
+/// Only regular files are added. Symbolic links are either stored as links, or followed to the
+/// file or folder they point to, depending on the symlink policy of `options`. Folders that were already visited
+/// are not followed again, to not loop endlessly on links to parent folders. Everything else is
+/// returned as [`WalkEntry::Skipped`], so the user can be told what wasn't included.
+///
+/// Unless hidden files are included, files and folders starting with a dot are returned as
+/// [`WalkEntry::Hidden`] and hidden folders are not entered.
+async fn walk_dir(dir: PathBuf, options: ZipOptions) -> Result<Vec<WalkEntry>, AppError> {
+    let mut visited_dirs = HashSet::new();
+    if let Ok(canonical_dir) = smol::fs::canonicalize(&dir).await {
+        visited_dirs.insert(canonical_dir);
+    }
+
     let mut dirs = vec![dir];
-    let mut files = vec![];
+    let mut entries = vec![];
 
     while !dirs.is_empty() {
         let mut dir_iter = smol::fs::read_dir(dirs.remove(0)).await?;
 
         while let Some(entry) = dir_iter.try_next().await? {
             let entry_path_buf = entry.path();
+            if !options.include_hidden && entry.file_name().as_encoded_bytes().starts_with(b".") {
+                log::debug!("Excluding hidden '{}'", entry_path_buf.display());
+                entries.push(WalkEntry::Hidden(entry_path_buf));
+                continue;
+            }
+
+            let mut file_type = entry.file_type().await?;
+
+            if file_type.is_symlink() {
+                if options.symlink_policy == SymlinkPolicy::Store {
+                    entries.push(WalkEntry::Symlink(entry_path_buf));
+                    continue;
+                }
 
-            if entry_path_buf.is_dir() {
-                dirs.push(entry_path_buf);
+                match smol::fs::metadata(&entry_path_buf).await {
+                    Ok(metadata) => file_type = metadata.file_type(),
+                    Err(err) => {
+                        log::warn!(
+                            "Skipping broken link '{}': {}",
+                            entry_path_buf.display(),
+                            err
+                        );
+                        entries.push(WalkEntry::Skipped(entry_path_buf));
+                        continue;
+                    }
+                }
+            }
+
+            if file_type.is_dir() {
+                let canonical_dir = smol::fs::canonicalize(&entry_path_buf).await?;
+                if visited_dirs.insert(canonical_dir) {
+                    dirs.push(entry_path_buf);
+                } else {
+                    log::warn!(
+                        "Skipping already visited folder '{}'",
+                        entry_path_buf.display()
+                    );
+                    entries.push(WalkEntry::Skipped(entry_path_buf));
+                }
+            } else if file_type.is_file() {
+                entries.push(WalkEntry::File(entry_path_buf));
             } else {
-                files.push(entry_path_buf);
+                log::warn!(
+                    "Skipping '{}', which is not a regular file",
+                    entry_path_buf.display()
+                );
+                entries.push(WalkEntry::Skipped(entry_path_buf));
             }
         }
     }
 
-    Ok(files)
+    Ok(entries)
 }
 
-pub async fn zip_dir<W: AsyncWrite + Unpin + Send + 'static, F: Fn(usize, usize)>(
+/// Creates an archive of a folder. The callback receives the progress whenever it changes.
+pub async fn zip_dir<W: AsyncWrite + Unpin + Send + 'static, F: Fn(ZipProgress)>(
     dir: &Path,
     writer: W,
+    options: ZipOptions,
     callback: F,
 ) -> Result<(), AppError> {
     let output_writer = ZipFileWriter::new(writer);
@@ -131,7 +334,427 @@ pub async fn zip_dir<W: AsyncWrite + Unpin + Send + 'static, F: Fn(usize, usize)
         return Err(UiError::new(&"Directory expected").into());
     }
 
-    handle_directory(dir, output_writer, callback).await?;
+    handle_directory(dir, output_writer, options, callback).await?;
 
     Ok(())
 }
+
+/// Makes an archive entry name unique by appending a number to its stem, like `file (1).txt`
+fn unique_entry_name(name: &str, used_names: &mut HashSet<String>) -> String {
+    let mut unique_name = name.to_owned();
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+        _ => (name, None),
+    };
+
+    let mut i = 1;
+    while used_names.contains(&unique_name) {
+        unique_name = match ext {
+            Some(ext) => format!("{stem} ({i}).{ext}"),
+            None => format!("{stem} ({i})"),
+        };
+        i += 1;
+    }
+
+    used_names.insert(unique_name.clone());
+    unique_name
+}
+
+/// Creates an archive containing all the given files and directories at its root
+pub async fn zip_files<W: AsyncWrite + Unpin + Send + 'static, F: Fn(ZipProgress)>(
+    paths: &[PathBuf],
+    writer: W,
+    options: ZipOptions,
+    callback: F,
+) -> Result<(), AppError> {
+    let mut writer = ZipFileWriter::new(writer);
+    let mut used_names = HashSet::new();
+    let mut progress = ZipProgress::default();
+    let mut all_entries = Vec::new();
+
+    callback(progress);
+
+    // All folders are walked first, so the size of the archive can be estimated from the start
+    for path in paths {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or(UiError::new(&format!(
+                "File path \"{}\" not valid UTF-8.",
+                path.display()
+            )))?;
+        let name = unique_entry_name(name, &mut used_names);
+
+        let entries = if path.is_dir() {
+            let (excluded_entries, entries): (Vec<_>, Vec<_>) = walk_dir(path.clone(), options)
+                .await?
+                .into_iter()
+                .partition(|entry| matches!(entry, WalkEntry::Skipped(_) | WalkEntry::Hidden(_)));
+
+            if !excluded_entries.is_empty() {
+                for entry in &excluded_entries {
+                    if matches!(entry, WalkEntry::Hidden(_)) {
+                        progress.hidden += 1;
+                    } else {
+                        progress.skipped += 1;
+                    }
+                }
+            }
+
+            entries
+                .into_iter()
+                .map(|entry| {
+                    let entry_path = entry.path();
+                    let relative_path = entry_path
+                        .strip_prefix(path)
+                        .ok()
+                        .and_then(|relative_path| relative_path.to_str())
+                        .ok_or(UiError::new(&format!(
+                            "Directory file path \"{}\" not valid UTF-8.",
+                            entry_path.display()
+                        )))?
+                        .to_owned();
+                    Ok((format!("{name}/{relative_path}"), entry))
+                })
+                .collect::<Result<Vec<_>, AppError>>()?
+        } else {
+            vec![(name, WalkEntry::File(path.clone()))]
+        };
+
+        all_entries.extend(entries);
+    }
+
+    progress.estimated_total = estimated_size(all_entries.iter().map(|(_, entry)| entry)).await;
+    callback(progress);
+
+    for (entry_name, entry) in all_entries {
+        let res = write_walk_entry(&entry_name, &entry, writer).await?;
+        progress.size += res.0;
+        writer = res.1;
+
+        progress.files += 1;
+
+        callback(progress);
+    }
+
+    let mut inner = writer.close().await?;
+    inner.flush().await?;
+    inner.close().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+
+    /// Number of bytes at the end of an archive kept by [`TailWriter`]
+    const TAIL_LEN: usize = 1024;
+
+    /// Counts the written bytes and keeps only the end of the data, where the central directory is
+    #[derive(Clone, Default)]
+    struct TailWriter(Arc<Mutex<(u64, Vec<u8>)>>);
+
+    impl AsyncWrite for TailWriter {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            let mut inner = self.0.lock().unwrap();
+            inner.0 += buf.len() as u64;
+            inner.1.extend_from_slice(buf);
+            let excess = inner.1.len().saturating_sub(TAIL_LEN);
+            inner.1.drain(..excess);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_unique_entry_name() {
+        let mut used_names = HashSet::new();
+        assert_eq!(unique_entry_name("file.txt", &mut used_names), "file.txt");
+        assert_eq!(
+            unique_entry_name("file.txt", &mut used_names),
+            "file (1).txt"
+        );
+        assert_eq!(
+            unique_entry_name("file.txt", &mut used_names),
+            "file (2).txt"
+        );
+        assert_eq!(unique_entry_name("folder", &mut used_names), "folder");
+        assert_eq!(unique_entry_name("folder", &mut used_names), "folder (1)");
+        assert_eq!(unique_entry_name(".hidden", &mut used_names), ".hidden");
+        assert_eq!(unique_entry_name(".hidden", &mut used_names), ".hidden (1)");
+    }
+
+    /// Keeps all written data and the size of the largest single write
+    #[derive(Clone, Default)]
+    struct ChunkWriter(Arc<Mutex<(usize, Vec<u8>)>>);
+
+    impl AsyncWrite for ChunkWriter {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            let mut inner = self.0.lock().unwrap();
+            inner.0 = inner.0.max(buf.len());
+            inner.1.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_write_entry_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.bin");
+        let content: Vec<u8> = (0..STREAM_CHUNK_SIZE * 3 + 17)
+            .map(|byte| (byte % 251) as u8)
+            .collect();
+        std::fs::write(&path, &content).unwrap();
+
+        let output = ChunkWriter::default();
+        smol::block_on(async {
+            let mut writer = ZipFileWriter::new(output.clone());
+            let file = File::open(&path).await.unwrap();
+            write_entry_stream("large.bin", file, &mut writer)
+                .await
+                .unwrap();
+            writer.close().await.unwrap();
+        });
+
+        let (largest_write, archive) = &*output.0.lock().unwrap();
+        // The file was never written in one piece
+        assert!(*largest_write <= STREAM_CHUNK_SIZE);
+
+        // Stored entries contain the file unchanged after the local file header
+        let name_len = usize::from(u16::from_le_bytes([archive[26], archive[27]]));
+        let extra_len = usize::from(u16::from_le_bytes([archive[28], archive[29]]));
+        let data_start = 30 + name_len + extra_len;
+        assert_eq!(&archive[30..30 + name_len], b"large.bin");
+        assert_eq!(&archive[data_start..data_start + content.len()], content);
+    }
+
+    #[test]
+    #[ignore = "Reads and checksums more than 4 GiB of data"]
+    fn test_zip_dir_large_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let size = u64::from(u32::MAX) + 1024;
+        // A sparse file, which doesn't take up any disk space
+        let file = std::fs::File::create(dir.path().join("large.bin")).unwrap();
+        file.set_len(size).unwrap();
+        std::fs::write(dir.path().join("small.txt"), "content").unwrap();
+
+        let writer = TailWriter::default();
+        let reported_size = Arc::new(Mutex::new(0));
+        let callback_size = reported_size.clone();
+        smol::block_on(zip_dir(
+            dir.path(),
+            writer.clone(),
+            ZipOptions {
+                symlink_policy: SymlinkPolicy::Follow,
+                include_hidden: true,
+            },
+            move |progress| *callback_size.lock().unwrap() = progress.size,
+        ))
+        .unwrap();
+
+        assert_eq!(*reported_size.lock().unwrap(), size + 7);
+
+        let (len, tail) = &*writer.0.lock().unwrap();
+        assert!(*len > size);
+        // Signature of the Zip64 end of central directory record
+        assert!(
+            tail.windows(4)
+                .any(|bytes| bytes == [0x50, 0x4b, 0x06, 0x06])
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_dir_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        let link = dir.path().join("link.txt");
+        let broken_link = dir.path().join("broken.txt");
+        std::fs::write(&file, "content").unwrap();
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("missing.txt"), &broken_link).unwrap();
+        // A link to the parent folder must not be followed endlessly
+        let parent_link = dir.path().join("parent");
+        std::os::unix::fs::symlink(dir.path(), &parent_link).unwrap();
+
+        let sorted = |mut entries: Vec<WalkEntry>| {
+            entries.sort_by(|a, b| a.path().cmp(b.path()));
+            entries
+        };
+
+        let entries = smol::block_on(walk_dir(
+            dir.path().into(),
+            ZipOptions {
+                symlink_policy: SymlinkPolicy::Follow,
+                include_hidden: true,
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            sorted(entries),
+            vec![
+                WalkEntry::Skipped(broken_link.clone()),
+                WalkEntry::File(file.clone()),
+                WalkEntry::File(link.clone()),
+                WalkEntry::Skipped(parent_link.clone()),
+            ]
+        );
+
+        let entries = smol::block_on(walk_dir(
+            dir.path().into(),
+            ZipOptions {
+                symlink_policy: SymlinkPolicy::Store,
+                include_hidden: true,
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            sorted(entries),
+            vec![
+                WalkEntry::Symlink(broken_link),
+                WalkEntry::File(file),
+                WalkEntry::Symlink(link),
+                WalkEntry::Symlink(parent_link),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_walk_dir_hidden() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        let hidden_file = dir.path().join(".hidden");
+        let hidden_dir = dir.path().join(".git");
+        std::fs::write(&file, "content").unwrap();
+        std::fs::write(&hidden_file, "content").unwrap();
+        std::fs::create_dir(&hidden_dir).unwrap();
+        std::fs::write(hidden_dir.join("config"), "content").unwrap();
+
+        let sorted = |mut entries: Vec<WalkEntry>| {
+            entries.sort_by(|a, b| a.path().cmp(b.path()));
+            entries
+        };
+
+        let entries = smol::block_on(walk_dir(
+            dir.path().into(),
+            ZipOptions {
+                symlink_policy: SymlinkPolicy::Follow,
+                include_hidden: true,
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            sorted(entries),
+            vec![
+                WalkEntry::File(hidden_dir.join("config")),
+                WalkEntry::File(hidden_file.clone()),
+                WalkEntry::File(file.clone()),
+            ]
+        );
+
+        // Hidden folders are not entered at all
+        let entries = smol::block_on(walk_dir(
+            dir.path().into(),
+            ZipOptions {
+                symlink_policy: SymlinkPolicy::Follow,
+                include_hidden: false,
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            sorted(entries),
+            vec![
+                WalkEntry::Hidden(hidden_dir),
+                WalkEntry::Hidden(hidden_file),
+                WalkEntry::File(file.clone()),
+            ]
+        );
+
+        let reported = Arc::new(Mutex::new((0, 0)));
+        let callback_reported = reported.clone();
+        smol::block_on(zip_dir(
+            dir.path(),
+            TailWriter::default(),
+            ZipOptions {
+                symlink_policy: SymlinkPolicy::Follow,
+                include_hidden: false,
+            },
+            move |progress| {
+                *callback_reported.lock().unwrap() = (progress.files, progress.hidden);
+            },
+        ))
+        .unwrap();
+        assert_eq!(*reported.lock().unwrap(), (1, 2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_zip_files_estimated_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        let folder = dir.path().join("folder");
+        std::fs::write(&file, "content").unwrap();
+        std::fs::create_dir(&folder).unwrap();
+        std::fs::write(folder.join("data.bin"), [0; 100]).unwrap();
+        std::os::unix::fs::symlink("../file.txt", folder.join("link")).unwrap();
+
+        // The estimate is known before the first file is added and doesn't change afterwards
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let callback_reported = reported.clone();
+        smol::block_on(zip_files(
+            &[file, folder],
+            TailWriter::default(),
+            ZipOptions {
+                symlink_policy: SymlinkPolicy::Store,
+                include_hidden: true,
+            },
+            move |progress| callback_reported.lock().unwrap().push(progress),
+        ))
+        .unwrap();
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported[0], ZipProgress::default());
+        assert!(
+            reported[1..]
+                .iter()
+                .all(|progress| progress.estimated_total == 118)
+        );
+        assert_eq!(
+            reported.last(),
+            Some(&ZipProgress {
+                files: 3,
+                size: 118,
+                estimated_total: 118,
+                skipped: 0,
+                hidden: 0,
+            })
+        );
+    }
+}